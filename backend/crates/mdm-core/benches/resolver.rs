@@ -0,0 +1,260 @@
+//! Fuzzy resolution throughput with trigram blocking.
+//!
+//! Run with `cargo bench -p mdm-core`. `blocked/500000` resolves typo'd
+//! queries against 500k synthetic contractor names; `brute_force/20000` scores
+//! every name with Jaro-Winkler, as the resolver did before blocking, on a
+//! corpus small enough to finish. The corpus is mostly natural persons built
+//! from common first names and surnames, so many names share trigrams.
+//!
+//! Reference run (release, one core): blocked/20000 ~2,200 queries/s,
+//! brute_force/20000 ~29 queries/s, blocked/500000 ~110 queries/s. At 500k
+//! names a lookup is dominated by walking the trigram postings and scoring,
+//! not by resetting the per-name counters.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mdm_core::cleaner::{DataCleaner, StandardCleaner};
+use mdm_core::domain::Contractor;
+use mdm_core::resolver::{EntityResolver, MatchResult};
+use std::hint::black_box;
+use strsim::jaro_winkler;
+
+const QUERIES: usize = 1_000;
+
+/// Deterministic generator, so every run sees the same corpus
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % bound as u64) as usize
+    }
+}
+
+const FIRST: [&str; 40] = [
+    "JUAN",
+    "CARLOS",
+    "JOSE",
+    "LUIS",
+    "ANDRES",
+    "JORGE",
+    "MIGUEL",
+    "DIEGO",
+    "DAVID",
+    "JAVIER",
+    "OSCAR",
+    "CAMILO",
+    "FELIPE",
+    "MAURICIO",
+    "ALEJANDRO",
+    "RICARDO",
+    "HERNAN",
+    "EDWIN",
+    "JHON",
+    "WILLIAM",
+    "MARIA",
+    "ANA",
+    "LUZ",
+    "CLAUDIA",
+    "SANDRA",
+    "PAOLA",
+    "DIANA",
+    "ANDREA",
+    "CAROLINA",
+    "LAURA",
+    "MARTHA",
+    "GLORIA",
+    "NATALIA",
+    "ADRIANA",
+    "MONICA",
+    "JULIANA",
+    "ANGELA",
+    "LILIANA",
+    "PATRICIA",
+    "CATALINA",
+];
+const SURNAME: [&str; 60] = [
+    "RODRIGUEZ",
+    "GOMEZ",
+    "GONZALEZ",
+    "MARTINEZ",
+    "GARCIA",
+    "LOPEZ",
+    "HERNANDEZ",
+    "SANCHEZ",
+    "RAMIREZ",
+    "PEREZ",
+    "DIAZ",
+    "MUNOZ",
+    "ROJAS",
+    "MORENO",
+    "JIMENEZ",
+    "VARGAS",
+    "CASTRO",
+    "GUTIERREZ",
+    "ALVAREZ",
+    "RUIZ",
+    "SUAREZ",
+    "TORRES",
+    "ROMERO",
+    "HERRERA",
+    "VALENCIA",
+    "QUINTERO",
+    "RESTREPO",
+    "GIRALDO",
+    "OSORIO",
+    "CARDONA",
+    "MEJIA",
+    "OSPINA",
+    "ARIAS",
+    "MORALES",
+    "ORTIZ",
+    "CASTILLO",
+    "RIOS",
+    "PATINO",
+    "SALAZAR",
+    "BETANCUR",
+    "ZAPATA",
+    "AGUDELO",
+    "MONTOYA",
+    "BERMUDEZ",
+    "PARRA",
+    "ACOSTA",
+    "CABRERA",
+    "CORTES",
+    "BUITRAGO",
+    "CIFUENTES",
+    "ECHEVERRY",
+    "FRANCO",
+    "GALVIS",
+    "HOYOS",
+    "LONDONO",
+    "NARVAEZ",
+    "OCAMPO",
+    "PINEDA",
+    "TRUJILLO",
+    "ZULUAGA",
+];
+const HEAD: [&str; 10] = [
+    "CONSTRUCTORA",
+    "INVERSIONES",
+    "SERVICIOS",
+    "COMERCIALIZADORA",
+    "CONSORCIO",
+    "UNION TEMPORAL",
+    "FUNDACION",
+    "DISTRIBUIDORA",
+    "INGENIERIA",
+    "GRUPO",
+];
+const SYLLABLE: [&str; 30] = [
+    "TA", "RI", "MO", "SAN", "VEL", "CO", "NA", "DE", "LU", "PRO", "GE", "TEC", "AL", "MAR", "BO",
+    "SIL", "VA", "RE", "IN", "TOR", "CA", "LI", "ME", "NOR", "PA", "ZA", "QUI", "DO", "FER", "GU",
+];
+const FORM: [&str; 4] = ["SAS", "LTDA", "SA", ""];
+
+/// Mostly natural persons, as in SECOP, plus companies with invented names
+fn synthetic_name(rng: &mut Lcg) -> String {
+    if rng.next(10) < 7 {
+        let mut name = FIRST[rng.next(FIRST.len())].to_string();
+        if rng.next(2) == 0 {
+            name = format!("{} {}", name, FIRST[rng.next(FIRST.len())]);
+        }
+        format!(
+            "{} {} {}",
+            name,
+            SURNAME[rng.next(SURNAME.len())],
+            SURNAME[rng.next(SURNAME.len())]
+        )
+    } else {
+        let brand: String = (0..2 + rng.next(3))
+            .map(|_| SYLLABLE[rng.next(SYLLABLE.len())])
+            .collect();
+        format!(
+            "{} {} {}",
+            HEAD[rng.next(HEAD.len())],
+            brand,
+            FORM[rng.next(FORM.len())]
+        )
+        .trim_end()
+        .to_string()
+    }
+}
+
+fn with_typo(name: &str, rng: &mut Lcg) -> String {
+    let mut chars: Vec<char> = name.chars().collect();
+    let at = rng.next(chars.len() - 1);
+    match rng.next(3) {
+        0 => chars[at] = (b'A' + rng.next(26) as u8) as char,
+        1 => {
+            chars.remove(at);
+        }
+        _ => chars.swap(at, at + 1),
+    }
+    chars.into_iter().collect()
+}
+
+fn corpus(size: usize) -> (EntityResolver, Vec<String>, Vec<String>) {
+    let mut rng = Lcg(7);
+    let mut resolver = EntityResolver::new();
+    let mut names = Vec::with_capacity(size);
+    for i in 0..size {
+        let name = synthetic_name(&mut rng);
+        resolver.register(Contractor::new(
+            name.clone(),
+            format!("{}", 900_000_000 + i),
+        ));
+        names.push(StandardCleaner::normalize_business_name(&name));
+    }
+    let queries = (0..QUERIES)
+        .map(|_| with_typo(&names[rng.next(names.len())], &mut rng))
+        .collect();
+    (resolver, names, queries)
+}
+
+fn bench_resolve(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resolve");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(QUERIES as u64));
+
+    for size in [20_000, 500_000] {
+        let (resolver, names, queries) = corpus(size);
+
+        group.bench_with_input(BenchmarkId::new("blocked", size), &queries, |b, queries| {
+            b.iter(|| {
+                queries
+                    .iter()
+                    .filter(|q| !matches!(resolver.resolve("", q), MatchResult::NoMatch))
+                    .count()
+            })
+        });
+
+        if size <= 20_000 {
+            group.bench_with_input(
+                BenchmarkId::new("brute_force", size),
+                &queries,
+                |b, queries| {
+                    b.iter(|| {
+                        queries
+                            .iter()
+                            .filter(|q| {
+                                let q = StandardCleaner::normalize_business_name(q);
+                                names
+                                    .iter()
+                                    .map(|n| jaro_winkler(&q, black_box(n)))
+                                    .fold(0.0, f64::max)
+                                    >= 0.85
+                            })
+                            .count()
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_resolve);
+criterion_main!(benches);
@@ -0,0 +1,395 @@
+//! Batch entity resolution over Polars DataFrames.
+//!
+//! [`EntityResolver::resolve_frame`] resolves every row of a frame of raw
+//! contractor records, creating golden records for the ones that match
+//! nothing, and appends the resolution as columns so a full SECOP snapshot is
+//! resolved in one call.
+
+use crate::cleaner::nit;
+use crate::merge::SourceRecord;
+use crate::resolver::{EntityResolver, MatchResult};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use polars::prelude::*;
+use std::collections::BTreeSet;
+
+/// Golden record UUID, as a string
+pub const UUID_COLUMN: &str = "contractor_uuid";
+/// "exact", "fuzzy" or "new"
+pub const MATCH_TYPE_COLUMN: &str = "match_type";
+/// 1.0 for exact matches, the Jaro-Winkler similarity for fuzzy ones, null for new records
+pub const CONFIDENCE_COLUMN: &str = "match_confidence";
+
+/// Input columns of a batch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchColumns {
+    pub legal_id: String,
+    pub name: String,
+    /// Linked to the golden record's `source_ids`; optional in the frame
+    pub source_id: String,
+    /// Source name recorded on every row, used by survivorship trust rules
+    pub source: String,
+    /// When each row was last seen at the source (a date, datetime or ISO-8601
+    /// string column), used by the most-recent survivorship rule. Rows without
+    /// one, or frames without the column, are stamped with the time of the run
+    pub observed_at: Option<String>,
+}
+
+impl Default for BatchColumns {
+    /// SECOP II contract columns
+    fn default() -> Self {
+        Self {
+            legal_id: "nit_contratista".to_string(),
+            name: "nombre_contratista".to_string(),
+            source_id: "id_contrato".to_string(),
+            source: "secop_ii".to_string(),
+            observed_at: Some("updated_at".to_string()),
+        }
+    }
+}
+
+fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    DateTime::parse_from_rfc3339(raw)
+        .map(|t| t.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|t| t.and_utc())
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .ok()
+                .map(|d| d.and_time(Default::default()).and_utc())
+        })
+}
+
+/// Per-row timestamps of a date, datetime or string column
+fn timestamps(column: &Column) -> PolarsResult<Vec<Option<DateTime<Utc>>>> {
+    if column.dtype() == &DataType::String {
+        return Ok(column
+            .str()?
+            .into_iter()
+            .map(|v| v.and_then(parse_timestamp))
+            .collect());
+    }
+    let millis = column
+        .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+        .cast(&DataType::Int64)?;
+    Ok(millis
+        .i64()?
+        .into_iter()
+        .map(|ms| ms.and_then(DateTime::from_timestamp_millis))
+        .collect())
+}
+
+/// Match counts of a batch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub exact: usize,
+    pub fuzzy: usize,
+    pub created: usize,
+    /// Rows with neither legal ID nor name
+    pub skipped: usize,
+    /// Rows whose legal ID has a check digit that does not match its base
+    /// number; they are still resolved by the base number
+    pub invalid_check_digits: usize,
+}
+
+impl EntityResolver {
+    /// Resolve every row of `df`, registering a new golden record for each
+    /// row that matches nothing, and return `df` with [`UUID_COLUMN`],
+    /// [`MATCH_TYPE_COLUMN`] and [`CONFIDENCE_COLUMN`] appended.
+    ///
+    /// Rows are resolved in order, so a later row can match a record created
+    /// by an earlier one. Rows with neither legal ID nor name get nulls. Each
+    /// row is kept as a source record of its golden record, and the attributes
+    /// of every golden record touched are recomputed once at the end.
+    pub fn resolve_frame(
+        &mut self,
+        df: &DataFrame,
+        columns: &BatchColumns,
+    ) -> PolarsResult<DataFrame> {
+        self.resolve_frame_with_summary(df, columns)
+            .map(|(resolved, _)| resolved)
+    }
+
+    /// [`EntityResolver::resolve_frame`], also returning the match counts
+    pub fn resolve_frame_with_summary(
+        &mut self,
+        df: &DataFrame,
+        columns: &BatchColumns,
+    ) -> PolarsResult<(DataFrame, BatchSummary)> {
+        let legal_ids = df.column(&columns.legal_id)?.cast(&DataType::String)?;
+        let legal_ids = legal_ids.str()?;
+        let names = df.column(&columns.name)?.cast(&DataType::String)?;
+        let names = names.str()?;
+        let source_ids = match df.column(&columns.source_id) {
+            Ok(column) => Some(column.cast(&DataType::String)?),
+            Err(_) => None,
+        };
+        let source_ids = source_ids.as_ref().map(|c| c.str()).transpose()?;
+        let observed = match columns.observed_at.as_deref().map(|name| df.column(name)) {
+            Some(Ok(column)) => Some(timestamps(column)?),
+            _ => None,
+        };
+
+        let mut uuids = Vec::with_capacity(df.height());
+        let mut match_types = Vec::with_capacity(df.height());
+        let mut confidences = Vec::with_capacity(df.height());
+        let mut summary = BatchSummary::default();
+        let mut touched = BTreeSet::new();
+
+        for row in 0..df.height() {
+            let legal_id = legal_ids.get(row).map(str::trim).unwrap_or_default();
+            let name = names.get(row).map(str::trim).unwrap_or_default();
+            if legal_id.is_empty() && name.is_empty() {
+                uuids.push(None);
+                match_types.push(None);
+                confidences.push(None);
+                summary.skipped += 1;
+                continue;
+            }
+
+            if nit::has_wrong_check_digit(legal_id) {
+                summary.invalid_check_digits += 1;
+            }
+
+            let source_id = source_ids.and_then(|s| s.get(row)).unwrap_or_default();
+            let mut record = SourceRecord::new(&columns.source, source_id, name, legal_id);
+            if let Some(observed_at) = observed.as_ref().and_then(|o| o[row]) {
+                record.observed_at = observed_at;
+            }
+            let (id, match_type, confidence) = match self.resolve(legal_id, name) {
+                MatchResult::ExactMatch(id) => {
+                    summary.exact += 1;
+                    self.observe(id, record);
+                    (id, "exact", Some(1.0))
+                }
+                MatchResult::FuzzyMatch { id, confidence } => {
+                    summary.fuzzy += 1;
+                    record.confidence = confidence as f32;
+                    self.observe(id, record);
+                    (id, "fuzzy", Some(confidence))
+                }
+                MatchResult::NoMatch => {
+                    summary.created += 1;
+                    (self.create_from(record), "new", None)
+                }
+            };
+
+            touched.insert(id);
+            uuids.push(Some(id.to_string()));
+            match_types.push(Some(match_type));
+            confidences.push(confidence);
+        }
+
+        for id in touched {
+            self.refresh(id);
+        }
+
+        let mut resolved = df.clone();
+        resolved.with_column(Series::new(UUID_COLUMN.into(), uuids))?;
+        resolved.with_column(Series::new(MATCH_TYPE_COLUMN.into(), match_types))?;
+        resolved.with_column(Series::new(CONFIDENCE_COLUMN.into(), confidences))?;
+        Ok((resolved, summary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_resolve_frame() {
+        let df = df!(
+            "id_contrato" => ["CO1.1", "CO1.2", "CO1.3", "CO1.4", "CO1.5", "CO1.6"],
+            "nit_contratista" => [Some("800.197.268-4"), Some("800197268"), None, Some("79123456"), None, None],
+            "nombre_contratista" => [
+                Some("Constructora Nacional S.A.S."),
+                Some("CONSTRUCTORA NACIONAL"),
+                Some("Constructora Nacionl"),
+                Some("Juan Perez"),
+                Some("Suministros del Caribe"),
+                None,
+            ],
+        )
+        .unwrap();
+
+        let mut resolver = EntityResolver::new();
+        let (resolved, summary) = resolver
+            .resolve_frame_with_summary(&df, &BatchColumns::default())
+            .unwrap();
+
+        assert_eq!(resolved.width(), df.width() + 3);
+        assert_eq!(
+            summary,
+            BatchSummary {
+                exact: 1,
+                fuzzy: 1,
+                created: 3,
+                skipped: 1,
+                invalid_check_digits: 0
+            }
+        );
+
+        let uuids = resolved.column(UUID_COLUMN).unwrap().str().unwrap();
+        let types: Vec<_> = resolved
+            .column(MATCH_TYPE_COLUMN)
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            types,
+            [
+                Some("new"),
+                Some("exact"),
+                Some("fuzzy"),
+                Some("new"),
+                Some("new"),
+                None
+            ]
+        );
+        assert_eq!(uuids.get(0), uuids.get(1));
+        assert_eq!(uuids.get(0), uuids.get(2));
+        assert_ne!(uuids.get(0), uuids.get(3));
+        assert_eq!(uuids.get(5), None);
+
+        let confidence = resolved.column(CONFIDENCE_COLUMN).unwrap().f64().unwrap();
+        assert_eq!(confidence.get(1), Some(1.0));
+        assert!(confidence.get(2).unwrap() >= 0.85);
+        assert_eq!(confidence.get(0), None);
+
+        let id = Uuid::parse_str(uuids.get(0).unwrap()).unwrap();
+        let contractor = resolver.get(&id).unwrap();
+        assert_eq!(contractor.source_ids, ["CO1.1", "CO1.2", "CO1.3"]);
+        assert!(contractor.confidence_score < 1.0);
+        assert_eq!(resolver.len(), 3);
+
+        // The fuzzy variant is now an alias of the golden record
+        assert!(
+            matches!(resolver.resolve("", "Constructora Nacionl"), MatchResult::ExactMatch(found) if found == id)
+        );
+    }
+
+    #[test]
+    fn test_conflicting_legal_ids_stay_apart() {
+        let df = df!(
+            "nit_contratista" => ["900111222", "901222333", "900.111.222-1"],
+            "nombre_contratista" => ["Consorcio Vial", "CONSORCIO VIAL", "Consorcio Vial"],
+        )
+        .unwrap();
+
+        let mut resolver = EntityResolver::new();
+        let (resolved, summary) = resolver
+            .resolve_frame_with_summary(&df, &BatchColumns::default())
+            .unwrap();
+
+        assert_eq!((summary.exact, summary.created), (1, 2));
+        let uuids = resolved.column(UUID_COLUMN).unwrap().str().unwrap();
+        assert_ne!(uuids.get(0), uuids.get(1));
+        assert_eq!(uuids.get(0), uuids.get(2));
+    }
+
+    #[test]
+    fn test_observed_at_from_column() {
+        let df = df!(
+            "id_contrato" => ["CO1.1", "CO1.2", "CO1.3"],
+            "nit_contratista" => ["900111222", "900111222", "900111222"],
+            "nombre_contratista" => ["CONSORCIO VIAL", "Consorcio Vial 2024", "Consorcio Vial Antiguo"],
+            "updated_at" => [Some("2024-03-01T08:00:00.000Z"), Some("2024-05-10T08:00:00.000"), None],
+        )
+        .unwrap();
+
+        let mut resolver = EntityResolver::new();
+        let before = Utc::now();
+        let resolved = resolver
+            .resolve_frame(&df, &BatchColumns::default())
+            .unwrap();
+        let id = Uuid::parse_str(
+            resolved
+                .column(UUID_COLUMN)
+                .unwrap()
+                .str()
+                .unwrap()
+                .get(0)
+                .unwrap(),
+        )
+        .unwrap();
+
+        let mut observed: Vec<_> = resolver.records(&id).map(|r| r.observed_at).collect();
+        observed.sort();
+        assert_eq!(
+            observed[0],
+            parse_timestamp("2024-03-01T08:00:00Z").unwrap()
+        );
+        assert_eq!(
+            observed[1],
+            parse_timestamp("2024-05-10").unwrap() + chrono::Duration::hours(8)
+        );
+        // No timestamp: stamped with the run
+        assert!(observed[2] >= before);
+
+        // Date columns work too
+        let df = df!(
+            "nit_contratista" => ["79123456"],
+            "nombre_contratista" => ["Juan Perez"],
+            "fecha_de_firma" => [NaiveDate::from_ymd_opt(2023, 7, 1).unwrap()],
+        )
+        .unwrap();
+        let columns = BatchColumns {
+            observed_at: Some("fecha_de_firma".to_string()),
+            ..BatchColumns::default()
+        };
+        let resolved = resolver.resolve_frame(&df, &columns).unwrap();
+        let id = Uuid::parse_str(
+            resolved
+                .column(UUID_COLUMN)
+                .unwrap()
+                .str()
+                .unwrap()
+                .get(0)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            resolver.get(&id).unwrap().last_updated,
+            parse_timestamp("2023-07-01").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_non_string_columns_are_cast() {
+        // A name column with no values at all is read back with the Null type
+        let df = DataFrame::new(vec![
+            Column::new("nit_contratista".into(), [800197268i64, 800197268]),
+            Series::new_null("nombre_contratista".into(), 2).into(),
+        ])
+        .unwrap();
+
+        let mut resolver = EntityResolver::new();
+        let (_, summary) = resolver
+            .resolve_frame_with_summary(&df, &BatchColumns::default())
+            .unwrap();
+        assert_eq!((summary.created, summary.exact), (1, 1));
+    }
+
+    #[test]
+    fn test_missing_ids_do_not_collapse() {
+        let df = df!(
+            "nit_contratista" => [None::<&str>, None],
+            "nombre_contratista" => ["Alcaldia de Pasto", "Hospital San Rafael"],
+        )
+        .unwrap();
+
+        let mut resolver = EntityResolver::new();
+        let resolved = resolver
+            .resolve_frame(&df, &BatchColumns::default())
+            .unwrap();
+        let uuids = resolved.column(UUID_COLUMN).unwrap().str().unwrap();
+        assert_ne!(uuids.get(0), uuids.get(1));
+    }
+}
@@ -0,0 +1,196 @@
+//! Candidate blocking for fuzzy name matching.
+//!
+//! Scoring every known name with Jaro-Winkler is O(n) per lookup. The
+//! [`BlockingIndex`] keeps an inverted index from padded character trigrams
+//! to names, so only names that share enough trigrams with the query are
+//! scored.
+//!
+//! Shared trigrams are counted per name while walking the query's posting
+//! lists, rarest first. A name that shares at least `t` of the query's `g`
+//! trigrams must contain one of the `g - t + 1` rarest, so the longer lists
+//! that follow only add to names already hit (prefix filtering). The
+//! counters live in a scratch buffer kept across lookups and only the prefix
+//! lists are walked again to reset them, so a lookup costs the postings it
+//! reads, not the size of the index.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Gram length
+const Q: usize = 3;
+/// Padding so the first and last characters get their own grams
+const PAD: char = '#';
+/// Share of the query's trigrams a candidate must contain
+const MIN_OVERLAP: f64 = 0.4;
+
+struct Entry {
+    name: String,
+    id: Uuid,
+}
+
+/// Inverted trigram index over normalized names
+#[derive(Default)]
+pub struct BlockingIndex {
+    gram_ids: HashMap<[char; Q], u32>,
+    /// Entry slots containing each gram, indexed by gram id
+    postings: Vec<Vec<u32>>,
+    entries: Vec<Entry>,
+    by_name: HashMap<String, u32>,
+    /// Shared-trigram count per entry slot, all zero between lookups
+    shared: Mutex<Vec<u16>>,
+}
+
+/// Distinct padded trigrams of a name
+fn qgrams(name: &str) -> Vec<[char; Q]> {
+    let padded: Vec<char> = std::iter::repeat_n(PAD, Q - 1)
+        .chain(name.chars())
+        .chain(std::iter::repeat_n(PAD, Q - 1))
+        .collect();
+    let mut grams: Vec<[char; Q]> = padded.windows(Q).map(|w| [w[0], w[1], w[2]]).collect();
+    grams.sort_unstable();
+    grams.dedup();
+    grams
+}
+
+impl BlockingIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index a normalized name; re-inserting a name points it at the new ID
+    pub fn insert(&mut self, name: &str, id: Uuid) {
+        if let Some(&slot) = self.by_name.get(name) {
+            self.entries[slot as usize].id = id;
+            return;
+        }
+
+        let slot = self.entries.len() as u32;
+        for gram in qgrams(name) {
+            let next = self.postings.len() as u32;
+            let gram_id = *self.gram_ids.entry(gram).or_insert(next);
+            if gram_id == next {
+                self.postings.push(Vec::new());
+            }
+            self.postings[gram_id as usize].push(slot);
+        }
+
+        self.entries.push(Entry {
+            name: name.to_string(),
+            id,
+        });
+        self.by_name.insert(name.to_string(), slot);
+        self.shared
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(0);
+    }
+
+    /// Names sharing at least [`MIN_OVERLAP`] of the query's trigrams
+    pub fn candidates(&self, name: &str) -> Vec<(&str, Uuid)> {
+        let grams = qgrams(name);
+        let required = ((grams.len() as f64 * MIN_OVERLAP).ceil() as usize).max(1);
+
+        // Grams never seen in the index have empty postings and sort first
+        let mut known: Vec<u32> = grams
+            .iter()
+            .filter_map(|gram| self.gram_ids.get(gram).copied())
+            .collect();
+        let unknown = grams.len() - known.len();
+        if known.len() < required {
+            return Vec::new();
+        }
+        known.sort_unstable_by_key(|&g| self.postings[g as usize].len());
+
+        let prefix = (grams.len() - required + 1).saturating_sub(unknown);
+        let mut shared = self.shared.lock().unwrap_or_else(|e| {
+            // A lookup panicked halfway and may have left counts behind
+            let mut shared = e.into_inner();
+            shared.fill(0);
+            shared
+        });
+        let mut candidates = Vec::new();
+        for (i, &gram) in known.iter().enumerate() {
+            for &slot in &self.postings[gram as usize] {
+                let count = &mut shared[slot as usize];
+                // Past the prefix, only names already hit can still qualify
+                if i >= prefix && *count == 0 {
+                    continue;
+                }
+                *count += 1;
+                if *count as usize == required {
+                    candidates.push(slot);
+                }
+            }
+        }
+        // Every counted slot appears in a prefix list
+        for &gram in &known[..prefix.min(known.len())] {
+            for &slot in &self.postings[gram as usize] {
+                shared[slot as usize] = 0;
+            }
+        }
+        drop(shared);
+
+        candidates
+            .into_iter()
+            .map(|slot| {
+                let entry = &self.entries[slot as usize];
+                (entry.name.as_str(), entry.id)
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates_share_trigrams() {
+        let mut index = BlockingIndex::new();
+        let nacional = Uuid::new_v4();
+        index.insert("CONSTRUCTORA NACIONAL", nacional);
+        index.insert("INVERSIONES DEL CARIBE", Uuid::new_v4());
+        index.insert("SUMINISTROS HOSPITALARIOS", Uuid::new_v4());
+
+        let candidates = index.candidates("CONSTRUCTORA NACONAL");
+        assert_eq!(candidates, vec![("CONSTRUCTORA NACIONAL", nacional)]);
+        assert!(index.candidates("ZZZZZZ").is_empty());
+    }
+
+    #[test]
+    fn test_reinsert_updates_id() {
+        let mut index = BlockingIndex::new();
+        let newer = Uuid::new_v4();
+        index.insert("EMPRESA XYZ", Uuid::new_v4());
+        index.insert("EMPRESA XYZ", newer);
+
+        assert_eq!(index.len(), 1);
+        assert_eq!(index.candidates("EMPRESA XYZ")[0].1, newer);
+    }
+
+    #[test]
+    fn test_lookups_do_not_leak_counts() {
+        let mut index = BlockingIndex::new();
+        index.insert("CONSTRUCTORA NACIONAL", Uuid::new_v4());
+        index.insert("CONSTRUCTORA DEL SUR", Uuid::new_v4());
+        index.insert("INVERSIONES DEL CARIBE", Uuid::new_v4());
+
+        // Every lookup starts from zero, so repeating one gives the same answer
+        let first = index.candidates("CONSTRUCTORA NACONAL");
+        assert_eq!(first.len(), 2);
+        for query in ["INVERSIONES CARIBE", "CONSTRUCTORA NACONAL"] {
+            index.candidates(query);
+        }
+        assert_eq!(index.candidates("CONSTRUCTORA NACONAL"), first);
+        assert!(index.shared.lock().unwrap().iter().all(|&count| count == 0));
+    }
+}
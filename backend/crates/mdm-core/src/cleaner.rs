@@ -1,5 +1,7 @@
 use polars::prelude::*;
 
+pub mod nit;
+
 pub trait DataCleaner {
     fn clean_string(input: &str) -> String {
         input.trim().to_uppercase()
@@ -17,6 +19,11 @@ pub trait DataCleaner {
             .trim()
             .to_string()
     }
+
+    /// Canonical legal ID (NIT/cédula base number, without DV) for matching
+    fn normalize_legal_id(legal_id: &str) -> String {
+        nit::canonical_legal_id(legal_id)
+    }
 }
 
 pub struct StandardCleaner;
@@ -0,0 +1,278 @@
+//! Colombian legal identifiers: NIT and cédula.
+//!
+//! Parses the formats found in SECOP ("900.123.456-7", "900123456-7",
+//! "NIT 900123456", "C.C. 79.123.456", "9001234567"), separates the DIAN
+//! verification digit (DV) from the base number and checks it. The base
+//! number without DV is the canonical key used for entity resolution.
+
+use std::fmt;
+use thiserror::Error;
+
+/// DIAN weights, applied to the base number from its rightmost digit
+const DIAN_WEIGHTS: [u32; 15] = [3, 7, 13, 17, 19, 23, 29, 37, 41, 43, 47, 53, 59, 67, 71];
+
+/// Kind of identifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdType {
+    /// NIT of a company (9 digits starting with 8 or 9)
+    Nit,
+    /// Cédula de ciudadanía of a natural person
+    Cedula,
+    /// Cédula de extranjería
+    CedulaExtranjeria,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LegalIdError {
+    #[error("empty legal ID")]
+    Empty,
+    #[error("legal ID contains non-numeric characters: {0:?}")]
+    InvalidCharacters(String),
+    #[error("legal ID has {0} digits (expected 3 to 15)")]
+    InvalidLength(usize),
+    #[error("check digit {found} does not match the expected {expected}")]
+    CheckDigitMismatch { expected: u8, found: u8 },
+}
+
+/// A parsed NIT or cédula
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LegalId {
+    /// Digits without check digit or leading zeros
+    pub base: String,
+    /// Check digit as written in the source, if any
+    pub check_digit: Option<u8>,
+    pub id_type: IdType,
+}
+
+/// DIAN check digit of a base number (digits only, at most 15)
+pub fn check_digit(base: &str) -> Option<u8> {
+    if base.is_empty() || base.len() > DIAN_WEIGHTS.len() {
+        return None;
+    }
+    let mut sum = 0;
+    for (digit, weight) in base.chars().rev().zip(DIAN_WEIGHTS) {
+        sum += digit.to_digit(10)? * weight;
+    }
+    Some(match sum % 11 {
+        r @ (0 | 1) => r as u8,
+        r => (11 - r) as u8,
+    })
+}
+
+/// Split a leading type prefix ("NIT", "CC", "C.C.", "CE", ...) from the number
+fn strip_prefix(raw: &str) -> (Option<IdType>, &str) {
+    let raw = raw.trim_start();
+    for (prefix, id_type) in [
+        ("NIT", IdType::Nit),
+        ("C.C.", IdType::Cedula),
+        ("CC", IdType::Cedula),
+        ("C.E.", IdType::CedulaExtranjeria),
+        ("CE", IdType::CedulaExtranjeria),
+    ] {
+        if raw
+            .get(..prefix.len())
+            .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+        {
+            let rest = &raw[prefix.len()..];
+            return (Some(id_type), rest.trim_start_matches(['.', ':', ' ', '#']));
+        }
+    }
+    (None, raw)
+}
+
+fn looks_like_company(base: &str) -> bool {
+    base.len() == 9 && (base.starts_with('8') || base.starts_with('9'))
+}
+
+impl LegalId {
+    /// Parse any common written form. The check digit is recorded but not
+    /// validated; see [`LegalId::validate`].
+    pub fn parse(raw: &str) -> Result<Self, LegalIdError> {
+        let (prefix_type, number) = strip_prefix(raw.trim());
+        let number = number.trim();
+        if number.is_empty() {
+            return Err(LegalIdError::Empty);
+        }
+
+        // An explicit DV follows the last dash
+        let (body, written_dv) = match number.rsplit_once('-') {
+            Some((body, dv)) if dv.trim().len() == 1 => (body, dv.trim().chars().next()),
+            _ => (number, None),
+        };
+
+        let digits: String = body
+            .chars()
+            .filter(|c| !matches!(c, '.' | ',' | ' '))
+            .collect();
+        if digits.is_empty() {
+            return Err(LegalIdError::Empty);
+        }
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(LegalIdError::InvalidCharacters(raw.to_string()));
+        }
+        let mut check = match written_dv {
+            Some(c) => Some(
+                c.to_digit(10)
+                    .ok_or_else(|| LegalIdError::InvalidCharacters(raw.to_string()))?
+                    as u8,
+            ),
+            None => None,
+        };
+
+        let mut base = digits.trim_start_matches('0').to_string();
+        // A company NIT with its DV glued on ("9001234567"), recognized only
+        // when the last digit is the correct DV
+        if check.is_none() && prefix_type != Some(IdType::Cedula) && base.len() == 10 {
+            let (head, tail) = base.split_at(9);
+            let dv = tail.parse::<u8>().ok();
+            if looks_like_company(head) && check_digit(head) == dv {
+                check = dv;
+                base = head.to_string();
+            }
+        }
+
+        if !(3..=DIAN_WEIGHTS.len()).contains(&base.len()) {
+            return Err(LegalIdError::InvalidLength(base.len()));
+        }
+
+        let id_type = prefix_type.unwrap_or(if looks_like_company(&base) {
+            IdType::Nit
+        } else {
+            IdType::Cedula
+        });
+
+        Ok(Self {
+            base,
+            check_digit: check,
+            id_type,
+        })
+    }
+
+    /// The DV this base number should carry
+    pub fn expected_check_digit(&self) -> u8 {
+        check_digit(&self.base).expect("base is 3 to 15 digits")
+    }
+
+    /// Fail if a written check digit does not match the computed one
+    pub fn validate(&self) -> Result<(), LegalIdError> {
+        match self.check_digit {
+            Some(found) if found != self.expected_check_digit() => {
+                Err(LegalIdError::CheckDigitMismatch {
+                    expected: self.expected_check_digit(),
+                    found,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Canonical key: the base number without DV
+    pub fn canonical(&self) -> &str {
+        &self.base
+    }
+}
+
+impl fmt::Display for LegalId {
+    /// "900123456-7" for NITs, the bare number for cédulas
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.id_type {
+            IdType::Nit => write!(f, "{}-{}", self.base, self.expected_check_digit()),
+            _ => write!(f, "{}", self.base),
+        }
+    }
+}
+
+/// Canonical key for any legal ID, or an empty string when it does not parse.
+///
+/// Placeholders ("NO DEFINIDO", "N/A", "0") are shared by unrelated companies,
+/// so they must not become a key that matches across them.
+pub fn canonical_legal_id(raw: &str) -> String {
+    LegalId::parse(raw).map(|id| id.base).unwrap_or_default()
+}
+
+/// Whether `raw` parses but carries a check digit that does not match its base
+pub fn has_wrong_check_digit(raw: &str) -> bool {
+    LegalId::parse(raw).is_ok_and(|id| id.validate().is_err())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_digit() {
+        // Published NITs: DIAN, Ecopetrol, Bancolombia
+        assert_eq!(check_digit("800197268"), Some(4));
+        assert_eq!(check_digit("899999068"), Some(1));
+        assert_eq!(check_digit("890903938"), Some(8));
+        assert_eq!(check_digit("90012345a"), None);
+    }
+
+    #[test]
+    fn test_parse_formats() {
+        for raw in [
+            "800.197.268-4",
+            "800197268-4",
+            "NIT 800197268",
+            "nit: 800.197.268 - 4",
+            "8001972684",
+        ] {
+            let id = LegalId::parse(raw).unwrap();
+            assert_eq!(id.canonical(), "800197268", "{}", raw);
+            assert_eq!(id.id_type, IdType::Nit);
+            assert!(id.validate().is_ok());
+        }
+
+        let cedula = LegalId::parse("C.C. 79.123.456").unwrap();
+        assert_eq!(cedula.id_type, IdType::Cedula);
+        assert_eq!(cedula.canonical(), "79123456");
+        assert_eq!(cedula.check_digit, None);
+
+        // A 10-digit cédula is not mistaken for a NIT with its DV
+        assert_eq!(
+            LegalId::parse("1085123456").unwrap().canonical(),
+            "1085123456"
+        );
+        assert_eq!(
+            LegalId::parse("CE 0456789").unwrap().id_type,
+            IdType::CedulaExtranjeria
+        );
+    }
+
+    #[test]
+    fn test_invalid_ids() {
+        assert_eq!(LegalId::parse("  "), Err(LegalIdError::Empty));
+        assert!(matches!(
+            LegalId::parse("No Definido"),
+            Err(LegalIdError::InvalidCharacters(_))
+        ));
+        assert_eq!(LegalId::parse("12"), Err(LegalIdError::InvalidLength(2)));
+        assert_eq!(
+            LegalId::parse("800197268-5").unwrap().validate(),
+            Err(LegalIdError::CheckDigitMismatch {
+                expected: 4,
+                found: 5
+            })
+        );
+    }
+
+    #[test]
+    fn test_display_and_canonical() {
+        assert_eq!(
+            LegalId::parse("800197268").unwrap().to_string(),
+            "800197268-4"
+        );
+        assert_eq!(canonical_legal_id("800.197.268-4"), "800197268");
+        for placeholder in [" pasaporte ab123 ", "NO DEFINIDO", "N/A", "0", ""] {
+            assert_eq!(canonical_legal_id(placeholder), "", "{}", placeholder);
+        }
+    }
+
+    #[test]
+    fn test_wrong_check_digit() {
+        assert!(has_wrong_check_digit("800.197.268-5"));
+        assert!(!has_wrong_check_digit("800.197.268-4"));
+        assert!(!has_wrong_check_digit("800197268"));
+        assert!(!has_wrong_check_digit("NO DEFINIDO"));
+    }
+}
@@ -1,7 +1,8 @@
-use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+use crate::cleaner::{DataCleaner, StandardCleaner};
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use uuid::Uuid;
 
 /// Represents a canonical Contractor (Golden Record)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,10 +21,15 @@ pub struct Contractor {
     pub confidence_score: f32,
 }
 
+/// Namespace of the deterministic contractor UUIDs (v5)
+pub const CONTRACTOR_NAMESPACE: Uuid = Uuid::from_u128(0x5d0e_6a52_8b1f_4c3e_9a47_2f6b_0c1d_7e83);
+
 impl Contractor {
+    /// New golden record whose ID is derived from its identity, so the same
+    /// contractor gets the same UUID on every run (see [`Contractor::stable_id`])
     pub fn new(name: String, legal_id: String) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: Self::stable_id(&name, &legal_id),
             name,
             legal_id,
             source_ids: Vec::new(),
@@ -31,6 +37,18 @@ impl Contractor {
             confidence_score: 1.0,
         }
     }
+
+    /// UUID v5 of the canonical legal ID, or of the normalized name when
+    /// there is no legal ID
+    pub fn stable_id(name: &str, legal_id: &str) -> Uuid {
+        let legal_id = StandardCleaner::normalize_legal_id(legal_id);
+        let key = if legal_id.is_empty() {
+            format!("name:{}", StandardCleaner::normalize_business_name(name))
+        } else {
+            format!("legal_id:{}", legal_id)
+        };
+        Uuid::new_v5(&CONTRACTOR_NAMESPACE, key.as_bytes())
+    }
 }
 
 /// Represents a Public Entity (Alcaldía, Gobernación, Ministerio, etc.)
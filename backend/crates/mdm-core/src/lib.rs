@@ -1,3 +1,7 @@
-pub mod domain;
+pub mod batch;
+pub mod blocking;
 pub mod cleaner;
+pub mod domain;
+pub mod merge;
 pub mod resolver;
+pub mod store;
@@ -0,0 +1,662 @@
+//! Golden-record maintenance: source records, survivorship, merge and unmerge.
+//!
+//! Every golden record keeps the source records resolved to it (one per
+//! source and source ID). Its attributes are recomputed from them with
+//! [`SurvivorshipRules`], and every attribute value chosen, merge and unmerge
+//! is appended to a lineage log. Each source record remembers the golden
+//! record it was first resolved to, so a wrong merge can be undone with
+//! [`EntityResolver::unmerge`] and the absorbed record gets its UUID back.
+
+use crate::cleaner::{DataCleaner, StandardCleaner};
+use crate::domain::Contractor;
+use crate::resolver::EntityResolver;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Source of the records seeded from a golden record that was registered
+/// directly, before it had any source records
+pub const GOLDEN_RECORD_SOURCE: &str = "golden_record";
+/// Trust of sources missing from [`SurvivorshipRules::source_trust`]
+pub const DEFAULT_TRUST: f32 = 0.5;
+
+/// A raw contractor record as seen in one source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceRecord {
+    /// Source dataset or system ("secop_ii", "manual", ...)
+    pub source: String,
+    /// Row ID within the source (e.g. SECOP `id_contrato`)
+    pub source_id: String,
+    pub name: String,
+    pub legal_id: String,
+    pub observed_at: DateTime<Utc>,
+    /// Confidence with which the record was matched to its golden record
+    pub confidence: f32,
+    /// Golden record the record was first resolved to
+    pub origin: Uuid,
+}
+
+impl SourceRecord {
+    pub fn new(source: &str, source_id: &str, name: &str, legal_id: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            source_id: source_id.to_string(),
+            name: name.to_string(),
+            legal_id: legal_id.to_string(),
+            observed_at: Utc::now(),
+            confidence: 1.0,
+            origin: Uuid::nil(),
+        }
+    }
+
+    /// Key within a golden record; records without a source ID are kept
+    /// once per golden record of origin
+    pub(crate) fn key(&self) -> (String, String) {
+        let source_id = if self.source_id.is_empty() {
+            format!("#{}", self.origin)
+        } else {
+            self.source_id.clone()
+        };
+        (self.source.clone(), source_id)
+    }
+}
+
+/// How the surviving value of an attribute is chosen among source records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    /// The value most records agree on (compared normalized)
+    MostFrequent,
+    /// The value of the most recently observed record
+    MostRecent,
+    /// The value from the most trusted source
+    MostTrusted,
+}
+
+/// Survivorship configuration; ties always go to the most recent record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurvivorshipRules {
+    pub name: Rule,
+    pub legal_id: Rule,
+    /// Trust per source, higher wins under [`Rule::MostTrusted`]
+    #[serde(default)]
+    pub source_trust: HashMap<String, f32>,
+}
+
+impl Default for SurvivorshipRules {
+    fn default() -> Self {
+        Self {
+            name: Rule::MostFrequent,
+            legal_id: Rule::MostTrusted,
+            source_trust: HashMap::new(),
+        }
+    }
+}
+
+impl SurvivorshipRules {
+    pub fn trust(&self, source: &str) -> f32 {
+        self.source_trust
+            .get(source)
+            .copied()
+            .unwrap_or(DEFAULT_TRUST)
+    }
+
+    /// Record supplying the surviving value; records with an empty key don't vote
+    fn pick<'a>(
+        &self,
+        records: &[&'a SourceRecord],
+        rule: Rule,
+        key: impl Fn(&SourceRecord) -> String,
+    ) -> Option<&'a SourceRecord> {
+        let candidates = records.iter().copied().filter(|r| !key(r).is_empty());
+        match rule {
+            Rule::MostRecent => candidates.max_by_key(|r| r.observed_at),
+            Rule::MostTrusted => candidates.max_by(|a, b| {
+                self.trust(&a.source)
+                    .total_cmp(&self.trust(&b.source))
+                    .then(a.observed_at.cmp(&b.observed_at))
+            }),
+            Rule::MostFrequent => {
+                let mut counts: HashMap<String, usize> = HashMap::new();
+                for record in records {
+                    *counts.entry(key(record)).or_default() += 1;
+                }
+                candidates.max_by_key(|r| (counts[&key(r)], r.observed_at))
+            }
+        }
+    }
+}
+
+/// Golden-record attribute set by survivorship
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Attribute {
+    Name,
+    LegalId,
+}
+
+/// What happened to a golden record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LineageEvent {
+    /// Survivorship chose a new value, supplied by the given source record
+    Attribute {
+        attribute: Attribute,
+        value: String,
+        source: String,
+        source_id: String,
+        rule: Rule,
+    },
+    /// Another golden record was merged into this one
+    Merged { absorbed: Uuid },
+    /// A previously merged golden record was split back out of this one
+    Unmerged { restored: Uuid },
+}
+
+/// One entry of the lineage log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineageEntry {
+    pub at: DateTime<Utc>,
+    pub contractor_id: Uuid,
+    #[serde(flatten)]
+    pub event: LineageEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MergeError {
+    #[error("unknown contractor {0}")]
+    UnknownContractor(Uuid),
+    #[error("cannot merge contractor {0} into itself")]
+    SameContractor(Uuid),
+    #[error("contractor {0} is not merged into another")]
+    NotMerged(Uuid),
+}
+
+impl EntityResolver {
+    /// Replace the survivorship rules; applied on the next refresh or merge
+    pub fn set_survivorship_rules(&mut self, rules: SurvivorshipRules) {
+        self.rules = rules;
+    }
+
+    pub fn survivorship_rules(&self) -> &SurvivorshipRules {
+        &self.rules
+    }
+
+    /// Source records behind a golden record
+    pub fn records(&self, id: &Uuid) -> impl Iterator<Item = &SourceRecord> {
+        self.records
+            .get(id)
+            .into_iter()
+            .flat_map(|records| records.values())
+    }
+
+    /// The full lineage log, oldest first
+    pub fn lineage(&self) -> &[LineageEntry] {
+        &self.lineage
+    }
+
+    /// Lineage of one golden record, oldest first
+    pub fn lineage_of(&self, id: Uuid) -> impl Iterator<Item = &LineageEntry> {
+        self.lineage
+            .iter()
+            .filter(move |entry| entry.contractor_id == id)
+    }
+
+    /// Current golden record of an ID, following merges; `None` if unknown
+    pub fn canonical_id(&self, id: Uuid) -> Option<Uuid> {
+        let mut current = id;
+        while let Some(&survivor) = self.merged_into.get(&current) {
+            current = survivor;
+        }
+        self.contractors.contains_key(&current).then_some(current)
+    }
+
+    /// Create a golden record from its first source record
+    pub fn create_from(&mut self, record: SourceRecord) -> Uuid {
+        let contractor = Contractor::new(record.name.clone(), record.legal_id.clone());
+        let id = contractor.id;
+        self.register(contractor);
+        self.records.entry(id).or_default();
+        self.observe(id, record);
+        id
+    }
+
+    /// Attach a source record to a golden record, replacing an earlier
+    /// version of the same record. Its name and legal ID become aliases of
+    /// the golden record unless they already identify another one.
+    /// Call [`EntityResolver::refresh`] afterwards to update the attributes.
+    pub fn observe(&mut self, id: Uuid, mut record: SourceRecord) {
+        self.seed_records(id);
+        self.index_record(id, &record);
+        let records = self.records.entry(id).or_default();
+        record.origin = id;
+        let key = record.key();
+        if let Some(existing) = records.get(&key) {
+            record.origin = existing.origin;
+        }
+        records.insert(key, record);
+    }
+
+    /// Recompute a golden record's attributes from its source records
+    pub fn refresh(&mut self, id: Uuid) {
+        let Some(contractor) = self.contractors.get_mut(&id) else {
+            return;
+        };
+        let records: Vec<&SourceRecord> = self
+            .records
+            .get(&id)
+            .map(|records| records.values().collect())
+            .unwrap_or_default();
+        if records.is_empty() {
+            return;
+        }
+
+        let name = self.rules.pick(&records, self.rules.name, |r| {
+            StandardCleaner::normalize_business_name(&r.name)
+        });
+        let legal_id = self.rules.pick(&records, self.rules.legal_id, |r| {
+            StandardCleaner::normalize_legal_id(&r.legal_id)
+        });
+
+        let mut changes = Vec::new();
+        for (attribute, rule, winner, current) in [
+            (Attribute::Name, self.rules.name, name, &mut contractor.name),
+            (
+                Attribute::LegalId,
+                self.rules.legal_id,
+                legal_id,
+                &mut contractor.legal_id,
+            ),
+        ] {
+            let Some(winner) = winner else { continue };
+            let value = match attribute {
+                Attribute::Name => &winner.name,
+                Attribute::LegalId => &winner.legal_id,
+            };
+            if *current != *value || !self.traced.contains(&(id, attribute)) {
+                *current = value.clone();
+                changes.push(LineageEvent::Attribute {
+                    attribute,
+                    value: value.clone(),
+                    source: winner.source.clone(),
+                    source_id: winner.source_id.clone(),
+                    rule,
+                });
+            }
+        }
+
+        let mut source_ids: Vec<String> = Vec::new();
+        for record in &records {
+            if !record.source_id.is_empty() && !source_ids.contains(&record.source_id) {
+                source_ids.push(record.source_id.clone());
+            }
+        }
+        contractor.source_ids = source_ids;
+        contractor.last_updated = records
+            .iter()
+            .map(|r| r.observed_at)
+            .max()
+            .unwrap_or(contractor.last_updated);
+        contractor.confidence_score =
+            records.iter().map(|r| r.confidence).sum::<f32>() / records.len() as f32;
+
+        for event in changes {
+            if let LineageEvent::Attribute { attribute, .. } = &event {
+                self.traced.insert((id, *attribute));
+            }
+            self.log(id, event);
+        }
+    }
+
+    /// Merge `absorbed` into `survivor`: its source records, aliases and old
+    /// IDs move to `survivor`, whose attributes are then recomputed
+    pub fn merge(&mut self, survivor: Uuid, absorbed: Uuid) -> Result<(), MergeError> {
+        if survivor == absorbed {
+            return Err(MergeError::SameContractor(survivor));
+        }
+        for id in [survivor, absorbed] {
+            if !self.contractors.contains_key(&id) {
+                return Err(MergeError::UnknownContractor(id));
+            }
+        }
+
+        self.seed_records(survivor);
+        self.seed_records(absorbed);
+        let moved = self.records.remove(&absorbed).unwrap_or_default();
+        self.records.entry(survivor).or_default().extend(moved);
+        self.contractors.remove(&absorbed);
+        self.merged_into.insert(absorbed, survivor);
+
+        for id in self.id_index.values_mut().filter(|id| **id == absorbed) {
+            *id = survivor;
+        }
+        for (name, id) in self
+            .name_index
+            .iter_mut()
+            .filter(|(_, id)| **id == absorbed)
+        {
+            *id = survivor;
+            self.blocking.insert(name, survivor);
+        }
+
+        self.log(survivor, LineageEvent::Merged { absorbed });
+        self.refresh(survivor);
+        Ok(())
+    }
+
+    /// Undo the merge of `absorbed`: the source records that came with it
+    /// (including records of golden records merged into it earlier) are split
+    /// back out into a golden record with its original UUID. Returns the
+    /// golden record it was split from.
+    pub fn unmerge(&mut self, absorbed: Uuid) -> Result<Uuid, MergeError> {
+        if !self.merged_into.contains_key(&absorbed) {
+            return Err(MergeError::NotMerged(absorbed));
+        }
+        let survivor = self
+            .canonical_id(absorbed)
+            .ok_or(MergeError::UnknownContractor(absorbed))?;
+
+        let (restored, kept): (BTreeMap<_, _>, BTreeMap<_, _>) = self
+            .records
+            .remove(&survivor)
+            .unwrap_or_default()
+            .into_iter()
+            .partition(|(_, record)| self.descends_from(record.origin, absorbed));
+        self.merged_into.remove(&absorbed);
+        self.records.insert(survivor, kept);
+        self.records.insert(absorbed, restored);
+
+        let mut contractor = self.contractors[&survivor].clone();
+        contractor.id = absorbed;
+        self.contractors.insert(absorbed, contractor);
+
+        // Survivor keeps the aliases both sides share
+        self.id_index.retain(|_, id| *id != survivor);
+        self.name_index.retain(|_, id| *id != survivor);
+        for id in [survivor, absorbed] {
+            let records: Vec<SourceRecord> = self.records(&id).cloned().collect();
+            for record in &records {
+                self.index_record(id, record);
+            }
+        }
+
+        self.log(survivor, LineageEvent::Unmerged { restored: absorbed });
+        self.refresh(survivor);
+        self.refresh(absorbed);
+        Ok(survivor)
+    }
+
+    /// Give a golden record registered without source records one record per
+    /// source ID (or a single one), so merges and unmerges can move it
+    fn seed_records(&mut self, id: Uuid) {
+        if self.records.contains_key(&id) {
+            return;
+        }
+        let Some(contractor) = self.contractors.get(&id) else {
+            return;
+        };
+        let source_ids = if contractor.source_ids.is_empty() {
+            vec![String::new()]
+        } else {
+            contractor.source_ids.clone()
+        };
+        let records = source_ids
+            .into_iter()
+            .map(|source_id| {
+                let record = SourceRecord {
+                    source: GOLDEN_RECORD_SOURCE.to_string(),
+                    source_id,
+                    name: contractor.name.clone(),
+                    legal_id: contractor.legal_id.clone(),
+                    observed_at: contractor.last_updated,
+                    confidence: contractor.confidence_score,
+                    origin: id,
+                };
+                (record.key(), record)
+            })
+            .collect();
+        self.records.insert(id, records);
+    }
+
+    /// Index a record's name and legal ID for `id`, unless already taken
+    fn index_record(&mut self, id: Uuid, record: &SourceRecord) {
+        let legal_id = StandardCleaner::normalize_legal_id(&record.legal_id);
+        if !legal_id.is_empty() {
+            self.id_index.entry(legal_id).or_insert(id);
+        }
+        let name = StandardCleaner::normalize_business_name(&record.name);
+        if !name.is_empty() && !self.name_index.contains_key(&name) {
+            self.blocking.insert(&name, id);
+            self.name_index.insert(name, id);
+        }
+    }
+
+    /// Whether `origin` is `ancestor` or was merged into it, directly or not
+    fn descends_from(&self, origin: Uuid, ancestor: Uuid) -> bool {
+        let mut current = origin;
+        loop {
+            if current == ancestor {
+                return true;
+            }
+            match self.merged_into.get(&current) {
+                Some(&parent) => current = parent,
+                None => return false,
+            }
+        }
+    }
+
+    fn log(&mut self, contractor_id: Uuid, event: LineageEvent) {
+        self.lineage.push(LineageEntry {
+            at: Utc::now(),
+            contractor_id,
+            event,
+        });
+    }
+
+    /// Rebuild the merge tree and traced attributes from a loaded lineage log
+    pub(crate) fn replay_lineage(&mut self, lineage: Vec<LineageEntry>) {
+        for entry in &lineage {
+            match &entry.event {
+                LineageEvent::Attribute { attribute, .. } => {
+                    self.traced.insert((entry.contractor_id, *attribute));
+                }
+                LineageEvent::Merged { absorbed } => {
+                    self.merged_into.insert(*absorbed, entry.contractor_id);
+                }
+                LineageEvent::Unmerged { restored } => {
+                    self.merged_into.remove(restored);
+                }
+            }
+        }
+        self.lineage = lineage;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::MatchResult;
+    use chrono::Duration;
+
+    fn record(
+        source: &str,
+        source_id: &str,
+        name: &str,
+        legal_id: &str,
+        days_ago: i64,
+    ) -> SourceRecord {
+        SourceRecord {
+            observed_at: Utc::now() - Duration::days(days_ago),
+            ..SourceRecord::new(source, source_id, name, legal_id)
+        }
+    }
+
+    #[test]
+    fn test_survivorship_rules() {
+        let mut resolver = EntityResolver::new();
+        let id = resolver.create_from(record(
+            "secop_ii",
+            "CO1.1",
+            "Constructora Nal",
+            "800197268",
+            30,
+        ));
+        resolver.observe(
+            id,
+            record(
+                "secop_ii",
+                "CO1.2",
+                "CONSTRUCTORA NACIONAL S.A.S.",
+                "800197268-4",
+                20,
+            ),
+        );
+        resolver.observe(
+            id,
+            record(
+                "secop_ii",
+                "CO1.3",
+                "Constructora Nacional",
+                "800197268",
+                10,
+            ),
+        );
+        resolver.observe(
+            id,
+            record(
+                "rues",
+                "R-1",
+                "Constructora Nacional de Colombia",
+                "800197268-4",
+                1,
+            ),
+        );
+        resolver.refresh(id);
+
+        let contractor = resolver.get(&id).unwrap();
+        assert_eq!(contractor.name, "Constructora Nacional");
+        assert_eq!(contractor.source_ids, ["R-1", "CO1.1", "CO1.2", "CO1.3"]);
+        assert_eq!(
+            contractor.last_updated,
+            resolver.records(&id).map(|r| r.observed_at).max().unwrap()
+        );
+
+        resolver.set_survivorship_rules(SurvivorshipRules {
+            name: Rule::MostTrusted,
+            source_trust: HashMap::from([("rues".to_string(), 0.9)]),
+            ..SurvivorshipRules::default()
+        });
+        resolver.refresh(id);
+        assert_eq!(
+            resolver.get(&id).unwrap().name,
+            "Constructora Nacional de Colombia"
+        );
+
+        resolver.set_survivorship_rules(SurvivorshipRules {
+            name: Rule::MostRecent,
+            ..SurvivorshipRules::default()
+        });
+        resolver.observe(
+            id,
+            record("secop_ii", "CO1.4", "Constructora Nacional SAS", "", 0),
+        );
+        resolver.refresh(id);
+        let contractor = resolver.get(&id).unwrap();
+        assert_eq!(contractor.name, "Constructora Nacional SAS");
+        assert_eq!(contractor.legal_id, "800197268-4");
+
+        // Every chosen name is traced to the record that supplied it
+        let names: Vec<_> = resolver
+            .lineage_of(id)
+            .filter_map(|entry| match &entry.event {
+                LineageEvent::Attribute {
+                    attribute: Attribute::Name,
+                    source_id,
+                    ..
+                } => Some(source_id.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, ["CO1.3", "R-1", "CO1.4"]);
+    }
+
+    #[test]
+    fn test_merge_and_unmerge() {
+        let mut resolver = EntityResolver::new();
+        let abc = resolver.create_from(record("secop_ii", "CO1.1", "Empresa ABC", "800197268", 5));
+        let xyz = resolver.create_from(record("secop_ii", "CO1.2", "Empresa XYZ", "900123456", 3));
+        resolver.observe(
+            xyz,
+            record("secop_ii", "CO1.3", "Empresa XYZ Ltda", "900123456", 2),
+        );
+        resolver.refresh(xyz);
+
+        resolver.merge(abc, xyz).unwrap();
+        assert_eq!(resolver.len(), 1);
+        assert_eq!(resolver.canonical_id(xyz), Some(abc));
+        assert_eq!(resolver.get(&abc).unwrap().name, "Empresa XYZ Ltda");
+        assert_eq!(
+            resolver.get(&abc).unwrap().source_ids,
+            ["CO1.1", "CO1.2", "CO1.3"]
+        );
+        assert!(
+            matches!(resolver.resolve("900123456", ""), MatchResult::ExactMatch(id) if id == abc)
+        );
+        assert_eq!(
+            resolver.merge(abc, abc),
+            Err(MergeError::SameContractor(abc))
+        );
+
+        assert_eq!(resolver.unmerge(xyz), Ok(abc));
+        assert_eq!(resolver.len(), 2);
+        assert_eq!(resolver.canonical_id(xyz), Some(xyz));
+        assert_eq!(resolver.get(&abc).unwrap().name, "Empresa ABC");
+        assert_eq!(resolver.get(&abc).unwrap().source_ids, ["CO1.1"]);
+        assert_eq!(resolver.get(&xyz).unwrap().source_ids, ["CO1.2", "CO1.3"]);
+        assert!(
+            matches!(resolver.resolve("900123456", ""), MatchResult::ExactMatch(id) if id == xyz)
+        );
+        assert!(
+            matches!(resolver.resolve("800197268", ""), MatchResult::ExactMatch(id) if id == abc)
+        );
+        assert_eq!(resolver.unmerge(xyz), Err(MergeError::NotMerged(xyz)));
+    }
+
+    #[test]
+    fn test_unmerge_restores_nested_merges() {
+        let mut resolver = EntityResolver::new();
+        let a = resolver.create_from(record("secop_ii", "1", "Empresa A", "800000001", 3));
+        let b = resolver.create_from(record("secop_ii", "2", "Empresa B", "800000002", 2));
+        let c = resolver.create_from(record("secop_ii", "3", "Empresa C", "800000003", 1));
+
+        resolver.merge(b, c).unwrap();
+        resolver.merge(a, b).unwrap();
+        assert_eq!(resolver.canonical_id(c), Some(a));
+
+        // B comes back with C still merged into it
+        resolver.unmerge(b).unwrap();
+        assert_eq!(resolver.canonical_id(c), Some(b));
+        assert_eq!(resolver.get(&a).unwrap().source_ids, ["1"]);
+        assert_eq!(resolver.get(&b).unwrap().source_ids, ["2", "3"]);
+    }
+
+    #[test]
+    fn test_merge_registered_contractors() {
+        let mut resolver = EntityResolver::new();
+        let mut abc = Contractor::new("Empresa ABC".to_string(), "800197268".to_string());
+        abc.source_ids = vec!["CO1.1".to_string()];
+        let (abc_id, abc_name) = (abc.id, abc.name.clone());
+        let other = Contractor::new("Empresa ABC Ltda".to_string(), "".to_string());
+        let other_id = other.id;
+        resolver.register(abc);
+        resolver.register(other);
+
+        resolver.merge(abc_id, other_id).unwrap();
+        resolver.unmerge(other_id).unwrap();
+        assert_eq!(resolver.get(&abc_id).unwrap().name, abc_name);
+        assert_eq!(resolver.get(&abc_id).unwrap().source_ids, ["CO1.1"]);
+        assert_eq!(resolver.get(&other_id).unwrap().name, "Empresa ABC Ltda");
+    }
+}
@@ -1,6 +1,8 @@
+use crate::blocking::BlockingIndex;
 use crate::cleaner::DataCleaner;
 use crate::domain::Contractor;
-use std::collections::HashMap;
+use crate::merge::{Attribute, LineageEntry, SourceRecord, SurvivorshipRules};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use strsim::jaro_winkler;
 use uuid::Uuid;
 
@@ -21,11 +23,22 @@ pub enum MatchResult {
 /// Entity Resolution Engine for MDM
 pub struct EntityResolver {
     /// Index: legal_id -> Contractor UUID
-    id_index: HashMap<String, Uuid>,
+    pub(crate) id_index: HashMap<String, Uuid>,
     /// Index: normalized_name -> Contractor UUID
-    name_index: HashMap<String, Uuid>,
+    pub(crate) name_index: HashMap<String, Uuid>,
+    /// Trigram blocking over normalized names, narrows fuzzy candidates
+    pub(crate) blocking: BlockingIndex,
     /// All known contractors
-    contractors: HashMap<Uuid, Contractor>,
+    pub(crate) contractors: HashMap<Uuid, Contractor>,
+    /// Source records behind each golden record, keyed by source and source ID
+    pub(crate) records: HashMap<Uuid, BTreeMap<(String, String), SourceRecord>>,
+    /// Golden records merged into another: absorbed -> survivor
+    pub(crate) merged_into: HashMap<Uuid, Uuid>,
+    /// Append-only lineage log
+    pub(crate) lineage: Vec<LineageEntry>,
+    /// Attributes with a lineage entry, per golden record
+    pub(crate) traced: HashSet<(Uuid, Attribute)>,
+    pub(crate) rules: SurvivorshipRules,
 }
 
 impl EntityResolver {
@@ -33,7 +46,13 @@ impl EntityResolver {
         Self {
             id_index: HashMap::new(),
             name_index: HashMap::new(),
+            blocking: BlockingIndex::new(),
             contractors: HashMap::new(),
+            records: HashMap::new(),
+            merged_into: HashMap::new(),
+            lineage: Vec::new(),
+            traced: HashSet::new(),
+            rules: SurvivorshipRules::default(),
         }
     }
 
@@ -41,42 +60,60 @@ impl EntityResolver {
     pub fn register(&mut self, contractor: Contractor) {
         let id = contractor.id;
 
-        // Index by legal ID (NIT/Cédula)
-        self.id_index.insert(contractor.legal_id.clone(), id);
+        // Index by canonical legal ID (NIT/Cédula without check digit)
+        // Empty keys are not indexed, so records missing them never match each other
+        let legal_id = crate::cleaner::StandardCleaner::normalize_legal_id(&contractor.legal_id);
+        if !legal_id.is_empty() {
+            self.id_index.insert(legal_id, id);
+        }
 
         // Index by normalized name
         let normalized = crate::cleaner::StandardCleaner::normalize_business_name(&contractor.name);
-        self.name_index.insert(normalized, id);
+        if !normalized.is_empty() {
+            self.blocking.insert(&normalized, id);
+            self.name_index.insert(normalized, id);
+        }
 
         self.contractors.insert(id, contractor);
     }
 
     /// Resolve an entity: find existing or return NoMatch
+    ///
+    /// A name match is never taken over a golden record whose legal ID
+    /// differs from `legal_id` (e.g. two "CONSORCIO VIAL ..." with their own
+    /// NITs); when both IDs are known and differ, the records stay apart.
     pub fn resolve(&self, legal_id: &str, name: &str) -> MatchResult {
         // Step 1: Deterministic match by legal ID
-        let clean_id = legal_id.trim().replace("-", "").replace(".", "");
+        let clean_id = crate::cleaner::StandardCleaner::normalize_legal_id(legal_id);
         if let Some(&id) = self.id_index.get(&clean_id) {
             return MatchResult::ExactMatch(id);
         }
 
         // Step 2: Fuzzy match by name
         let normalized_name = crate::cleaner::StandardCleaner::normalize_business_name(name);
+        if normalized_name.is_empty() {
+            return MatchResult::NoMatch;
+        }
 
         // Check exact name match first
         if let Some(&id) = self.name_index.get(&normalized_name) {
-            return MatchResult::ExactMatch(id);
+            if !self.conflicts(&id, &clean_id) {
+                return MatchResult::ExactMatch(id);
+            }
         }
 
-        // Fuzzy search through all names
+        // Fuzzy search among the names sharing enough trigrams
         let mut best_match: Option<(Uuid, f64)> = None;
 
-        for (existing_name, &id) in &self.name_index {
+        for (existing_name, id) in self.blocking.candidates(&normalized_name) {
+            if self.conflicts(&id, &clean_id) {
+                continue;
+            }
             let similarity = jaro_winkler(&normalized_name, existing_name);
 
-            if similarity >= FUZZY_THRESHOLD {
-                if best_match.is_none() || similarity > best_match.unwrap().1 {
-                    best_match = Some((id, similarity));
-                }
+            if similarity >= FUZZY_THRESHOLD && best_match.is_none_or(|(_, best)| similarity > best)
+            {
+                best_match = Some((id, similarity));
             }
         }
 
@@ -86,6 +123,16 @@ impl EntityResolver {
         }
     }
 
+    /// Whether golden record `id` has a legal ID other than the canonical `legal_id`
+    fn conflicts(&self, id: &Uuid, legal_id: &str) -> bool {
+        !legal_id.is_empty()
+            && self.contractors.get(id).is_some_and(|contractor| {
+                let existing =
+                    crate::cleaner::StandardCleaner::normalize_legal_id(&contractor.legal_id);
+                !existing.is_empty() && existing != legal_id
+            })
+    }
+
     /// Get a contractor by UUID
     pub fn get(&self, id: &Uuid) -> Option<&Contractor> {
         self.contractors.get(id)
@@ -121,15 +168,33 @@ mod tests {
     fn test_exact_match_by_nit() {
         let mut resolver = EntityResolver::new();
 
+        let contractor = Contractor::new("Empresa ABC S.A.S.".to_string(), "900123456".to_string());
+        let expected_id = contractor.id;
+        resolver.register(contractor);
+
+        let result = resolver.resolve("900123456", "Empresa ABC");
+        assert!(matches!(result, MatchResult::ExactMatch(id) if id == expected_id));
+    }
+
+    #[test]
+    fn test_exact_match_across_nit_formats() {
+        let mut resolver = EntityResolver::new();
+
         let contractor = Contractor::new(
             "Empresa ABC S.A.S.".to_string(),
-            "900123456".to_string(),
+            "800.197.268-4".to_string(),
         );
         let expected_id = contractor.id;
         resolver.register(contractor);
 
-        let result = resolver.resolve("900123456", "Empresa ABC");
-        assert!(matches!(result, MatchResult::ExactMatch(id) if id == expected_id));
+        for legal_id in ["800197268", "800197268-4", "NIT 800.197.268", "8001972684"] {
+            let result = resolver.resolve(legal_id, "Otro Nombre Totalmente Distinto");
+            assert!(
+                matches!(result, MatchResult::ExactMatch(id) if id == expected_id),
+                "{}",
+                legal_id
+            );
+        }
     }
 
     #[test]
@@ -143,23 +208,183 @@ mod tests {
         let expected_id = contractor.id;
         resolver.register(contractor);
 
-        // Different NIT, slightly different name (typo/variation)
-        let result = resolver.resolve("999999999", "CONSTRUCTORA NACONAL"); // Missing 'I'
+        // No NIT, slightly different name (typo/variation)
+        let result = resolver.resolve("", "CONSTRUCTORA NACONAL"); // Missing 'I'
         assert!(matches!(result, MatchResult::FuzzyMatch { id, confidence }
             if id == expected_id && confidence > 0.85));
     }
 
     #[test]
-    fn test_no_match() {
+    fn test_different_legal_ids_never_match_by_name() {
         let mut resolver = EntityResolver::new();
 
         let contractor = Contractor::new(
-            "Empresa XYZ".to_string(),
-            "900999888".to_string(),
+            "CONSORCIO VIAL DEL CAUCA".to_string(),
+            "900111222".to_string(),
+        );
+        let expected_id = contractor.id;
+        resolver.register(contractor);
+
+        // Same name and a typo of it, each with another NIT
+        for name in ["CONSORCIO VIAL DEL CAUCA", "CONSORCIO VIAL DEL CAUKA"] {
+            let result = resolver.resolve("901222333", name);
+            assert!(matches!(result, MatchResult::NoMatch), "{}", name);
+        }
+
+        // Same NIT in another format, or no usable NIT, still matches
+        let result = resolver.resolve("900.111.222", "CONSORCIO VIAL DEL CAUCA");
+        assert!(matches!(result, MatchResult::ExactMatch(id) if id == expected_id));
+        let result = resolver.resolve("NO DEFINIDO", "CONSORCIO VIAL DEL CAUCA");
+        assert!(matches!(result, MatchResult::ExactMatch(id) if id == expected_id));
+    }
+
+    #[test]
+    fn test_placeholder_ids_do_not_match() {
+        let mut resolver = EntityResolver::new();
+
+        let contractor =
+            Contractor::new("Constructora Andina".to_string(), "NO DEFINIDO".to_string());
+        let id = contractor.id;
+        resolver.register(contractor);
+
+        for placeholder in ["NO DEFINIDO", "no definido", "N/A", "0"] {
+            let result = resolver.resolve(placeholder, "Hospital San Rafael");
+            assert!(matches!(result, MatchResult::NoMatch), "{}", placeholder);
+            assert_ne!(
+                Contractor::stable_id("Hospital San Rafael", placeholder),
+                id
+            );
+        }
+        // Without a usable ID the name still resolves
+        assert!(
+            matches!(resolver.resolve("N/A", "Constructora Andina"), MatchResult::ExactMatch(found) if found == id)
         );
+    }
+
+    #[test]
+    fn test_no_match() {
+        let mut resolver = EntityResolver::new();
+
+        let contractor = Contractor::new("Empresa XYZ".to_string(), "900999888".to_string());
         resolver.register(contractor);
 
         let result = resolver.resolve("111222333", "Totally Different Company");
         assert!(matches!(result, MatchResult::NoMatch));
     }
+
+    /// Deterministic generator for synthetic names and typos
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((self.0 >> 33) % bound as u64) as usize
+        }
+    }
+
+    fn synthetic_name(rng: &mut Lcg) -> String {
+        const HEAD: [&str; 8] = [
+            "CONSTRUCTORA",
+            "INVERSIONES",
+            "SERVICIOS",
+            "COMERCIALIZADORA",
+            "CONSORCIO",
+            "UNION TEMPORAL",
+            "FUNDACION",
+            "DISTRIBUIDORA",
+        ];
+        const BODY: [&str; 12] = [
+            "NACIONAL",
+            "DEL CARIBE",
+            "ANDINA",
+            "LOS ANDES",
+            "SAN JOSE",
+            "EL PROGRESO",
+            "DEL PACIFICO",
+            "INTEGRAL",
+            "MEDICA",
+            "VIAL",
+            "AMBIENTAL",
+            "TECNOLOGICA",
+        ];
+        const TAIL: [&str; 10] = [
+            "DE COLOMBIA",
+            "DEL CAUCA",
+            "DE NARINO",
+            "DE BOYACA",
+            "2020",
+            "GLOBAL",
+            "Y CIA",
+            "DEL HUILA",
+            "SUR",
+            "XXI",
+        ];
+        format!(
+            "{} {} {} {}",
+            HEAD[rng.next(HEAD.len())],
+            BODY[rng.next(BODY.len())],
+            TAIL[rng.next(TAIL.len())],
+            rng.next(1000)
+        )
+    }
+
+    fn with_typos(name: &str, rng: &mut Lcg) -> String {
+        let mut chars: Vec<char> = name.chars().collect();
+        for _ in 0..1 + rng.next(2) {
+            let at = rng.next(chars.len() - 1);
+            match rng.next(4) {
+                0 => chars[at] = (b'A' + rng.next(26) as u8) as char,
+                1 => {
+                    chars.remove(at);
+                }
+                2 => chars.insert(at, (b'A' + rng.next(26) as u8) as char),
+                _ => chars.swap(at, at + 1),
+            }
+        }
+        chars.into_iter().collect()
+    }
+
+    /// Best fuzzy similarity scoring every registered name
+    fn brute_force_best(resolver: &EntityResolver, name: &str) -> Option<f64> {
+        let normalized = crate::cleaner::StandardCleaner::normalize_business_name(name);
+        resolver
+            .name_index
+            .keys()
+            .map(|existing| jaro_winkler(&normalized, existing))
+            .filter(|s| *s >= FUZZY_THRESHOLD)
+            .reduce(f64::max)
+    }
+
+    #[test]
+    fn test_blocking_recall_matches_brute_force() {
+        let mut rng = Lcg(42);
+        let mut resolver = EntityResolver::new();
+        let mut names = Vec::new();
+        for i in 0..2_000 {
+            let name = synthetic_name(&mut rng);
+            resolver.register(Contractor::new(name.clone(), format!("9{:08}", i)));
+            names.push(name);
+        }
+
+        let mut queries: Vec<String> = (0..300)
+            .map(|_| with_typos(&names[rng.next(names.len())], &mut rng))
+            .collect();
+        queries.extend((0..50).map(|_| synthetic_name(&mut rng)));
+
+        let mut matched = 0;
+        for query in &queries {
+            let blocked = match resolver.resolve("", query) {
+                MatchResult::ExactMatch(_) => Some(1.0),
+                MatchResult::FuzzyMatch { confidence, .. } => Some(confidence),
+                MatchResult::NoMatch => None,
+            };
+            let expected = brute_force_best(&resolver, query);
+            assert_eq!(blocked, expected, "{}", query);
+            matched += blocked.is_some() as usize;
+        }
+        assert!(matched > 300);
+    }
 }
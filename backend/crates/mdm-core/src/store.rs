@@ -0,0 +1,809 @@
+//! Persistent golden-record store.
+//!
+//! [`EntityResolver::save`] writes the contractors, the legal-ID and name
+//! indexes, the source records and the lineage log to a directory, next to a
+//! `manifest.json` with the schema version and format;
+//! [`EntityResolver::load`] reads them back, so golden records and their UUIDs
+//! survive pipeline restarts. Parquet and JSON lines are always available,
+//! SQLite needs the `sqlite` feature.
+//!
+//! Layout of a store directory (the lineage log is JSON lines in both file
+//! formats):
+//!
+//! ```text
+//! manifest.json
+//! contractors.parquet | contractors.jsonl | golden_records.sqlite
+//! index.parquet       | index.jsonl
+//! records.parquet     | records.jsonl
+//! lineage.jsonl       | lineage.jsonl
+//! ```
+
+use crate::domain::Contractor;
+use crate::merge::{LineageEntry, SourceRecord};
+use crate::resolver::EntityResolver;
+use chrono::{DateTime, Utc};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use uuid::Uuid;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+/// Version of the on-disk layout, bumped on incompatible changes.
+/// Version 2 added source records and the lineage log; version 1 stores
+/// still load, without them.
+pub const SCHEMA_VERSION: u32 = 2;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const CONTRACTORS_FILE: &str = "contractors";
+const INDEX_FILE: &str = "index";
+const RECORDS_FILE: &str = "records";
+const LINEAGE_FILE: &str = "lineage.jsonl";
+
+/// On-disk format of a store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreFormat {
+    Parquet,
+    JsonLines,
+    /// Single SQLite database (`sqlite` feature)
+    Sqlite,
+}
+
+/// Contents of `manifest.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub schema_version: u32,
+    pub format: StoreFormat,
+    /// Number of golden records
+    pub contractors: usize,
+    pub saved_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Parquet error: {0}")]
+    Polars(#[from] PolarsError),
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("store schema version {found} is not supported (expected {expected})")]
+    UnsupportedSchema { found: u32, expected: u32 },
+    #[error("store format {0:?} is not enabled in this build")]
+    FormatUnavailable(StoreFormat),
+    #[error("invalid store record: {0}")]
+    Invalid(String),
+    #[error(
+        "an interrupted save left {0:?} and no store to recover; move it into place or remove it"
+    )]
+    InterruptedSave(PathBuf),
+}
+
+/// Index a persisted key belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum IndexKind {
+    LegalId,
+    Name,
+}
+
+impl IndexKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            IndexKind::LegalId => "legal_id",
+            IndexKind::Name => "name",
+        }
+    }
+
+    fn parse(raw: &str) -> Result<Self, StoreError> {
+        match raw {
+            "legal_id" => Ok(IndexKind::LegalId),
+            "name" => Ok(IndexKind::Name),
+            other => Err(StoreError::Invalid(format!(
+                "unknown index kind {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+/// One entry of the legal-ID or name index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct IndexEntry {
+    pub kind: IndexKind,
+    pub key: String,
+    pub contractor_id: Uuid,
+}
+
+/// A source record and the golden record it belongs to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RecordRow {
+    pub contractor_id: Uuid,
+    #[serde(flatten)]
+    pub record: SourceRecord,
+}
+
+/// Everything a store holds
+#[derive(Debug, Default)]
+pub(crate) struct Snapshot {
+    pub contractors: Vec<Contractor>,
+    pub index: Vec<IndexEntry>,
+    pub records: Vec<RecordRow>,
+    pub lineage: Vec<LineageEntry>,
+}
+
+impl EntityResolver {
+    /// Write the resolver's full state to `dir`, replacing its
+    /// previous contents. The store is built in a staging directory and
+    /// swapped in at the end, so a failed save leaves the previous one intact.
+    pub fn save(&self, dir: &Path, format: StoreFormat) -> Result<Manifest, StoreError> {
+        let snapshot = self.snapshot();
+
+        let staging = dir.with_extension("staging");
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging)?;
+        }
+        std::fs::create_dir_all(&staging)?;
+
+        match format {
+            StoreFormat::Parquet => {
+                write_parquet(
+                    &mut contractors_frame(&snapshot.contractors)?,
+                    &staging.join(format!("{}.parquet", CONTRACTORS_FILE)),
+                )?;
+                write_parquet(
+                    &mut index_frame(&snapshot.index)?,
+                    &staging.join(format!("{}.parquet", INDEX_FILE)),
+                )?;
+                write_parquet(
+                    &mut records_frame(&snapshot.records)?,
+                    &staging.join(format!("{}.parquet", RECORDS_FILE)),
+                )?;
+                write_json_lines(&staging.join(LINEAGE_FILE), &snapshot.lineage)?;
+            }
+            StoreFormat::JsonLines => {
+                write_json_lines(
+                    &staging.join(format!("{}.jsonl", CONTRACTORS_FILE)),
+                    &snapshot.contractors,
+                )?;
+                write_json_lines(
+                    &staging.join(format!("{}.jsonl", INDEX_FILE)),
+                    &snapshot.index,
+                )?;
+                write_json_lines(
+                    &staging.join(format!("{}.jsonl", RECORDS_FILE)),
+                    &snapshot.records,
+                )?;
+                write_json_lines(&staging.join(LINEAGE_FILE), &snapshot.lineage)?;
+            }
+            #[cfg(feature = "sqlite")]
+            StoreFormat::Sqlite => sqlite::write(&staging, &snapshot)?,
+            #[cfg(not(feature = "sqlite"))]
+            StoreFormat::Sqlite => return Err(StoreError::FormatUnavailable(format)),
+        }
+
+        let manifest = Manifest {
+            schema_version: SCHEMA_VERSION,
+            format,
+            contractors: snapshot.contractors.len(),
+            saved_at: Utc::now(),
+        };
+        std::fs::write(
+            staging.join(MANIFEST_FILE),
+            serde_json::to_vec_pretty(&manifest)?,
+        )?;
+        swap_into_place(&staging, dir)?;
+        Ok(manifest)
+    }
+
+    /// Load a store written by [`EntityResolver::save`], or start empty if
+    /// there is none yet.
+    ///
+    /// A save that crashed between its two renames leaves the previous store
+    /// at `dir.old`, which is loaded instead. A leftover `dir.staging` with no
+    /// store to fall back on is an error: starting empty would silently drop
+    /// every merge and lineage entry.
+    pub fn load(dir: &Path) -> Result<Self, StoreError> {
+        if dir.join(MANIFEST_FILE).exists() {
+            return Self::read_store(dir);
+        }
+        let previous = dir.with_extension("old");
+        if previous.join(MANIFEST_FILE).exists() {
+            return Self::read_store(&previous);
+        }
+        let staging = dir.with_extension("staging");
+        if staging.exists() {
+            return Err(StoreError::InterruptedSave(staging));
+        }
+        Ok(Self::new())
+    }
+
+    fn read_store(dir: &Path) -> Result<Self, StoreError> {
+        let manifest: Manifest = serde_json::from_slice(&std::fs::read(dir.join(MANIFEST_FILE))?)?;
+        if !(1..=SCHEMA_VERSION).contains(&manifest.schema_version) {
+            return Err(StoreError::UnsupportedSchema {
+                found: manifest.schema_version,
+                expected: SCHEMA_VERSION,
+            });
+        }
+        let with_records = manifest.schema_version >= 2;
+
+        let snapshot = match manifest.format {
+            StoreFormat::Parquet => Snapshot {
+                contractors: read_contractors(&read_parquet(
+                    &dir.join(format!("{}.parquet", CONTRACTORS_FILE)),
+                )?)?,
+                index: read_index(&read_parquet(&dir.join(format!("{}.parquet", INDEX_FILE)))?)?,
+                records: if with_records {
+                    read_records(&read_parquet(
+                        &dir.join(format!("{}.parquet", RECORDS_FILE)),
+                    )?)?
+                } else {
+                    Vec::new()
+                },
+                lineage: if with_records {
+                    read_json_lines(&dir.join(LINEAGE_FILE))?
+                } else {
+                    Vec::new()
+                },
+            },
+            StoreFormat::JsonLines => Snapshot {
+                contractors: read_json_lines(&dir.join(format!("{}.jsonl", CONTRACTORS_FILE)))?,
+                index: read_json_lines(&dir.join(format!("{}.jsonl", INDEX_FILE)))?,
+                records: if with_records {
+                    read_json_lines(&dir.join(format!("{}.jsonl", RECORDS_FILE)))?
+                } else {
+                    Vec::new()
+                },
+                lineage: if with_records {
+                    read_json_lines(&dir.join(LINEAGE_FILE))?
+                } else {
+                    Vec::new()
+                },
+            },
+            #[cfg(feature = "sqlite")]
+            StoreFormat::Sqlite => sqlite::read(dir)?,
+            #[cfg(not(feature = "sqlite"))]
+            StoreFormat::Sqlite => return Err(StoreError::FormatUnavailable(manifest.format)),
+        };
+        Self::from_snapshot(snapshot)
+    }
+
+    /// Owned copy of the resolver state, sorted so unchanged records produce
+    /// identical files
+    fn snapshot(&self) -> Snapshot {
+        let mut contractors: Vec<Contractor> = self.contractors.values().cloned().collect();
+        contractors.sort_by_key(|c| c.id);
+
+        let mut index: Vec<IndexEntry> = self
+            .id_index
+            .iter()
+            .map(|(key, &id)| (IndexKind::LegalId, key, id))
+            .chain(
+                self.name_index
+                    .iter()
+                    .map(|(key, &id)| (IndexKind::Name, key, id)),
+            )
+            .map(|(kind, key, contractor_id)| IndexEntry {
+                kind,
+                key: key.clone(),
+                contractor_id,
+            })
+            .collect();
+        index.sort_by(|a, b| (a.kind.as_str(), &a.key).cmp(&(b.kind.as_str(), &b.key)));
+
+        let mut records: Vec<RecordRow> = self
+            .records
+            .iter()
+            .flat_map(|(&contractor_id, records)| {
+                records.values().map(move |record| RecordRow {
+                    contractor_id,
+                    record: record.clone(),
+                })
+            })
+            .collect();
+        records.sort_by_key(|row| (row.contractor_id, row.record.key()));
+
+        Snapshot {
+            contractors,
+            index,
+            records,
+            lineage: self.lineage.clone(),
+        }
+    }
+
+    fn from_snapshot(snapshot: Snapshot) -> Result<Self, StoreError> {
+        let mut resolver = Self::new();
+        for contractor in snapshot.contractors {
+            resolver.contractors.insert(contractor.id, contractor);
+        }
+        for entry in snapshot.index {
+            if !resolver.contractors.contains_key(&entry.contractor_id) {
+                return Err(StoreError::Invalid(format!(
+                    "index key {:?} points to unknown contractor {}",
+                    entry.key, entry.contractor_id
+                )));
+            }
+            match entry.kind {
+                // Older stores also indexed placeholders ("NO DEFINIDO"), which
+                // no longer canonicalize to a key
+                IndexKind::LegalId => {
+                    if crate::cleaner::nit::canonical_legal_id(&entry.key) == entry.key {
+                        resolver.id_index.insert(entry.key, entry.contractor_id);
+                    }
+                }
+                IndexKind::Name => {
+                    resolver.blocking.insert(&entry.key, entry.contractor_id);
+                    resolver.name_index.insert(entry.key, entry.contractor_id);
+                }
+            }
+        }
+        for row in snapshot.records {
+            if !resolver.contractors.contains_key(&row.contractor_id) {
+                return Err(StoreError::Invalid(format!(
+                    "source record {:?} belongs to unknown contractor {}",
+                    row.record.source_id, row.contractor_id
+                )));
+            }
+            resolver
+                .records
+                .entry(row.contractor_id)
+                .or_default()
+                .insert(row.record.key(), row.record);
+        }
+        resolver.replay_lineage(snapshot.lineage);
+        Ok(resolver)
+    }
+}
+
+/// Replace `dir` with the fully written `staging` directory
+fn swap_into_place(staging: &Path, dir: &Path) -> std::io::Result<()> {
+    let previous = dir.with_extension("old");
+    if previous.exists() {
+        std::fs::remove_dir_all(&previous)?;
+    }
+    if dir.exists() {
+        std::fs::rename(dir, &previous)?;
+    }
+    std::fs::rename(staging, dir)?;
+    if previous.exists() {
+        std::fs::remove_dir_all(&previous)?;
+    }
+    Ok(())
+}
+
+fn write_json_lines<T: Serialize>(path: &Path, rows: &[T]) -> Result<(), StoreError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for row in rows {
+        serde_json::to_writer(&mut writer, row)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_json_lines<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Vec<T>, StoreError> {
+    let mut rows = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            rows.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(rows)
+}
+
+fn write_parquet(df: &mut DataFrame, path: &Path) -> PolarsResult<()> {
+    ParquetWriter::new(File::create(path)?)
+        .with_compression(ParquetCompression::Zstd(None))
+        .finish(df)?;
+    Ok(())
+}
+
+fn read_parquet(path: &Path) -> PolarsResult<DataFrame> {
+    ParquetReader::new(File::open(path)?).finish()
+}
+
+/// Golden records as a frame; `last_updated` is milliseconds since the epoch, UTC
+fn contractors_frame(contractors: &[Contractor]) -> PolarsResult<DataFrame> {
+    let source_ids: Vec<Series> = contractors
+        .iter()
+        .map(|c| Series::new(PlSmallStr::EMPTY, &c.source_ids))
+        .collect();
+    let source_ids = Series::new("source_ids".into(), source_ids)
+        .cast(&DataType::List(Box::new(DataType::String)))?;
+    let last_updated: Vec<i64> = contractors
+        .iter()
+        .map(|c| c.last_updated.timestamp_millis())
+        .collect();
+
+    DataFrame::new(vec![
+        Column::new(
+            "id".into(),
+            contractors
+                .iter()
+                .map(|c| c.id.to_string())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "name".into(),
+            contractors
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "legal_id".into(),
+            contractors
+                .iter()
+                .map(|c| c.legal_id.as_str())
+                .collect::<Vec<_>>(),
+        ),
+        source_ids.into(),
+        Series::new("last_updated".into(), last_updated)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+            .into(),
+        Column::new(
+            "confidence_score".into(),
+            contractors
+                .iter()
+                .map(|c| c.confidence_score)
+                .collect::<Vec<_>>(),
+        ),
+    ])
+}
+
+fn read_contractors(df: &DataFrame) -> Result<Vec<Contractor>, StoreError> {
+    let ids = df.column("id")?.str()?;
+    let names = df.column("name")?.str()?;
+    let legal_ids = df.column("legal_id")?.str()?;
+    let source_ids = df.column("source_ids")?.list()?;
+    let last_updated = df.column("last_updated")?.cast(&DataType::Int64)?;
+    let last_updated = last_updated.i64()?;
+    let confidence = df.column("confidence_score")?.f32()?;
+
+    (0..df.height())
+        .map(|row| {
+            let sources = match source_ids.get_as_series(row) {
+                Some(series) => series
+                    .str()?
+                    .into_iter()
+                    .flatten()
+                    .map(String::from)
+                    .collect(),
+                None => Vec::new(),
+            };
+            Ok(Contractor {
+                id: parse_uuid(ids.get(row))?,
+                name: names.get(row).unwrap_or_default().to_string(),
+                legal_id: legal_ids.get(row).unwrap_or_default().to_string(),
+                source_ids: sources,
+                last_updated: last_updated
+                    .get(row)
+                    .and_then(DateTime::from_timestamp_millis)
+                    .unwrap_or_default(),
+                confidence_score: confidence.get(row).unwrap_or(1.0),
+            })
+        })
+        .collect()
+}
+
+fn index_frame(index: &[IndexEntry]) -> PolarsResult<DataFrame> {
+    DataFrame::new(vec![
+        Column::new(
+            "kind".into(),
+            index.iter().map(|e| e.kind.as_str()).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "key".into(),
+            index.iter().map(|e| e.key.as_str()).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "contractor_id".into(),
+            index
+                .iter()
+                .map(|e| e.contractor_id.to_string())
+                .collect::<Vec<_>>(),
+        ),
+    ])
+}
+
+fn read_index(df: &DataFrame) -> Result<Vec<IndexEntry>, StoreError> {
+    let kinds = df.column("kind")?.str()?;
+    let keys = df.column("key")?.str()?;
+    let ids = df.column("contractor_id")?.str()?;
+
+    (0..df.height())
+        .map(|row| {
+            Ok(IndexEntry {
+                kind: IndexKind::parse(kinds.get(row).unwrap_or_default())?,
+                key: keys.get(row).unwrap_or_default().to_string(),
+                contractor_id: parse_uuid(ids.get(row))?,
+            })
+        })
+        .collect()
+}
+
+/// Source records as a frame; timestamps as in [`contractors_frame`]
+fn records_frame(rows: &[RecordRow]) -> PolarsResult<DataFrame> {
+    let text = |name: &str, value: fn(&RecordRow) -> String| {
+        Column::new(name.into(), rows.iter().map(value).collect::<Vec<_>>())
+    };
+    let observed_at: Vec<i64> = rows
+        .iter()
+        .map(|r| r.record.observed_at.timestamp_millis())
+        .collect();
+
+    DataFrame::new(vec![
+        text("contractor_id", |r| r.contractor_id.to_string()),
+        text("source", |r| r.record.source.clone()),
+        text("source_id", |r| r.record.source_id.clone()),
+        text("name", |r| r.record.name.clone()),
+        text("legal_id", |r| r.record.legal_id.clone()),
+        Series::new("observed_at".into(), observed_at)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+            .into(),
+        Column::new(
+            "confidence".into(),
+            rows.iter().map(|r| r.record.confidence).collect::<Vec<_>>(),
+        ),
+        text("origin", |r| r.record.origin.to_string()),
+    ])
+}
+
+fn read_records(df: &DataFrame) -> Result<Vec<RecordRow>, StoreError> {
+    let contractor_ids = df.column("contractor_id")?.str()?;
+    let sources = df.column("source")?.str()?;
+    let source_ids = df.column("source_id")?.str()?;
+    let names = df.column("name")?.str()?;
+    let legal_ids = df.column("legal_id")?.str()?;
+    let observed_at = df.column("observed_at")?.cast(&DataType::Int64)?;
+    let observed_at = observed_at.i64()?;
+    let confidence = df.column("confidence")?.f32()?;
+    let origins = df.column("origin")?.str()?;
+
+    (0..df.height())
+        .map(|row| {
+            Ok(RecordRow {
+                contractor_id: parse_uuid(contractor_ids.get(row))?,
+                record: SourceRecord {
+                    source: sources.get(row).unwrap_or_default().to_string(),
+                    source_id: source_ids.get(row).unwrap_or_default().to_string(),
+                    name: names.get(row).unwrap_or_default().to_string(),
+                    legal_id: legal_ids.get(row).unwrap_or_default().to_string(),
+                    observed_at: observed_at
+                        .get(row)
+                        .and_then(DateTime::from_timestamp_millis)
+                        .unwrap_or_default(),
+                    confidence: confidence.get(row).unwrap_or(1.0),
+                    origin: parse_uuid(origins.get(row))?,
+                },
+            })
+        })
+        .collect()
+}
+
+fn parse_uuid(raw: Option<&str>) -> Result<Uuid, StoreError> {
+    let raw = raw.unwrap_or_default();
+    Uuid::parse_str(raw).map_err(|_| StoreError::Invalid(format!("invalid UUID {:?}", raw)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::MatchResult;
+
+    fn sample_resolver() -> EntityResolver {
+        let mut resolver = EntityResolver::new();
+        let mut abc = Contractor::new(
+            "Empresa ABC S.A.S.".to_string(),
+            "800.197.268-4".to_string(),
+        );
+        abc.source_ids = vec!["CO1.1".to_string(), "CO1.2".to_string()];
+        resolver.register(abc);
+        resolver.register(Contractor::new("Juan Perez".to_string(), "".to_string()));
+        resolver
+    }
+
+    fn temp_store(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("mdm_store_{}_{}", name, Uuid::new_v4()))
+    }
+
+    /// Every format enabled in this build
+    fn formats() -> Vec<StoreFormat> {
+        let mut formats = vec![StoreFormat::Parquet, StoreFormat::JsonLines];
+        if cfg!(feature = "sqlite") {
+            formats.push(StoreFormat::Sqlite);
+        }
+        formats
+    }
+
+    #[test]
+    fn test_stable_ids() {
+        let first = Contractor::new("Empresa ABC".to_string(), "800197268-4".to_string());
+        let second = Contractor::new(
+            "EMPRESA ABC S.A.S.".to_string(),
+            "NIT 800.197.268".to_string(),
+        );
+        assert_eq!(first.id, second.id);
+        assert_eq!(
+            Contractor::new("Juan Perez".to_string(), "".to_string()).id,
+            Contractor::new("JUAN PEREZ".to_string(), " ".to_string()).id
+        );
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        for format in formats() {
+            let dir = temp_store("round_trip");
+            let resolver = sample_resolver();
+            let manifest = resolver.save(&dir, format).unwrap();
+            assert_eq!(manifest.schema_version, SCHEMA_VERSION);
+            assert_eq!(manifest.contractors, 2);
+
+            let loaded = EntityResolver::load(&dir).unwrap();
+            assert_eq!(loaded.len(), 2);
+            let id = match loaded.resolve("800197268", "") {
+                MatchResult::ExactMatch(id) => id,
+                other => panic!("{:?}: {:?}", format, other),
+            };
+            let abc = loaded.get(&id).unwrap();
+            assert_eq!(abc.source_ids, ["CO1.1", "CO1.2"]);
+            assert_eq!(
+                abc.last_updated.timestamp_millis(),
+                resolver.get(&id).unwrap().last_updated.timestamp_millis()
+            );
+            assert!(matches!(
+                loaded.resolve("", "JUAN PERES"),
+                MatchResult::FuzzyMatch { .. }
+            ));
+
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_merge_state_round_trip() {
+        use crate::merge::SourceRecord;
+
+        for format in formats() {
+            let dir = temp_store("merge_state");
+            let mut resolver = EntityResolver::new();
+            let abc = resolver.create_from(SourceRecord::new(
+                "secop_ii",
+                "CO1.1",
+                "Empresa ABC",
+                "800197268",
+            ));
+            let xyz = resolver.create_from(SourceRecord::new(
+                "secop_ii",
+                "CO1.2",
+                "Empresa XYZ",
+                "900123456",
+            ));
+            resolver.merge(abc, xyz).unwrap();
+            let manifest = resolver.save(&dir, format).unwrap();
+            assert_eq!(manifest.contractors, 1);
+
+            let mut loaded = EntityResolver::load(&dir).unwrap();
+            assert_eq!(loaded.len(), 1);
+            assert_eq!(loaded.records(&abc).count(), 2);
+            assert_eq!(loaded.lineage(), resolver.lineage());
+            assert_eq!(loaded.canonical_id(xyz), Some(abc));
+
+            assert_eq!(loaded.unmerge(xyz), Ok(abc), "{:?}", format);
+            assert_eq!(loaded.get(&xyz).unwrap().name, "Empresa XYZ");
+            assert!(
+                matches!(loaded.resolve("900123456", ""), MatchResult::ExactMatch(id) if id == xyz)
+            );
+
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    #[test]
+    fn test_sqlite_unavailable() {
+        let dir = temp_store("no_sqlite");
+        assert!(matches!(
+            sample_resolver().save(&dir, StoreFormat::Sqlite),
+            Err(StoreError::FormatUnavailable(StoreFormat::Sqlite))
+        ));
+        assert!(!dir.exists());
+        std::fs::remove_dir_all(dir.with_extension("staging")).ok();
+    }
+
+    #[test]
+    fn test_load_schema_v1_fixture() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/store_v1");
+        let loaded = EntityResolver::load(&fixture).unwrap();
+        assert_eq!(loaded.len(), 2);
+
+        let abc = Uuid::parse_str("6f1f4c52-3b7e-5a0d-8c21-4e9a7b3d2f10").unwrap();
+        let perez = Uuid::parse_str("0c8d2e91-7a64-5f3b-b1e0-93d5c7a8e642").unwrap();
+        assert!(
+            matches!(loaded.resolve("NIT 800.197.268", ""), MatchResult::ExactMatch(id) if id == abc)
+        );
+        assert!(
+            matches!(loaded.resolve("", "JUAN PERES"), MatchResult::FuzzyMatch { id, .. } if id == perez)
+        );
+        assert_eq!(
+            loaded.get(&abc).unwrap().source_ids,
+            ["CO1.PCCNTR.1", "CO1.PCCNTR.2"]
+        );
+        // Version 1 has no source records or lineage
+        assert_eq!(loaded.records(&abc).count(), 0);
+        assert!(loaded.lineage().is_empty());
+        // Its placeholder legal-ID key is no longer a match
+        assert!(matches!(
+            loaded.resolve("NO DEFINIDO", "Hospital San Rafael"),
+            MatchResult::NoMatch
+        ));
+
+        // Saving upgrades it to the current version
+        let dir = temp_store("upgrade");
+        let manifest = loaded.save(&dir, StoreFormat::JsonLines).unwrap();
+        assert_eq!(manifest.schema_version, SCHEMA_VERSION);
+        assert_eq!(EntityResolver::load(&dir).unwrap().len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_after_interrupted_save() {
+        let dir = temp_store("interrupted");
+        let previous = dir.with_extension("old");
+        let staging = dir.with_extension("staging");
+        sample_resolver().save(&dir, StoreFormat::Parquet).unwrap();
+
+        // Crash after moving the store aside, before the new one is in place
+        std::fs::rename(&dir, &previous).unwrap();
+        std::fs::create_dir_all(&staging).unwrap();
+        let loaded = EntityResolver::load(&dir).unwrap();
+        assert_eq!(loaded.len(), 2);
+
+        // The next save replaces both leftovers
+        loaded.save(&dir, StoreFormat::Parquet).unwrap();
+        assert!(!previous.exists() && !staging.exists());
+        assert_eq!(EntityResolver::load(&dir).unwrap().len(), 2);
+
+        // A staging directory and nothing to recover fails loudly
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::create_dir_all(&staging).unwrap();
+        assert!(
+            matches!(EntityResolver::load(&dir), Err(StoreError::InterruptedSave(path)) if path == staging)
+        );
+
+        std::fs::remove_dir_all(&staging).unwrap();
+    }
+
+    #[test]
+    fn test_load_missing_and_unsupported() {
+        let dir = temp_store("versions");
+        assert!(EntityResolver::load(&dir).unwrap().is_empty());
+
+        EntityResolver::new()
+            .save(&dir, StoreFormat::Parquet)
+            .unwrap();
+        assert!(EntityResolver::load(&dir).unwrap().is_empty());
+
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let mut manifest: Manifest =
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        manifest.schema_version = SCHEMA_VERSION + 1;
+        std::fs::write(&manifest_path, serde_json::to_vec(&manifest).unwrap()).unwrap();
+        assert!(matches!(
+            EntityResolver::load(&dir),
+            Err(StoreError::UnsupportedSchema { .. })
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,305 @@
+//! SQLite backend of the golden-record store (`sqlite` feature).
+//!
+//! Everything lives in one database: `meta` holds the schema version,
+//! `contractors` the golden records (source IDs as a JSON array, timestamps
+//! as RFC 3339), `index_keys` the legal-ID and name indexes,
+//! `source_records` the records behind each golden record and `lineage` the
+//! lineage log (one JSON entry per row, in order).
+
+use super::{IndexEntry, IndexKind, RecordRow, Snapshot, StoreError, SCHEMA_VERSION};
+use crate::domain::Contractor;
+use crate::merge::SourceRecord;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OpenFlags};
+use std::path::Path;
+
+const DB_FILE: &str = "golden_records.sqlite";
+
+const SCHEMA: &str = "
+    CREATE TABLE meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE contractors (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        legal_id TEXT NOT NULL,
+        source_ids TEXT NOT NULL,
+        last_updated TEXT NOT NULL,
+        confidence_score REAL NOT NULL
+    );
+    CREATE TABLE index_keys (
+        kind TEXT NOT NULL,
+        key TEXT NOT NULL,
+        contractor_id TEXT NOT NULL REFERENCES contractors (id),
+        PRIMARY KEY (kind, key)
+    );
+    CREATE TABLE source_records (
+        contractor_id TEXT NOT NULL REFERENCES contractors (id),
+        source TEXT NOT NULL,
+        source_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        legal_id TEXT NOT NULL,
+        observed_at TEXT NOT NULL,
+        confidence REAL NOT NULL,
+        origin TEXT NOT NULL
+    );
+    CREATE TABLE lineage (
+        seq INTEGER PRIMARY KEY,
+        entry TEXT NOT NULL
+    );
+";
+
+fn parse_timestamp(raw: &str) -> Result<DateTime<Utc>, StoreError> {
+    DateTime::parse_from_rfc3339(raw)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| StoreError::Invalid(format!("timestamp {:?}", raw)))
+}
+
+pub(super) fn write(dir: &Path, snapshot: &Snapshot) -> Result<(), StoreError> {
+    let mut conn = Connection::open(dir.join(DB_FILE))?;
+    conn.execute_batch(SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO meta (key, value) VALUES ('schema_version', ?1)",
+        params![SCHEMA_VERSION.to_string()],
+    )?;
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO contractors (id, name, legal_id, source_ids, last_updated, confidence_score)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for contractor in &snapshot.contractors {
+            insert.execute(params![
+                contractor.id.to_string(),
+                contractor.name,
+                contractor.legal_id,
+                serde_json::to_string(&contractor.source_ids)?,
+                contractor.last_updated.to_rfc3339(),
+                f64::from(contractor.confidence_score),
+            ])?;
+        }
+
+        let mut insert =
+            tx.prepare("INSERT INTO index_keys (kind, key, contractor_id) VALUES (?1, ?2, ?3)")?;
+        for entry in &snapshot.index {
+            insert.execute(params![
+                entry.kind.as_str(),
+                entry.key,
+                entry.contractor_id.to_string()
+            ])?;
+        }
+
+        let mut insert = tx.prepare(
+            "INSERT INTO source_records
+                (contractor_id, source, source_id, name, legal_id, observed_at, confidence, origin)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for row in &snapshot.records {
+            let record = &row.record;
+            insert.execute(params![
+                row.contractor_id.to_string(),
+                record.source,
+                record.source_id,
+                record.name,
+                record.legal_id,
+                record.observed_at.to_rfc3339(),
+                f64::from(record.confidence),
+                record.origin.to_string(),
+            ])?;
+        }
+
+        let mut insert = tx.prepare("INSERT INTO lineage (entry) VALUES (?1)")?;
+        for entry in &snapshot.lineage {
+            insert.execute(params![serde_json::to_string(entry)?])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub(super) fn read(dir: &Path) -> Result<Snapshot, StoreError> {
+    let conn = Connection::open_with_flags(dir.join(DB_FILE), OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let version: String = conn.query_row(
+        "SELECT value FROM meta WHERE key = 'schema_version'",
+        [],
+        |row| row.get(0),
+    )?;
+    let found = version
+        .parse::<u32>()
+        .map_err(|_| StoreError::Invalid(format!("schema version {:?}", version)))?;
+    if !(1..=SCHEMA_VERSION).contains(&found) {
+        return Err(StoreError::UnsupportedSchema {
+            found,
+            expected: SCHEMA_VERSION,
+        });
+    }
+
+    let mut select = conn.prepare(
+        "SELECT id, name, legal_id, source_ids, last_updated, confidence_score
+         FROM contractors ORDER BY id",
+    )?;
+    let rows = select.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, f64>(5)?,
+        ))
+    })?;
+
+    let mut contractors = Vec::new();
+    for row in rows {
+        let (id, name, legal_id, source_ids, last_updated, confidence_score) = row?;
+        contractors.push(Contractor {
+            id: super::parse_uuid(Some(&id))?,
+            name,
+            legal_id,
+            source_ids: serde_json::from_str(&source_ids)?,
+            last_updated: parse_timestamp(&last_updated)?,
+            confidence_score: confidence_score as f32,
+        });
+    }
+
+    let mut select =
+        conn.prepare("SELECT kind, key, contractor_id FROM index_keys ORDER BY kind, key")?;
+    let rows = select.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+
+    let mut index = Vec::new();
+    for row in rows {
+        let (kind, key, contractor_id) = row?;
+        index.push(IndexEntry {
+            kind: IndexKind::parse(&kind)?,
+            key,
+            contractor_id: super::parse_uuid(Some(&contractor_id))?,
+        });
+    }
+
+    let mut snapshot = Snapshot {
+        contractors,
+        index,
+        ..Snapshot::default()
+    };
+    // Version 1 databases have no source records or lineage
+    if found < 2 {
+        return Ok(snapshot);
+    }
+
+    let mut select = conn.prepare(
+        "SELECT contractor_id, source, source_id, name, legal_id, observed_at, confidence, origin
+         FROM source_records ORDER BY contractor_id, source, source_id",
+    )?;
+    let rows = select.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            SourceRecord {
+                source: row.get(1)?,
+                source_id: row.get(2)?,
+                name: row.get(3)?,
+                legal_id: row.get(4)?,
+                observed_at: Default::default(),
+                confidence: row.get::<_, f64>(6)? as f32,
+                origin: Default::default(),
+            },
+            row.get::<_, String>(5)?,
+            row.get::<_, String>(7)?,
+        ))
+    })?;
+    for row in rows {
+        let (contractor_id, mut record, observed_at, origin) = row?;
+        record.observed_at = parse_timestamp(&observed_at)?;
+        record.origin = super::parse_uuid(Some(&origin))?;
+        snapshot.records.push(RecordRow {
+            contractor_id: super::parse_uuid(Some(&contractor_id))?,
+            record,
+        });
+    }
+
+    let mut select = conn.prepare("SELECT entry FROM lineage ORDER BY seq")?;
+    let entries = select.query_map([], |row| row.get::<_, String>(0))?;
+    for entry in entries {
+        snapshot.lineage.push(serde_json::from_str(&entry?)?);
+    }
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::EntityResolver;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("mdm_sqlite_{}_{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn merged_snapshot() -> Snapshot {
+        let mut resolver = EntityResolver::new();
+        let abc = resolver.create_from(SourceRecord::new(
+            "secop_ii",
+            "CO1.1",
+            "Empresa ABC",
+            "800197268",
+        ));
+        let xyz = resolver.create_from(SourceRecord::new(
+            "secop_ii",
+            "CO1.2",
+            "Empresa XYZ",
+            "900123456",
+        ));
+        resolver.merge(abc, xyz).unwrap();
+        resolver.snapshot()
+    }
+
+    #[test]
+    fn test_write_read_round_trip() {
+        let dir = temp_dir("round_trip");
+        let snapshot = merged_snapshot();
+        assert_eq!(snapshot.records.len(), 2);
+        assert!(!snapshot.lineage.is_empty());
+        write(&dir, &snapshot).unwrap();
+
+        let loaded = read(&dir).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded.contractors).unwrap(),
+            serde_json::to_value(&snapshot.contractors).unwrap()
+        );
+        assert_eq!(loaded.index, snapshot.index);
+        assert_eq!(loaded.records, snapshot.records);
+        assert_eq!(loaded.lineage, snapshot.lineage);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_schema_v1_database() {
+        let dir = temp_dir("v1");
+        write(&dir, &merged_snapshot()).unwrap();
+        Connection::open(dir.join(DB_FILE))
+            .unwrap()
+            .execute(
+                "UPDATE meta SET value = '1' WHERE key = 'schema_version'",
+                [],
+            )
+            .unwrap();
+
+        let loaded = read(&dir).unwrap();
+        assert_eq!(loaded.contractors.len(), 1);
+        assert!(loaded.records.is_empty());
+        assert!(loaded.lineage.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
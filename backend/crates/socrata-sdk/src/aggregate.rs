@@ -0,0 +1,337 @@
+//! Server-side aggregation with SoQL `$group`.
+//!
+//! Socrata returns every aggregate as a JSON string (`"count": "1234"`), so
+//! [`AggregateRow`] parses metrics into `f64` and keeps group keys as text.
+//! For custom row structs, the [`number`] and [`optional_number`] helpers
+//! can be used with `#[serde(deserialize_with = ...)]`.
+
+use crate::{Condition, PaginationOptions, SocrataClient, SocrataError, SoqlQuery, SortOrder};
+use futures_util::TryStreamExt;
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// An aggregate function applied to a column
+#[derive(Debug, Clone, PartialEq)]
+pub enum Aggregate {
+    /// `count(*)`
+    Count,
+    /// `count(DISTINCT column)`
+    CountDistinct(String),
+    Sum(String),
+    Avg(String),
+    Min(String),
+    Max(String),
+}
+
+impl Aggregate {
+    fn to_soql(&self) -> String {
+        match self {
+            Aggregate::Count => "count(*)".to_string(),
+            Aggregate::CountDistinct(column) => format!("count(DISTINCT {})", column),
+            Aggregate::Sum(column) => format!("sum({})", column),
+            Aggregate::Avg(column) => format!("avg({})", column),
+            Aggregate::Min(column) => format!("min({})", column),
+            Aggregate::Max(column) => format!("max({})", column),
+        }
+    }
+}
+
+/// Builder for a grouped aggregate query.
+///
+/// ```
+/// use socrata_sdk::AggregateQuery;
+///
+/// let query = AggregateQuery::new()
+///     .group_by("departamento")
+///     .group_by_month("fecha_de_firma", "mes")
+///     .count("contratos")
+///     .sum("valor_del_contrato", "valor_total")
+///     .to_soql();
+/// assert_eq!(
+///     query.to_params()[0].1,
+///     "departamento, date_trunc_ym(fecha_de_firma) AS mes, \
+///      count(*) AS contratos, sum(valor_del_contrato) AS valor_total"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AggregateQuery {
+    /// `(expression, alias)` pairs
+    groups: Vec<(String, String)>,
+    /// `(aggregate, alias)` pairs
+    metrics: Vec<(Aggregate, String)>,
+    filter: Option<Condition>,
+    having: Option<Condition>,
+    order: Vec<(String, SortOrder)>,
+    limit: Option<u32>,
+}
+
+impl AggregateQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Group by a column, keyed by its own name.
+    pub fn group_by(mut self, column: &str) -> Self {
+        self.groups.push((column.to_string(), column.to_string()));
+        self
+    }
+
+    /// Group by calendar month of a date column (`date_trunc_ym`).
+    pub fn group_by_month(mut self, column: &str, alias: &str) -> Self {
+        self.groups
+            .push((format!("date_trunc_ym({})", column), alias.to_string()));
+        self
+    }
+
+    /// Group by year of a date column (`date_trunc_y`).
+    pub fn group_by_year(mut self, column: &str, alias: &str) -> Self {
+        self.groups
+            .push((format!("date_trunc_y({})", column), alias.to_string()));
+        self
+    }
+
+    pub fn metric(mut self, aggregate: Aggregate, alias: &str) -> Self {
+        self.metrics.push((aggregate, alias.to_string()));
+        self
+    }
+
+    pub fn count(self, alias: &str) -> Self {
+        self.metric(Aggregate::Count, alias)
+    }
+
+    pub fn sum(self, column: &str, alias: &str) -> Self {
+        self.metric(Aggregate::Sum(column.to_string()), alias)
+    }
+
+    pub fn avg(self, column: &str, alias: &str) -> Self {
+        self.metric(Aggregate::Avg(column.to_string()), alias)
+    }
+
+    /// Add a `$where` condition applied before grouping.
+    pub fn filter(mut self, condition: Condition) -> Self {
+        self.filter = Some(match self.filter.take() {
+            Some(existing) => existing.and(condition),
+            None => condition,
+        });
+        self
+    }
+
+    /// Add a `$having` condition on aggregates (e.g. `Condition::gt("count(*)", 10)`).
+    pub fn having(mut self, condition: Condition) -> Self {
+        self.having = Some(match self.having.take() {
+            Some(existing) => existing.and(condition),
+            None => condition,
+        });
+        self
+    }
+
+    /// Sort by an alias (group or metric).
+    pub fn order_by(mut self, alias: &str, direction: SortOrder) -> Self {
+        self.order.push((alias.to_string(), direction));
+        self
+    }
+
+    /// Return at most `limit` groups.
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn group_expression(&self, alias: &str) -> Option<&str> {
+        self.groups
+            .iter()
+            .find(|(_, a)| a == alias)
+            .map(|(expression, _)| expression.as_str())
+    }
+
+    /// The equivalent [`SoqlQuery`].
+    pub fn to_soql(&self) -> SoqlQuery {
+        let select = self
+            .groups
+            .iter()
+            .map(|(expression, alias)| {
+                if expression == alias {
+                    expression.clone()
+                } else {
+                    format!("{} AS {}", expression, alias)
+                }
+            })
+            .chain(
+                self.metrics
+                    .iter()
+                    .map(|(aggregate, alias)| format!("{} AS {}", aggregate.to_soql(), alias)),
+            );
+
+        let mut query = SoqlQuery::new()
+            .select(select)
+            .group_by(self.groups.iter().map(|(expression, _)| expression));
+        if let Some(filter) = &self.filter {
+            query = query.filter(filter.clone());
+        }
+        if let Some(having) = &self.having {
+            query = query.having(having.clone());
+        }
+        for (alias, direction) in &self.order {
+            // Group aliases are not valid sort keys; sort by their expression
+            let key = self.group_expression(alias).unwrap_or(alias);
+            query = query.order_by(key, *direction);
+        }
+        if let Some(limit) = self.limit {
+            query = query.limit(limit);
+        }
+        query
+    }
+}
+
+/// One row of an aggregate result
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AggregateRow {
+    /// Group values by alias (`None` for rows where the column is null)
+    pub keys: BTreeMap<String, Option<String>>,
+    /// Metric values by alias (absent when the server returned null)
+    pub values: BTreeMap<String, f64>,
+}
+
+impl AggregateRow {
+    pub fn key(&self, alias: &str) -> Option<&str> {
+        self.keys.get(alias).and_then(|k| k.as_deref())
+    }
+
+    pub fn value(&self, alias: &str) -> Option<f64> {
+        self.values.get(alias).copied()
+    }
+
+    fn from_json(query: &AggregateQuery, mut row: Map<String, Value>) -> Result<Self, SocrataError> {
+        let mut parsed = AggregateRow::default();
+        for (_, alias) in &query.groups {
+            let key = match row.remove(alias) {
+                Some(Value::String(s)) => Some(s),
+                Some(Value::Null) | None => None,
+                Some(other) => Some(other.to_string()),
+            };
+            parsed.keys.insert(alias.clone(), key);
+        }
+        for (_, alias) in &query.metrics {
+            if let Some(value) = row.remove(alias) {
+                if let Some(number) = optional_number(value)? {
+                    parsed.values.insert(alias.clone(), number);
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Deserialize a number Socrata sent as a string (or a plain JSON number).
+pub fn number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    optional_number(value)
+        .map_err(de::Error::custom)?
+        .ok_or_else(|| de::Error::custom("expected a number, got null"))
+}
+
+/// Like [`number`], mapping `null` to `None`.
+pub fn optional_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<f64>, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    match value {
+        Value::Null => Ok(None),
+        Value::Number(n) => Ok(n.as_f64()),
+        Value::String(s) => s
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| de::Error::custom(format!("invalid number {:?}", s))),
+        other => Err(de::Error::custom(format!("expected a number, got {}", other))),
+    }
+}
+
+impl SocrataClient {
+    /// Run an aggregate query, paging through every group.
+    pub async fn aggregate(
+        &self,
+        dataset_id: &str,
+        query: &AggregateQuery,
+    ) -> Result<Vec<AggregateRow>, SocrataError> {
+        let rows: Vec<Map<String, Value>> = self.aggregate_as(dataset_id, query).await?;
+        rows.into_iter()
+            .map(|row| AggregateRow::from_json(query, row))
+            .collect()
+    }
+
+    /// Run an aggregate query into caller-defined rows.
+    pub async fn aggregate_as<T: DeserializeOwned>(
+        &self,
+        dataset_id: &str,
+        query: &AggregateQuery,
+    ) -> Result<Vec<T>, SocrataError> {
+        let soql = query.to_soql();
+        if query.groups.is_empty() {
+            // A single row; ungrouped aggregates cannot be sorted by `:id`
+            return self.fetch(dataset_id, &soql).await;
+        }
+        self.stream(dataset_id, soql, PaginationOptions::default())
+            .try_collect()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_soql_orders_groups_by_expression() {
+        let query = AggregateQuery::new()
+            .group_by("nit_entidad")
+            .group_by_year("fecha_de_firma", "anio")
+            .sum("valor_del_contrato", "total")
+            .metric(Aggregate::CountDistinct("nit_contratista".into()), "contratistas")
+            .having(Condition::gt("count(*)", 10_i64))
+            .order_by("anio", SortOrder::Asc)
+            .order_by("total", SortOrder::Desc);
+        let params = query.to_soql().to_params();
+
+        assert_eq!(
+            params,
+            vec![
+                (
+                    "$select",
+                    "nit_entidad, date_trunc_y(fecha_de_firma) AS anio, \
+                     sum(valor_del_contrato) AS total, \
+                     count(DISTINCT nit_contratista) AS contratistas"
+                        .to_string()
+                ),
+                (
+                    "$order",
+                    "date_trunc_y(fecha_de_firma) ASC, total DESC".to_string()
+                ),
+                (
+                    "$group",
+                    "nit_entidad, date_trunc_y(fecha_de_firma)".to_string()
+                ),
+                ("$having", "count(*) > 10".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_row_parses_string_numbers() {
+        let query = AggregateQuery::new()
+            .group_by("departamento")
+            .count("contratos")
+            .sum("valor_del_contrato", "total");
+        let row = serde_json::json!({ "contratos": "42", "total": null });
+        let row = AggregateRow::from_json(&query, row.as_object().unwrap().clone()).unwrap();
+
+        assert_eq!(row.key("departamento"), None);
+        assert_eq!(row.value("contratos"), Some(42.0));
+        assert_eq!(row.value("total"), None);
+
+        let bad = serde_json::json!({ "departamento": "Cauca", "contratos": "n/a" });
+        assert!(AggregateRow::from_json(&query, bad.as_object().unwrap().clone()).is_err());
+    }
+}
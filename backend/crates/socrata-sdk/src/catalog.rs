@@ -0,0 +1,231 @@
+//! Dataset discovery through the Socrata catalog API (`/api/catalog/v1`).
+//!
+//! Searches are scoped to the client's own domain, so related datasets
+//! (sanctions, public officials, ...) can be found by keyword instead of
+//! hard-coding their 4x4 IDs.
+
+use crate::query::QUERY_VALUE;
+use crate::{SocrataClient, SocrataError};
+use percent_encoding::utf8_percent_encode;
+use serde::{Deserialize, Serialize};
+
+/// A catalog search, built like [`SoqlQuery`](crate::SoqlQuery)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CatalogQuery {
+    text: Option<String>,
+    categories: Vec<String>,
+    tags: Vec<String>,
+    only: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+impl CatalogQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Full-text search over names, descriptions and column names (`q`).
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Restrict to a domain category. Repeated calls match any of them.
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.categories.push(category.into());
+        self
+    }
+
+    /// Restrict to a domain tag. Repeated calls match any of them.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Restrict to one asset type ("dataset", "chart", "map", ...).
+    pub fn only(mut self, asset_type: impl Into<String>) -> Self {
+        self.only = Some(asset_type.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Unencoded `(parameter, value)` pairs, scoped to `domain`.
+    pub fn to_params(&self, domain: &str) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("domains", domain.to_string()),
+            ("search_context", domain.to_string()),
+        ];
+        if let Some(text) = &self.text {
+            params.push(("q", text.clone()));
+        }
+        params.extend(self.categories.iter().map(|c| ("categories", c.clone())));
+        params.extend(self.tags.iter().map(|t| ("tags", t.clone())));
+        if let Some(only) = &self.only {
+            params.push(("only", only.clone()));
+        }
+        if let Some(limit) = self.limit {
+            params.push(("limit", limit.to_string()));
+        }
+        if let Some(offset) = self.offset {
+            params.push(("offset", offset.to_string()));
+        }
+        params
+    }
+}
+
+/// A dataset found by [`SocrataClient::search_catalog`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Asset type ("dataset", "chart", ...)
+    pub asset_type: Option<String>,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    /// API field names of the columns
+    pub columns: Vec<String>,
+    /// RFC 3339 timestamp of the last data change
+    pub data_updated_at: Option<String>,
+    /// RFC 3339 timestamp of the last metadata change
+    pub metadata_updated_at: Option<String>,
+    pub permalink: Option<String>,
+}
+
+/// One page of catalog results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogPage {
+    pub results: Vec<CatalogEntry>,
+    /// Total matches across all pages
+    pub total: u64,
+}
+
+#[derive(Deserialize)]
+struct RawPage {
+    #[serde(default)]
+    results: Vec<RawResult>,
+    #[serde(rename = "resultSetSize", default)]
+    result_set_size: u64,
+}
+
+#[derive(Deserialize)]
+struct RawResult {
+    resource: RawResource,
+    #[serde(default)]
+    classification: RawClassification,
+    #[serde(default)]
+    permalink: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawResource {
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(rename = "type", default)]
+    asset_type: Option<String>,
+    #[serde(default)]
+    columns_field_name: Vec<String>,
+    #[serde(default)]
+    data_updated_at: Option<String>,
+    #[serde(default)]
+    metadata_updated_at: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawClassification {
+    #[serde(default)]
+    domain_category: Option<String>,
+    #[serde(default)]
+    domain_tags: Vec<String>,
+}
+
+impl From<RawResult> for CatalogEntry {
+    fn from(raw: RawResult) -> Self {
+        // The catalog returns empty descriptions as ""
+        let description = raw.resource.description.filter(|d| !d.is_empty());
+        Self {
+            id: raw.resource.id,
+            name: raw.resource.name,
+            description,
+            asset_type: raw.resource.asset_type,
+            category: raw.classification.domain_category,
+            tags: raw.classification.domain_tags,
+            columns: raw.resource.columns_field_name,
+            data_updated_at: raw.resource.data_updated_at,
+            metadata_updated_at: raw.resource.metadata_updated_at,
+            permalink: raw.permalink,
+        }
+    }
+}
+
+impl SocrataClient {
+    /// Host part of the base URL ("https://www.datos.gov.co" -> "www.datos.gov.co").
+    fn domain(&self) -> &str {
+        let without_scheme = self
+            .base_url
+            .split_once("://")
+            .map_or(self.base_url.as_str(), |(_, rest)| rest);
+        without_scheme.split(['/', ':']).next().unwrap_or_default()
+    }
+
+    /// Build the catalog URL for a search scoped to the client's domain.
+    pub fn catalog_url(&self, query: &CatalogQuery) -> String {
+        let params = query
+            .to_params(self.domain())
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, utf8_percent_encode(&value, QUERY_VALUE)))
+            .collect::<Vec<_>>()
+            .join("&");
+        format!("{}/api/catalog/v1?{}", self.base_url, params)
+    }
+
+    /// Search the domain's catalog.
+    pub async fn search_catalog(&self, query: &CatalogQuery) -> Result<CatalogPage, SocrataError> {
+        let url = self.catalog_url(query);
+        let raw: RawPage = self.get_json(&url).await?;
+        Ok(CatalogPage {
+            total: raw.result_set_size,
+            results: raw.results.into_iter().map(CatalogEntry::from).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_url() {
+        let client = SocrataClient::new("https://www.datos.gov.co", None);
+        let query = CatalogQuery::new()
+            .text("sanciones proveedores")
+            .category("Gastos Gubernamentales")
+            .only("dataset")
+            .limit(10);
+        assert_eq!(
+            client.catalog_url(&query),
+            "https://www.datos.gov.co/api/catalog/v1?domains=www.datos.gov.co\
+             &search_context=www.datos.gov.co&q=sanciones%20proveedores\
+             &categories=Gastos%20Gubernamentales&only=dataset&limit=10"
+        );
+    }
+
+    #[test]
+    fn test_domain_strips_scheme_and_port() {
+        let client = SocrataClient::new("http://127.0.0.1:8080", None);
+        assert_eq!(client.domain(), "127.0.0.1");
+    }
+}
@@ -1,8 +1,28 @@
-use reqwest::Client;
+use reqwest::{Client, Response, StatusCode};
 use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 use tracing::{info, warn};
 
+pub mod aggregate;
+pub mod catalog;
+pub mod metadata;
+pub mod pagination;
+pub mod query;
+pub mod replay;
+pub mod retry;
+pub mod schema;
+
+pub use aggregate::{Aggregate, AggregateQuery, AggregateRow};
+pub use catalog::{CatalogEntry, CatalogPage, CatalogQuery};
+pub use metadata::{ColumnInfo, DatasetMetadata};
+pub use pagination::PaginationOptions;
+pub use query::{Comparison, Condition, SoqlQuery, SoqlValue, SortOrder};
+pub use replay::ReplayMode;
+pub use retry::RetryPolicy;
+pub use schema::{DriftPolicy, ExpectedColumn, ExpectedSchema, SchemaDiff, TypeChange};
+
 #[derive(Error, Debug)]
 pub enum SocrataError {
     #[error("API Request failed: {0}")]
@@ -11,6 +31,24 @@ pub enum SocrataError {
     ApiError(reqwest::StatusCode, String),
     #[error("Failed to parse response: {0}")]
     ParseError(#[from] serde_json::Error),
+    #[error("Rate limited after {attempts} attempts (retry after {retry_after:?})")]
+    RateLimited {
+        attempts: u32,
+        retry_after: Option<Duration>,
+    },
+    #[error("Schema drift in dataset {dataset_id}: required fields missing or changed: {fields:?}")]
+    SchemaDrift {
+        dataset_id: String,
+        fields: Vec<String>,
+    },
+    #[error("No recorded response for {url} (expected {path:?})")]
+    FixtureMissing { url: String, path: PathBuf },
+    #[error("Unknown replay mode {0:?} (expected live, record or replay)")]
+    InvalidReplayMode(String),
+    #[error("SOCRATA_FIXTURES_DIR must be set to record or replay responses")]
+    FixturesDirUnset,
+    #[error("Failed to read or write a fixture: {0}")]
+    FixtureIo(#[from] std::io::Error),
 }
 
 /// A client for the Socrata Open Data API (SODA).
@@ -18,6 +56,8 @@ pub struct SocrataClient {
     client: Client,
     base_url: String,
     app_token: Option<String>,
+    retry_policy: RetryPolicy,
+    replay: ReplayMode,
 }
 
 impl SocrataClient {
@@ -31,6 +71,30 @@ impl SocrataClient {
             client: Client::new(),
             base_url: base_url.to_string(),
             app_token,
+            retry_policy: RetryPolicy::default(),
+            replay: ReplayMode::default(),
+        }
+    }
+
+    /// Replace the retry policy used for every request.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Record responses to, or replay them from, fixture files.
+    pub fn with_replay(mut self, replay: ReplayMode) -> Self {
+        self.replay = replay;
+        self
+    }
+
+    /// Build the full resource URL for a dataset and query.
+    pub fn resource_url(&self, dataset_id: &str, query: &SoqlQuery) -> String {
+        let params = query.to_query_string();
+        if params.is_empty() {
+            format!("{}/resource/{}.json", self.base_url, dataset_id)
+        } else {
+            format!("{}/resource/{}.json?{}", self.base_url, dataset_id, params)
         }
     }
 
@@ -38,50 +102,91 @@ impl SocrataClient {
     ///
     /// # Arguments
     /// * `dataset_id` - The 4x4 ID of the dataset (e.g. "jbjy-vk9h")
-    /// * `limit` - Number of records to return
-    /// * `offset` - Number of records to skip
-    /// * `order` - SoQL order clause (e.g. "date DESC")
-    /// * `where_clause` - Optional SoQL where clause
+    /// * `query` - SoQL query (select, where, order, limit, offset...)
     pub async fn fetch<T: DeserializeOwned>(
         &self,
         dataset_id: &str,
-        limit: u32,
-        offset: u32,
-        order: Option<&str>,
-        where_clause: Option<&str>,
+        query: &SoqlQuery,
     ) -> Result<Vec<T>, SocrataError> {
-        let mut url = format!(
-            "{}/resource/{}.json?$limit={}&$offset={}",
-            self.base_url, dataset_id, limit, offset
-        );
+        let url = self.resource_url(dataset_id, query);
 
-        if let Some(ord) = order {
-            url.push_str(&format!("&$order={}", ord));
-        }
+        self.get_json(&url).await
+    }
 
-        if let Some(clause) = where_clause {
-            url.push_str(&format!("&$where={}", clause));
-        }
+    /// GET `url` and decode the JSON body, going through the replay fixtures
+    /// when a [`ReplayMode`] other than `Live` is set.
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, SocrataError> {
+        let body = match &self.replay {
+            ReplayMode::Replay(dir) => {
+                info!("SODA Replay: {}", url);
+                replay::load(dir, url).await?
+            }
+            mode => {
+                let body = self.get_with_retry(url).await?.bytes().await?.to_vec();
+                if let ReplayMode::Record(dir) = mode {
+                    replay::save(dir, url, &body).await?;
+                }
+                body
+            }
+        };
+        Ok(serde_json::from_slice(&body)?)
+    }
 
+    /// GET `url`, retrying on 429, 5xx and transient connection errors
+    /// according to the client's [`RetryPolicy`].
+    async fn get_with_retry(&self, url: &str) -> Result<Response, SocrataError> {
         info!("SODA Request: {}", url);
 
-        let mut request = self.client.get(&url);
-
-        if let Some(token) = &self.app_token {
-            request = request.header("X-App-Token", token);
-        } else {
+        if self.app_token.is_none() {
             warn!("No App Token provided. Rate limits may apply.");
         }
 
-        let response = request.send().await?;
+        let policy = &self.retry_policy;
+        let mut attempt = 1;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(SocrataError::ApiError(status, body));
-        }
+        loop {
+            let mut request = self.client.get(url);
+            if let Some(token) = &self.app_token {
+                request = request.header("X-App-Token", token);
+            }
+
+            let (wait, failure) = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    let wait = retry::retry_after(response.headers());
+                    let body = response.text().await.unwrap_or_default();
 
-        let data: Vec<T> = response.json().await?;
-        Ok(data)
+                    if !retry::is_retryable_status(status) {
+                        return Err(SocrataError::ApiError(status, body));
+                    }
+                    if attempt >= policy.max_attempts {
+                        return Err(if status == StatusCode::TOO_MANY_REQUESTS {
+                            SocrataError::RateLimited {
+                                attempts: attempt,
+                                retry_after: wait,
+                            }
+                        } else {
+                            SocrataError::ApiError(status, body)
+                        });
+                    }
+                    (wait, status.to_string())
+                }
+                Err(e) => {
+                    if !retry::is_retryable_error(&e) || attempt >= policy.max_attempts {
+                        return Err(e.into());
+                    }
+                    (None, e.to_string())
+                }
+            };
+
+            let delay = policy.backoff(attempt, wait);
+            warn!(
+                "SODA request failed ({}), retrying in {:?} (attempt {}/{})",
+                failure, delay, attempt, policy.max_attempts
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
     }
 }
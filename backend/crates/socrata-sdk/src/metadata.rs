@@ -0,0 +1,97 @@
+//! Dataset metadata from the Socrata views API (`/api/views/{id}.json`).
+
+use crate::{SocrataClient, SocrataError, SoqlQuery};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A column as described by the views API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnInfo {
+    /// API field name used in SoQL and JSON rows (e.g. "valor_del_contrato")
+    pub field_name: String,
+    /// Human-readable column name
+    #[serde(default)]
+    pub name: String,
+    /// Socrata data type ("text", "number", "calendar_date", ...)
+    #[serde(default)]
+    pub data_type_name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Metadata of a single dataset
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatasetMetadata {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub attribution: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub columns: Vec<ColumnInfo>,
+    /// When the rows were last changed, in seconds since the Unix epoch
+    #[serde(default)]
+    pub rows_updated_at: Option<u64>,
+    /// When the metadata was last changed, in seconds since the Unix epoch
+    #[serde(default)]
+    pub view_last_modified: Option<u64>,
+    /// Set by [`SocrataClient::metadata_with_row_count`]; the views API does not report it
+    #[serde(default)]
+    pub row_count: Option<u64>,
+}
+
+impl DatasetMetadata {
+    pub fn column(&self, field_name: &str) -> Option<&ColumnInfo> {
+        self.columns.iter().find(|c| c.field_name == field_name)
+    }
+
+    /// When the rows were last changed
+    pub fn rows_updated(&self) -> Option<SystemTime> {
+        self.rows_updated_at
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Whether rows changed after `since`; true when the timestamp is unknown
+    pub fn updated_since(&self, since: SystemTime) -> bool {
+        self.rows_updated().is_none_or(|updated| updated > since)
+    }
+}
+
+#[derive(Deserialize)]
+struct CountRow {
+    count: String,
+}
+
+impl SocrataClient {
+    /// Fetch a dataset's metadata, including its column definitions.
+    pub async fn metadata(&self, dataset_id: &str) -> Result<DatasetMetadata, SocrataError> {
+        let url = format!("{}/api/views/{}.json", self.base_url, dataset_id);
+        self.get_json(&url).await
+    }
+
+    /// Count the rows of a dataset with `SELECT count(*)`.
+    pub async fn row_count(&self, dataset_id: &str) -> Result<u64, SocrataError> {
+        let query = SoqlQuery::new().select(["count(*) AS count"]);
+        let rows: Vec<CountRow> = self.fetch(dataset_id, &query).await?;
+        let count = rows.first().map(|r| r.count.as_str()).unwrap_or("0");
+        Ok(serde_json::from_str(count)?)
+    }
+
+    /// [`metadata`](Self::metadata) plus the row count (one extra request).
+    pub async fn metadata_with_row_count(
+        &self,
+        dataset_id: &str,
+    ) -> Result<DatasetMetadata, SocrataError> {
+        let mut metadata = self.metadata(dataset_id).await?;
+        metadata.row_count = Some(self.row_count(dataset_id).await?);
+        Ok(metadata)
+    }
+}
@@ -0,0 +1,141 @@
+//! Transparent paging over large Socrata datasets.
+
+use crate::{SocrataClient, SocrataError, SoqlQuery};
+use futures_util::stream::{self, Stream, TryStreamExt};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use tracing::info;
+
+/// Controls how [`SocrataClient::stream`] walks through a dataset.
+#[derive(Debug, Clone)]
+pub struct PaginationOptions {
+    /// Rows requested per page
+    pub page_size: u32,
+    /// Stop after this many rows in total (None = whole dataset)
+    pub max_records: Option<u64>,
+    /// Pause between consecutive page requests to respect rate limits
+    pub page_delay: Duration,
+}
+
+impl Default for PaginationOptions {
+    fn default() -> Self {
+        Self {
+            page_size: 1000,
+            max_records: None,
+            page_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+impl PaginationOptions {
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    pub fn max_records(mut self, max_records: u64) -> Self {
+        self.max_records = Some(max_records);
+        self
+    }
+
+    pub fn page_delay(mut self, page_delay: Duration) -> Self {
+        self.page_delay = page_delay;
+        self
+    }
+}
+
+struct PageCursor {
+    query: SoqlQuery,
+    offset: u32,
+    fetched: u64,
+    first_page: bool,
+    done: bool,
+}
+
+impl SocrataClient {
+    /// Stream every row matching `query`, fetching pages on demand.
+    ///
+    /// `:id` is appended to the sort order so rows sharing the same value in
+    /// the other order columns (e.g. many contracts signed on the same
+    /// `fecha_de_firma`) are never skipped or duplicated across pages.
+    /// `$limit` and `$offset` on `query` are honored as the overall cap and
+    /// starting point.
+    pub fn stream<'a, T>(
+        &'a self,
+        dataset_id: &'a str,
+        query: SoqlQuery,
+        options: PaginationOptions,
+    ) -> impl Stream<Item = Result<T, SocrataError>> + 'a
+    where
+        T: DeserializeOwned + 'a,
+    {
+        let max_records = match (options.max_records, query.limit_value()) {
+            (Some(max), Some(limit)) => Some(max.min(limit as u64)),
+            (max, limit) => max.or(limit.map(u64::from)),
+        };
+
+        let cursor = PageCursor {
+            offset: query.start_offset(),
+            query: query.with_stable_order(),
+            fetched: 0,
+            first_page: true,
+            done: false,
+        };
+
+        stream::try_unfold(cursor, move |cursor| {
+            self.next_page(dataset_id, cursor, options.clone(), max_records)
+        })
+        .map_ok(|rows| stream::iter(rows.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    async fn next_page<T: DeserializeOwned>(
+        &self,
+        dataset_id: &str,
+        mut cursor: PageCursor,
+        options: PaginationOptions,
+        max_records: Option<u64>,
+    ) -> Result<Option<(Vec<T>, PageCursor)>, SocrataError> {
+        if cursor.done {
+            return Ok(None);
+        }
+
+        let page_size = match max_records {
+            Some(max) => {
+                let remaining = max.saturating_sub(cursor.fetched);
+                if remaining == 0 {
+                    return Ok(None);
+                }
+                remaining.min(options.page_size as u64) as u32
+            }
+            None => options.page_size,
+        };
+
+        if !cursor.first_page && !options.page_delay.is_zero() {
+            tokio::time::sleep(options.page_delay).await;
+        }
+
+        let page_query = cursor.query.clone().limit(page_size).offset(cursor.offset);
+        let rows: Vec<T> = self.fetch(dataset_id, &page_query).await?;
+
+        info!(
+            "Fetched page of {} rows from {} (offset {})",
+            rows.len(),
+            dataset_id,
+            cursor.offset
+        );
+
+        cursor.first_page = false;
+        cursor.fetched += rows.len() as u64;
+        cursor.offset = cursor.offset.saturating_add(rows.len() as u32);
+        if rows.len() < page_size as usize {
+            cursor.done = true;
+        }
+
+        if rows.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some((rows, cursor)))
+        }
+    }
+}
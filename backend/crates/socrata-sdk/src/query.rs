@@ -0,0 +1,699 @@
+//! Typed builder for SoQL (Socrata Query Language) requests.
+//!
+//! Identifiers (column names, select expressions) are passed through as-is,
+//! while every literal is quoted and escaped, and the whole query string is
+//! percent-encoded so entity names with quotes or accents are safe to send.
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::fmt;
+
+/// Characters left untouched when encoding query values (RFC 3986 unreserved).
+pub(crate) const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// A SoQL literal value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SoqlValue {
+    /// Text literal, rendered single-quoted with embedded quotes doubled.
+    Text(String),
+    /// Numeric literal, rendered unquoted.
+    Number(String),
+    /// Boolean literal.
+    Bool(bool),
+}
+
+impl fmt::Display for SoqlValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SoqlValue::Text(s) => write!(f, "'{}'", s.replace('\'', "''")),
+            SoqlValue::Number(n) => write!(f, "{}", n),
+            SoqlValue::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+impl From<&str> for SoqlValue {
+    fn from(value: &str) -> Self {
+        SoqlValue::Text(value.to_string())
+    }
+}
+
+impl From<String> for SoqlValue {
+    fn from(value: String) -> Self {
+        SoqlValue::Text(value)
+    }
+}
+
+impl From<&String> for SoqlValue {
+    fn from(value: &String) -> Self {
+        SoqlValue::Text(value.clone())
+    }
+}
+
+impl From<bool> for SoqlValue {
+    fn from(value: bool) -> Self {
+        SoqlValue::Bool(value)
+    }
+}
+
+macro_rules! impl_number_value {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for SoqlValue {
+                fn from(value: $t) -> Self {
+                    SoqlValue::Number(value.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_number_value!(i32, i64, u32, u64, usize, f64);
+
+/// Comparison operators supported in `$where` / `$having`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    NotEq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl Comparison {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Comparison::Eq => "=",
+            Comparison::NotEq => "!=",
+            Comparison::Gt => ">",
+            Comparison::Gte => ">=",
+            Comparison::Lt => "<",
+            Comparison::Lte => "<=",
+        }
+    }
+}
+
+/// A boolean SoQL expression used in `$where` and `$having`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Compare {
+        column: String,
+        op: Comparison,
+        value: SoqlValue,
+    },
+    Between {
+        column: String,
+        low: SoqlValue,
+        high: SoqlValue,
+    },
+    In {
+        column: String,
+        values: Vec<SoqlValue>,
+    },
+    Like {
+        column: String,
+        pattern: String,
+    },
+    StartsWith {
+        column: String,
+        prefix: String,
+    },
+    IsNull(String),
+    IsNotNull(String),
+    Not(Box<Condition>),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    /// Pre-built SoQL, inserted verbatim. Escaping is the caller's job.
+    Raw(String),
+}
+
+impl Condition {
+    fn compare(column: &str, op: Comparison, value: impl Into<SoqlValue>) -> Self {
+        Condition::Compare {
+            column: column.to_string(),
+            op,
+            value: value.into(),
+        }
+    }
+
+    pub fn eq(column: &str, value: impl Into<SoqlValue>) -> Self {
+        Self::compare(column, Comparison::Eq, value)
+    }
+
+    pub fn not_eq(column: &str, value: impl Into<SoqlValue>) -> Self {
+        Self::compare(column, Comparison::NotEq, value)
+    }
+
+    pub fn gt(column: &str, value: impl Into<SoqlValue>) -> Self {
+        Self::compare(column, Comparison::Gt, value)
+    }
+
+    pub fn gte(column: &str, value: impl Into<SoqlValue>) -> Self {
+        Self::compare(column, Comparison::Gte, value)
+    }
+
+    pub fn lt(column: &str, value: impl Into<SoqlValue>) -> Self {
+        Self::compare(column, Comparison::Lt, value)
+    }
+
+    pub fn lte(column: &str, value: impl Into<SoqlValue>) -> Self {
+        Self::compare(column, Comparison::Lte, value)
+    }
+
+    /// `column between low and high` (inclusive on both ends)
+    pub fn between(column: &str, low: impl Into<SoqlValue>, high: impl Into<SoqlValue>) -> Self {
+        Condition::Between {
+            column: column.to_string(),
+            low: low.into(),
+            high: high.into(),
+        }
+    }
+
+    /// `column in (v1, v2, ...)`; an empty list matches nothing and renders `false`
+    pub fn in_list<V: Into<SoqlValue>>(column: &str, values: impl IntoIterator<Item = V>) -> Self {
+        Condition::In {
+            column: column.to_string(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// `column like 'pattern'` (use `%` as wildcard)
+    pub fn like(column: &str, pattern: &str) -> Self {
+        Condition::Like {
+            column: column.to_string(),
+            pattern: pattern.to_string(),
+        }
+    }
+
+    /// `starts_with(column, 'prefix')`
+    pub fn starts_with(column: &str, prefix: &str) -> Self {
+        Condition::StartsWith {
+            column: column.to_string(),
+            prefix: prefix.to_string(),
+        }
+    }
+
+    pub fn is_null(column: &str) -> Self {
+        Condition::IsNull(column.to_string())
+    }
+
+    pub fn is_not_null(column: &str) -> Self {
+        Condition::IsNotNull(column.to_string())
+    }
+
+    pub fn raw(soql: &str) -> Self {
+        Condition::Raw(soql.to_string())
+    }
+
+    /// Combine all conditions with `AND`.
+    pub fn all(conditions: impl IntoIterator<Item = Condition>) -> Self {
+        Condition::And(conditions.into_iter().collect())
+    }
+
+    /// Combine all conditions with `OR`.
+    pub fn any(conditions: impl IntoIterator<Item = Condition>) -> Self {
+        Condition::Or(conditions.into_iter().collect())
+    }
+
+    pub fn and(self, other: Condition) -> Self {
+        match self {
+            Condition::And(mut items) => {
+                items.push(other);
+                Condition::And(items)
+            }
+            first => Condition::And(vec![first, other]),
+        }
+    }
+
+    pub fn or(self, other: Condition) -> Self {
+        match self {
+            Condition::Or(mut items) => {
+                items.push(other);
+                Condition::Or(items)
+            }
+            first => Condition::Or(vec![first, other]),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Condition::Not(Box::new(self))
+    }
+
+    /// True for conditions that always hold: an `And` with no member that
+    /// can fail, an `Or` with one that always holds, or `Not` of a condition
+    /// that never does. They are left out of the rendered SoQL.
+    pub fn is_empty(&self) -> bool {
+        self.constant() == Some(true)
+    }
+
+    /// Truth value when it does not depend on the row. An empty `Or`, like an
+    /// empty `In`, matches nothing.
+    fn constant(&self) -> Option<bool> {
+        match self {
+            Condition::And(items) => {
+                let values: Vec<Option<bool>> = items.iter().map(Condition::constant).collect();
+                if values.contains(&Some(false)) {
+                    Some(false)
+                } else {
+                    values.iter().all(|v| *v == Some(true)).then_some(true)
+                }
+            }
+            Condition::Or(items) => {
+                let values: Vec<Option<bool>> = items.iter().map(Condition::constant).collect();
+                if values.contains(&Some(true)) {
+                    Some(true)
+                } else {
+                    values.iter().all(|v| *v == Some(false)).then_some(false)
+                }
+            }
+            Condition::Not(inner) => inner.constant().map(|value| !value),
+            Condition::In { values, .. } => values.is_empty().then_some(false),
+            _ => None,
+        }
+    }
+
+    fn is_compound(&self) -> bool {
+        matches!(self, Condition::And(_) | Condition::Or(_) | Condition::Raw(_)) && self.constant().is_none()
+    }
+
+    /// Render the members of a group, leaving out the ones that cannot change
+    /// its result (always-true members of an `And`, never-true ones of an `Or`)
+    fn fmt_group(f: &mut fmt::Formatter<'_>, items: &[Condition], joiner: &str) -> fmt::Result {
+        let neutral = joiner == "AND";
+        let items: Vec<&Condition> = items
+            .iter()
+            .filter(|item| item.constant() != Some(neutral))
+            .collect();
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                write!(f, " {} ", joiner)?;
+            }
+            if item.is_compound() && items.len() > 1 {
+                write!(f, "({})", item)?;
+            } else {
+                write!(f, "{}", item)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Compare { column, op, value } => {
+                write!(f, "{} {} {}", column, op.as_str(), value)
+            }
+            Condition::Between { column, low, high } => {
+                write!(f, "{} between {} and {}", column, low, high)
+            }
+            Condition::In { values, .. } if values.is_empty() => write!(f, "false"),
+            Condition::In { column, values } => {
+                let list: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "{} in({})", column, list.join(", "))
+            }
+            Condition::Like { column, pattern } => {
+                write!(f, "{} like {}", column, SoqlValue::from(pattern.as_str()))
+            }
+            Condition::StartsWith { column, prefix } => {
+                write!(f, "starts_with({}, {})", column, SoqlValue::from(prefix.as_str()))
+            }
+            Condition::IsNull(column) => write!(f, "{} IS NULL", column),
+            Condition::IsNotNull(column) => write!(f, "{} IS NOT NULL", column),
+            Condition::Not(inner) => match inner.constant() {
+                Some(value) => write!(f, "{}", !value),
+                None => write!(f, "NOT ({})", inner),
+            },
+            Condition::Or(_) if self.constant() == Some(false) => write!(f, "false"),
+            Condition::And(items) => Self::fmt_group(f, items, "AND"),
+            Condition::Or(items) => Self::fmt_group(f, items, "OR"),
+            Condition::Raw(soql) => write!(f, "{}", soql),
+        }
+    }
+}
+
+/// Sort direction for `$order`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// Builder for a SoQL query.
+///
+/// ```
+/// use socrata_sdk::{Condition, SoqlQuery};
+///
+/// let query = SoqlQuery::new()
+///     .filter(Condition::eq("departamento", "Bogotá D.C."))
+///     .order_desc("fecha_de_firma")
+///     .limit(100);
+/// assert_eq!(
+///     query.to_query_string(),
+///     "$where=departamento%20%3D%20%27Bogot%C3%A1%20D.C.%27&$order=fecha_de_firma%20DESC&$limit=100"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SoqlQuery {
+    select: Vec<String>,
+    filter: Option<Condition>,
+    order: Vec<(String, SortOrder)>,
+    group: Vec<String>,
+    having: Option<Condition>,
+    search: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+impl SoqlQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add columns or expressions to `$select` (e.g. `"sum(valor_del_contrato) AS total"`).
+    pub fn select<S: AsRef<str>>(mut self, columns: impl IntoIterator<Item = S>) -> Self {
+        self.select
+            .extend(columns.into_iter().map(|c| c.as_ref().to_string()));
+        self
+    }
+
+    /// Add a `$where` condition. Repeated calls are combined with `AND`.
+    pub fn filter(mut self, condition: Condition) -> Self {
+        self.filter = Some(match self.filter.take() {
+            Some(existing) => existing.and(condition),
+            None => condition,
+        });
+        self
+    }
+
+    pub fn order_by(mut self, column: &str, direction: SortOrder) -> Self {
+        self.order.push((column.to_string(), direction));
+        self
+    }
+
+    pub fn order_asc(self, column: &str) -> Self {
+        self.order_by(column, SortOrder::Asc)
+    }
+
+    pub fn order_desc(self, column: &str) -> Self {
+        self.order_by(column, SortOrder::Desc)
+    }
+
+    /// Add columns to `$group`.
+    pub fn group_by<S: AsRef<str>>(mut self, columns: impl IntoIterator<Item = S>) -> Self {
+        self.group
+            .extend(columns.into_iter().map(|c| c.as_ref().to_string()));
+        self
+    }
+
+    /// Add a `$having` condition. Repeated calls are combined with `AND`.
+    pub fn having(mut self, condition: Condition) -> Self {
+        self.having = Some(match self.having.take() {
+            Some(existing) => existing.and(condition),
+            None => condition,
+        });
+        self
+    }
+
+    /// Full-text search across all text columns (`$q`).
+    pub fn search(mut self, text: &str) -> Self {
+        self.search = Some(text.to_string());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Offset the query starts from (0 when unset).
+    pub(crate) fn start_offset(&self) -> u32 {
+        self.offset.unwrap_or(0)
+    }
+
+    /// Limit set on the query, if any.
+    pub(crate) fn limit_value(&self) -> Option<u32> {
+        self.limit
+    }
+
+    /// Append `:id` as a final sort key so rows sharing the same value in the
+    /// other order columns always come back in the same sequence.
+    ///
+    /// Grouped queries have no `:id`; their group columns are unique per row
+    /// and are appended instead.
+    pub(crate) fn with_stable_order(mut self) -> Self {
+        let keys = if self.group.is_empty() {
+            vec![":id".to_string()]
+        } else {
+            self.group.clone()
+        };
+        for key in keys {
+            if !self.order.iter().any(|(column, _)| *column == key) {
+                self.order.push((key, SortOrder::Asc));
+            }
+        }
+        self
+    }
+
+    /// Unencoded `(parameter, value)` pairs in canonical order.
+    pub fn to_params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+
+        if !self.select.is_empty() {
+            params.push(("$select", self.select.join(", ")));
+        }
+        if let Some(filter) = self.filter.as_ref().filter(|c| !c.is_empty()) {
+            params.push(("$where", filter.to_string()));
+        }
+        if !self.order.is_empty() {
+            let order: Vec<String> = self
+                .order
+                .iter()
+                .map(|(column, direction)| match direction {
+                    SortOrder::Asc => format!("{} ASC", column),
+                    SortOrder::Desc => format!("{} DESC", column),
+                })
+                .collect();
+            params.push(("$order", order.join(", ")));
+        }
+        if !self.group.is_empty() {
+            params.push(("$group", self.group.join(", ")));
+        }
+        if let Some(having) = self.having.as_ref().filter(|c| !c.is_empty()) {
+            params.push(("$having", having.to_string()));
+        }
+        if let Some(search) = &self.search {
+            params.push(("$q", search.clone()));
+        }
+        if let Some(limit) = self.limit {
+            params.push(("$limit", limit.to_string()));
+        }
+        if let Some(offset) = self.offset {
+            params.push(("$offset", offset.to_string()));
+        }
+
+        params
+    }
+
+    /// Percent-encoded query string, without the leading `?`.
+    pub fn to_query_string(&self) -> String {
+        self.to_params()
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, utf8_percent_encode(&value, QUERY_VALUE)))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SocrataClient;
+
+    const BASE: &str = "https://www.datos.gov.co";
+
+    #[test]
+    fn test_text_literal_escaping() {
+        assert_eq!(SoqlValue::from("O'Brien").to_string(), "'O''Brien'");
+        assert_eq!(SoqlValue::from(42_i64).to_string(), "42");
+        assert_eq!(SoqlValue::from(true).to_string(), "true");
+    }
+
+    #[test]
+    fn test_condition_grouping() {
+        let cond = Condition::eq("departamento", "Antioquia")
+            .and(Condition::any([
+                Condition::eq("modalidad_de_contratacion", "Contratación directa"),
+                Condition::gt("valor_del_contrato", 1_000_000_000_i64),
+            ]));
+        assert_eq!(
+            cond.to_string(),
+            "departamento = 'Antioquia' AND (modalidad_de_contratacion = 'Contratación directa' OR valor_del_contrato > 1000000000)"
+        );
+    }
+
+    #[test]
+    fn test_empty_query() {
+        let client = SocrataClient::new(BASE, None);
+        assert_eq!(
+            client.resource_url("jbjy-vk9h", &SoqlQuery::new()),
+            "https://www.datos.gov.co/resource/jbjy-vk9h.json"
+        );
+    }
+
+    #[test]
+    fn test_latest_contracts_url() {
+        let client = SocrataClient::new(BASE, None);
+        let query = SoqlQuery::new()
+            .filter(Condition::gt("fecha_de_firma", "2024-01-01T00:00:00.000"))
+            .order_desc("fecha_de_firma")
+            .limit(1000)
+            .offset(2000);
+
+        assert_eq!(
+            client.resource_url("jbjy-vk9h", &query),
+            "https://www.datos.gov.co/resource/jbjy-vk9h.json?\
+             $where=fecha_de_firma%20%3E%20%272024-01-01T00%3A00%3A00.000%27\
+             &$order=fecha_de_firma%20DESC\
+             &$limit=1000&$offset=2000"
+        );
+    }
+
+    #[test]
+    fn test_entity_with_quotes_and_accents() {
+        let client = SocrataClient::new(BASE, None);
+        let query = SoqlQuery::new()
+            .filter(Condition::eq("nombre_entidad", "ALCALDÍA DE SAN JOSÉ DE CÚCUTA"))
+            .filter(Condition::like("nombre_contratista", "%D'ANDREA%"));
+
+        assert_eq!(
+            client.resource_url("jbjy-vk9h", &query),
+            "https://www.datos.gov.co/resource/jbjy-vk9h.json?\
+             $where=nombre_entidad%20%3D%20%27ALCALD%C3%8DA%20DE%20SAN%20JOS%C3%89%20DE%20C%C3%9ACUTA%27\
+             %20AND%20nombre_contratista%20like%20%27%25D%27%27ANDREA%25%27"
+        );
+    }
+
+    #[test]
+    fn test_between_in_and_starts_with() {
+        let query = SoqlQuery::new()
+            .select(["id_contrato", "valor_del_contrato"])
+            .filter(Condition::between(
+                "fecha_de_firma",
+                "2024-01-01T00:00:00",
+                "2024-12-31T23:59:59",
+            ))
+            .filter(Condition::in_list(
+                "tipo_de_contrato",
+                ["Obra", "Prestación de servicios"],
+            ))
+            .filter(Condition::starts_with("nit_entidad", "890"));
+
+        let params = query.to_params();
+        assert_eq!(params[0], ("$select", "id_contrato, valor_del_contrato".to_string()));
+        assert_eq!(
+            params[1],
+            (
+                "$where",
+                "fecha_de_firma between '2024-01-01T00:00:00' and '2024-12-31T23:59:59' \
+                 AND tipo_de_contrato in('Obra', 'Prestación de servicios') \
+                 AND starts_with(nit_entidad, '890')"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_group_having_and_search_url() {
+        let client = SocrataClient::new(BASE, None);
+        let query = SoqlQuery::new()
+            .select(["nit_entidad", "sum(valor_del_contrato) AS total"])
+            .group_by(["nit_entidad"])
+            .having(Condition::gt("sum(valor_del_contrato)", 1_000_000_i64))
+            .search("puente")
+            .order_desc("total")
+            .limit(10);
+
+        assert_eq!(
+            client.resource_url("jbjy-vk9h", &query),
+            "https://www.datos.gov.co/resource/jbjy-vk9h.json?\
+             $select=nit_entidad%2C%20sum%28valor_del_contrato%29%20AS%20total\
+             &$order=total%20DESC\
+             &$group=nit_entidad\
+             &$having=sum%28valor_del_contrato%29%20%3E%201000000\
+             &$q=puente\
+             &$limit=10"
+        );
+    }
+
+    #[test]
+    fn test_empty_in_list_url() {
+        let client = SocrataClient::new(BASE, None);
+        let query = SoqlQuery::new()
+            .filter(Condition::eq("estado_contrato", "Activo"))
+            .filter(Condition::in_list("id_del_portafolio", Vec::<String>::new()));
+
+        assert_eq!(
+            client.resource_url("jbjy-vk9h", &query),
+            "https://www.datos.gov.co/resource/jbjy-vk9h.json?\
+             $where=estado_contrato%20%3D%20%27Activo%27%20AND%20false"
+        );
+    }
+
+    #[test]
+    fn test_empty_groups_url() {
+        let client = SocrataClient::new(BASE, None);
+
+        // An empty OR matches nothing; NOT of it matches everything and is dropped
+        let query = SoqlQuery::new()
+            .filter(Condition::eq("departamento", "Antioquia"))
+            .filter(Condition::any(Vec::new()))
+            .filter(Condition::all([Condition::any(Vec::new())]).not())
+            .filter(Condition::all(Vec::new()));
+        assert_eq!(
+            client.resource_url("jbjy-vk9h", &query),
+            "https://www.datos.gov.co/resource/jbjy-vk9h.json?\
+             $where=departamento%20%3D%20%27Antioquia%27%20AND%20false"
+        );
+
+        // Members that never match leave the rest of an OR
+        let query = SoqlQuery::new().filter(Condition::any([
+            Condition::any(Vec::new()),
+            Condition::eq("departamento", "Cauca"),
+            Condition::in_list("ciudad", Vec::<String>::new()),
+        ]));
+        assert_eq!(
+            client.resource_url("jbjy-vk9h", &query),
+            "https://www.datos.gov.co/resource/jbjy-vk9h.json?\
+             $where=departamento%20%3D%20%27Cauca%27"
+        );
+        assert_eq!(Condition::any([Condition::all(Vec::new())]).to_string(), "");
+        assert_eq!(Condition::any(Vec::new()).not().to_string(), "true");
+
+        let only_empty = SoqlQuery::new().filter(Condition::all(Vec::new())).limit(5);
+        assert_eq!(
+            client.resource_url("jbjy-vk9h", &only_empty),
+            "https://www.datos.gov.co/resource/jbjy-vk9h.json?$limit=5"
+        );
+    }
+
+    #[test]
+    fn test_not_and_null_checks() {
+        let cond = Condition::is_not_null("valor_del_contrato")
+            .and(Condition::eq("estado_contrato", "Cancelado").not());
+        assert_eq!(
+            cond.to_string(),
+            "valor_del_contrato IS NOT NULL AND NOT (estado_contrato = 'Cancelado')"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Record and replay of API responses, for running without network access.
+//!
+//! In [`ReplayMode::Record`] every successful response is saved as a JSON
+//! fixture named after the normalized request URL; in [`ReplayMode::Replay`]
+//! requests are served from those fixtures and never reach the network.
+//! Normalization drops the scheme and host and sorts the query parameters,
+//! so fixtures recorded against one base URL replay against any other.
+
+use crate::SocrataError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Environment variable selecting the mode: `live` (default), `record` or `replay`
+pub const REPLAY_ENV: &str = "SOCRATA_REPLAY";
+/// Environment variable with the fixtures directory
+pub const FIXTURES_ENV: &str = "SOCRATA_FIXTURES_DIR";
+
+/// Where responses come from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Talk to the API
+    #[default]
+    Live,
+    /// Talk to the API and save every response under the directory
+    Record(PathBuf),
+    /// Serve responses from the directory only
+    Replay(PathBuf),
+}
+
+impl ReplayMode {
+    /// Read the mode from [`REPLAY_ENV`] and the directory from [`FIXTURES_ENV`],
+    /// which must be set unless the mode is `live`.
+    pub fn from_env() -> Result<Self, SocrataError> {
+        let mode = Self::from_env_or(PathBuf::new())?;
+        if mode != ReplayMode::Live && std::env::var_os(FIXTURES_ENV).is_none() {
+            return Err(SocrataError::FixturesDirUnset);
+        }
+        Ok(mode)
+    }
+
+    /// Like [`from_env`](Self::from_env), with `default_dir` used when
+    /// [`FIXTURES_ENV`] is unset. Pass an absolute path (e.g. one built from
+    /// `CARGO_MANIFEST_DIR`) so the result does not depend on the working directory.
+    pub fn from_env_or(default_dir: impl Into<PathBuf>) -> Result<Self, SocrataError> {
+        let mode = std::env::var(REPLAY_ENV).unwrap_or_default();
+        let dir = std::env::var_os(FIXTURES_ENV).map_or_else(|| default_dir.into(), PathBuf::from);
+        Self::parse(&mode, dir)
+    }
+
+    /// Build a mode from its name (`live`, `record`, `replay`; empty means `live`).
+    pub fn parse(mode: &str, dir: impl Into<PathBuf>) -> Result<Self, SocrataError> {
+        match mode.trim().to_ascii_lowercase().as_str() {
+            "" | "live" | "off" => Ok(ReplayMode::Live),
+            "record" => Ok(ReplayMode::Record(dir.into())),
+            "replay" => Ok(ReplayMode::Replay(dir.into())),
+            other => Err(SocrataError::InvalidReplayMode(other.to_string())),
+        }
+    }
+}
+
+/// A recorded response
+#[derive(Debug, Serialize, Deserialize)]
+struct Fixture {
+    url: String,
+    body: Value,
+}
+
+/// Request URL without scheme and host, with query parameters sorted.
+pub fn normalize_url(url: &str) -> String {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path_and_query = without_scheme
+        .find('/')
+        .map_or("/", |start| &without_scheme[start..]);
+
+    match path_and_query.split_once('?') {
+        None => path_and_query.to_string(),
+        Some((path, query)) => {
+            let mut params: Vec<&str> = query.split('&').filter(|p| !p.is_empty()).collect();
+            params.sort_unstable();
+            format!("{}?{}", path, params.join("&"))
+        }
+    }
+}
+
+/// Fixture file for a request URL: a readable slug of the path plus a hash
+/// of the normalized URL (e.g. `resource-jbjy-vk9h-3f2a9c0d1e4b5a67.json`).
+pub fn fixture_path(dir: &Path, url: &str) -> PathBuf {
+    let normalized = normalize_url(url);
+    let path = normalized.split('?').next().unwrap_or_default();
+    let slug: String = path
+        .trim_start_matches("/api")
+        .trim_end_matches(".json")
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    dir.join(format!("{}-{:016x}.json", slug, fnv1a(normalized.as_bytes())))
+}
+
+/// FNV-1a, used instead of `DefaultHasher` because fixture names must not
+/// change between Rust releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+pub(crate) async fn load(dir: &Path, url: &str) -> Result<Vec<u8>, SocrataError> {
+    let path = fixture_path(dir, url);
+    let raw = tokio::fs::read(&path)
+        .await
+        .map_err(|_| SocrataError::FixtureMissing {
+            url: normalize_url(url),
+            path: path.clone(),
+        })?;
+    let fixture: Fixture = serde_json::from_slice(&raw)?;
+    Ok(serde_json::to_vec(&fixture.body)?)
+}
+
+pub(crate) async fn save(dir: &Path, url: &str, body: &[u8]) -> Result<(), SocrataError> {
+    let fixture = Fixture {
+        url: normalize_url(url),
+        body: serde_json::from_slice(body)?,
+    };
+    tokio::fs::create_dir_all(dir).await?;
+    tokio::fs::write(fixture_path(dir, url), serde_json::to_vec_pretty(&fixture)?).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_url_ignores_host_and_param_order() {
+        assert_eq!(
+            normalize_url("https://www.datos.gov.co/resource/jbjy-vk9h.json?$limit=10&$order=%3Aid%20ASC"),
+            "/resource/jbjy-vk9h.json?$limit=10&$order=%3Aid%20ASC"
+        );
+        assert_eq!(
+            normalize_url("http://127.0.0.1:4000/resource/jbjy-vk9h.json?$order=%3Aid%20ASC&$limit=10"),
+            "/resource/jbjy-vk9h.json?$limit=10&$order=%3Aid%20ASC"
+        );
+        assert_eq!(normalize_url("https://www.datos.gov.co"), "/");
+    }
+
+    #[test]
+    fn test_fixture_path() {
+        let dir = Path::new("fixtures");
+        let path = fixture_path(dir, "https://www.datos.gov.co/api/views/jbjy-vk9h.json");
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("views-jbjy-vk9h-"), "{}", name);
+        assert_ne!(
+            fixture_path(dir, "https://www.datos.gov.co/resource/jbjy-vk9h.json?$limit=1"),
+            fixture_path(dir, "https://www.datos.gov.co/resource/jbjy-vk9h.json?$limit=2")
+        );
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(ReplayMode::parse("", "f").unwrap(), ReplayMode::Live);
+        assert_eq!(
+            ReplayMode::parse("Replay", "f").unwrap(),
+            ReplayMode::Replay(PathBuf::from("f"))
+        );
+        assert!(ReplayMode::parse("sometimes", "f").is_err());
+    }
+}
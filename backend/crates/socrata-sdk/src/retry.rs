@@ -0,0 +1,169 @@
+//! Retry policy with exponential backoff for transient SODA failures.
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// How the client retries requests that fail with 429, 5xx or a dropped connection.
+///
+/// The delay before retry `n` (1-based) is `base_delay * 2^(n-1)`, capped at
+/// `max_delay`, then spread by up to `jitter` (a fraction of the delay) in
+/// either direction. A `Retry-After` header overrides the computed delay,
+/// still bounded by `max_delay`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first request (1 = no retries)
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    /// Upper bound for any single delay
+    pub max_delay: Duration,
+    /// Random spread applied to each delay, between 0.0 and 1.0
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay to wait before retry number `retry` (1-based).
+    pub fn backoff(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(wait) = retry_after {
+            return wait.min(self.max_delay);
+        }
+
+        let exp = 2_u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(exp).min(self.max_delay);
+
+        if self.jitter <= 0.0 {
+            return delay;
+        }
+
+        // Uniform factor in [1 - jitter, 1 + jitter]
+        let factor = 1.0 + self.jitter * (2.0 * random_unit() - 1.0);
+        delay.mul_f64(factor).min(self.max_delay)
+    }
+}
+
+/// Whether a response status is worth retrying.
+pub(crate) fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Whether a transport error is transient (refused/reset connection, timeout).
+pub(crate) fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request()
+}
+
+/// Parse a `Retry-After` header expressed in seconds.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Cheap random number in [0, 1) without pulling in a RNG crate.
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_exponential_backoff_is_capped() {
+        let policy = RetryPolicy::default()
+            .base_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5))
+            .jitter(0.0);
+
+        assert_eq!(policy.backoff(1, None), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2, None), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3, None), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4, None), Duration::from_secs(5));
+        assert_eq!(policy.backoff(40, None), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_jitter_stays_in_bounds() {
+        let policy = RetryPolicy::default()
+            .base_delay(Duration::from_millis(1000))
+            .jitter(0.5);
+
+        for _ in 0..100 {
+            let delay = policy.backoff(1, None);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1500));
+        }
+    }
+
+    #[test]
+    fn test_retry_after_overrides_backoff() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        let wait = retry_after(&headers);
+        assert_eq!(wait, Some(Duration::from_secs(7)));
+
+        let policy = RetryPolicy::default().max_delay(Duration::from_secs(5));
+        assert_eq!(policy.backoff(1, wait), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!is_retryable_status(StatusCode::BAD_REQUEST));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+    }
+}
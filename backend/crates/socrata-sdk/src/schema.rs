@@ -0,0 +1,272 @@
+//! Schema drift detection: compare a dataset's live columns against the
+//! fields a consumer relies on.
+
+use crate::metadata::{ColumnInfo, DatasetMetadata};
+use crate::SocrataError;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// A field the consumer reads from the dataset
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExpectedColumn {
+    pub field_name: String,
+    /// Expected Socrata data type; None skips the type check
+    pub data_type: Option<String>,
+    /// Whether the consumer cannot work without this field
+    pub required: bool,
+}
+
+/// The columns a consumer expects from a dataset.
+///
+/// ```
+/// use socrata_sdk::ExpectedSchema;
+///
+/// let schema = ExpectedSchema::new()
+///     .required("id_contrato", "text")
+///     .required("valor_del_contrato", "number")
+///     .optional("ciudad");
+/// assert_eq!(schema.columns().len(), 3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ExpectedSchema {
+    columns: Vec<ExpectedColumn>,
+}
+
+impl ExpectedSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A field that must exist with the given data type
+    pub fn required(mut self, field_name: &str, data_type: &str) -> Self {
+        self.columns.push(ExpectedColumn {
+            field_name: field_name.to_string(),
+            data_type: Some(data_type.to_string()),
+            required: true,
+        });
+        self
+    }
+
+    /// A field that is read if present, of any type
+    pub fn optional(mut self, field_name: &str) -> Self {
+        self.columns.push(ExpectedColumn {
+            field_name: field_name.to_string(),
+            data_type: None,
+            required: false,
+        });
+        self
+    }
+
+    pub fn columns(&self) -> &[ExpectedColumn] {
+        &self.columns
+    }
+
+    /// Compare against the live dataset columns.
+    ///
+    /// System fields (`:id`, `:updated_at`, ...) are not listed by the views
+    /// API and are ignored.
+    pub fn compare(&self, metadata: &DatasetMetadata) -> SchemaDiff {
+        let expected = |name: &str| self.columns.iter().any(|c| c.field_name == name);
+
+        let added = metadata
+            .columns
+            .iter()
+            .filter(|c| !c.field_name.starts_with(':') && !expected(&c.field_name))
+            .cloned()
+            .collect();
+
+        let mut removed = Vec::new();
+        let mut type_changed = Vec::new();
+        for column in self.columns.iter().filter(|c| !c.field_name.starts_with(':')) {
+            match metadata.column(&column.field_name) {
+                None => removed.push(column.clone()),
+                Some(live) => {
+                    if let Some(data_type) = &column.data_type {
+                        if !live.data_type_name.eq_ignore_ascii_case(data_type) {
+                            type_changed.push(TypeChange {
+                                field_name: column.field_name.clone(),
+                                expected: data_type.clone(),
+                                actual: live.data_type_name.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        SchemaDiff {
+            dataset_id: metadata.id.clone(),
+            added,
+            removed,
+            type_changed,
+        }
+    }
+}
+
+/// A column whose data type differs from the expected one
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TypeChange {
+    pub field_name: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Differences between the expected and the live schema
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaDiff {
+    pub dataset_id: String,
+    /// Live columns the consumer does not know about
+    pub added: Vec<ColumnInfo>,
+    /// Expected columns missing from the dataset
+    pub removed: Vec<ExpectedColumn>,
+    pub type_changed: Vec<TypeChange>,
+}
+
+/// What to do when a required field disappears or changes type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftPolicy {
+    /// Return [`SocrataError::SchemaDrift`]
+    #[default]
+    Fail,
+    /// Log a warning and carry on
+    Warn,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.type_changed.is_empty()
+    }
+
+    /// Required fields that are missing or changed type
+    pub fn breaking_fields(&self) -> Vec<String> {
+        let mut fields: Vec<String> = self
+            .removed
+            .iter()
+            .filter(|c| c.required)
+            .map(|c| c.field_name.clone())
+            .collect();
+        fields.extend(self.type_changed.iter().map(|c| c.field_name.clone()));
+        fields
+    }
+
+    /// Log every difference and apply `policy` to breaking ones.
+    pub fn check(&self, policy: DriftPolicy) -> Result<(), SocrataError> {
+        for column in &self.added {
+            warn!(
+                "Dataset {}: new column {} ({})",
+                self.dataset_id, column.field_name, column.data_type_name
+            );
+        }
+        for column in self.removed.iter().filter(|c| !c.required) {
+            warn!("Dataset {}: optional column {} is missing", self.dataset_id, column.field_name);
+        }
+        for change in &self.type_changed {
+            warn!(
+                "Dataset {}: column {} changed type from {} to {}",
+                self.dataset_id, change.field_name, change.expected, change.actual
+            );
+        }
+
+        let breaking = self.breaking_fields();
+        if breaking.is_empty() {
+            return Ok(());
+        }
+        match policy {
+            DriftPolicy::Fail => Err(SocrataError::SchemaDrift {
+                dataset_id: self.dataset_id.clone(),
+                fields: breaking,
+            }),
+            DriftPolicy::Warn => {
+                warn!(
+                    "Dataset {}: required fields missing or changed: {}",
+                    self.dataset_id,
+                    breaking.join(", ")
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(field_name: &str, data_type_name: &str) -> ColumnInfo {
+        ColumnInfo {
+            field_name: field_name.to_string(),
+            name: field_name.to_string(),
+            data_type_name: data_type_name.to_string(),
+            description: None,
+        }
+    }
+
+    fn metadata(columns: Vec<ColumnInfo>) -> DatasetMetadata {
+        DatasetMetadata {
+            id: "jbjy-vk9h".to_string(),
+            name: "SECOP II - Contratos".to_string(),
+            columns,
+            ..Default::default()
+        }
+    }
+
+    fn schema() -> ExpectedSchema {
+        ExpectedSchema::new()
+            .required("id_contrato", "text")
+            .required("valor_del_contrato", "number")
+            .optional("ciudad")
+            .optional(":updated_at")
+    }
+
+    #[test]
+    fn test_matching_schema_has_no_diff() {
+        let live = metadata(vec![
+            column("id_contrato", "text"),
+            column("valor_del_contrato", "number"),
+            column("ciudad", "text"),
+        ]);
+        let diff = schema().compare(&live);
+        assert!(diff.is_empty());
+        assert!(diff.check(DriftPolicy::Fail).is_ok());
+    }
+
+    #[test]
+    fn test_added_removed_and_type_changed() {
+        let live = metadata(vec![
+            column("id_contrato", "text"),
+            column("valor_del_contrato", "text"),
+            column("urlproceso", "url"),
+        ]);
+        let diff = schema().compare(&live);
+
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].field_name, "urlproceso");
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].field_name, "ciudad");
+        assert_eq!(
+            diff.type_changed,
+            vec![TypeChange {
+                field_name: "valor_del_contrato".to_string(),
+                expected: "number".to_string(),
+                actual: "text".to_string(),
+            }]
+        );
+        assert_eq!(diff.breaking_fields(), vec!["valor_del_contrato"]);
+    }
+
+    #[test]
+    fn test_drift_policy() {
+        let live = metadata(vec![column("valor_del_contrato", "number")]);
+        let diff = schema().compare(&live);
+
+        assert!(diff.check(DriftPolicy::Warn).is_ok());
+        match diff.check(DriftPolicy::Fail) {
+            Err(SocrataError::SchemaDrift { dataset_id, fields }) => {
+                assert_eq!(dataset_id, "jbjy-vk9h");
+                assert_eq!(fields, vec!["id_contrato"]);
+            }
+            other => panic!("expected SchemaDrift, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,455 @@
+use futures_util::TryStreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use socrata_sdk::{
+    AggregateQuery, CatalogQuery, Condition, DriftPolicy, ExpectedSchema, PaginationOptions,
+    ReplayMode, RetryPolicy, SocrataClient, SocrataError, SoqlQuery,
+};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+#[derive(Debug, Deserialize)]
+struct Row {
+    id_contrato: String,
+    fecha_de_firma: String,
+}
+
+/// Serves `total` rows, all signed on the same date, honoring `$limit`/`$offset`.
+struct PagedDataset {
+    total: u32,
+}
+
+impl Respond for PagedDataset {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let param = |name: &str| {
+            request
+                .url
+                .query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.into_owned())
+        };
+
+        let order = param("$order").unwrap_or_default();
+        if !order.ends_with(":id ASC") {
+            return ResponseTemplate::new(400).set_body_string("missing :id tie-break");
+        }
+
+        let limit: u32 = param("$limit").and_then(|v| v.parse().ok()).unwrap_or(1000);
+        let offset: u32 = param("$offset").and_then(|v| v.parse().ok()).unwrap_or(0);
+
+        let rows: Vec<_> = (offset..self.total.min(offset + limit))
+            .map(|i| {
+                json!({
+                    "id_contrato": format!("CO1.PCCNTR.{}", i),
+                    "fecha_de_firma": "2024-03-01T00:00:00.000"
+                })
+            })
+            .collect();
+
+        ResponseTemplate::new(200).set_body_json(rows)
+    }
+}
+
+async fn mock_dataset(total: u32) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/resource/jbjy-vk9h.json"))
+        .respond_with(PagedDataset { total })
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn test_stream_pages_without_gaps_or_duplicates() {
+    let server = mock_dataset(2_345).await;
+    let client = SocrataClient::new(&server.uri(), None);
+
+    let query = SoqlQuery::new()
+        .filter(Condition::gt("fecha_de_firma", "2024-01-01T00:00:00.000"))
+        .order_desc("fecha_de_firma");
+    let options = PaginationOptions::default()
+        .page_size(500)
+        .page_delay(Duration::ZERO);
+
+    let rows: Vec<Row> = client
+        .stream("jbjy-vk9h", query, options)
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(rows.len(), 2_345);
+    for (i, row) in rows.iter().enumerate() {
+        assert_eq!(row.id_contrato, format!("CO1.PCCNTR.{}", i));
+        assert_eq!(row.fecha_de_firma, "2024-03-01T00:00:00.000");
+    }
+
+    // 4 full pages + 1 partial page
+    assert_eq!(server.received_requests().await.unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn test_stream_respects_max_records() {
+    let server = mock_dataset(10_000).await;
+    let client = SocrataClient::new(&server.uri(), None);
+
+    let options = PaginationOptions::default()
+        .page_size(300)
+        .max_records(700)
+        .page_delay(Duration::ZERO);
+
+    let rows: Vec<Row> = client
+        .stream("jbjy-vk9h", SoqlQuery::new(), options)
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(rows.len(), 700);
+    assert_eq!(rows.last().unwrap().id_contrato, "CO1.PCCNTR.699");
+
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 3);
+    assert!(requests[2].url.query().unwrap().contains("$limit=100&$offset=600"));
+}
+
+#[tokio::test]
+async fn test_stream_exact_page_multiple_stops_on_empty_page() {
+    let server = mock_dataset(400).await;
+    let client = SocrataClient::new(&server.uri(), None);
+
+    let options = PaginationOptions::default()
+        .page_size(200)
+        .page_delay(Duration::ZERO);
+
+    let rows: Vec<Row> = client
+        .stream("jbjy-vk9h", SoqlQuery::new(), options)
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(rows.len(), 400);
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+fn fast_retries(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy::default()
+        .max_attempts(max_attempts)
+        .base_delay(Duration::from_millis(5))
+        .jitter(0.0)
+}
+
+async fn mount_failures(server: &MockServer, status: u16, times: u64) {
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(status).insert_header("Retry-After", "0"))
+        .up_to_n_times(times)
+        .with_priority(1)
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_retries_server_errors_then_succeeds() {
+    let server = mock_dataset(3).await;
+    mount_failures(&server, 503, 1).await;
+    mount_failures(&server, 500, 1).await;
+
+    let client = SocrataClient::new(&server.uri(), None).with_retry_policy(fast_retries(3));
+    let query = SoqlQuery::new().order_asc(":id");
+    let rows: Vec<Row> = client.fetch("jbjy-vk9h", &query).await.unwrap();
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_rate_limited_after_retries_exhausted() {
+    let server = MockServer::start().await;
+    mount_failures(&server, 429, 10).await;
+
+    let client = SocrataClient::new(&server.uri(), None).with_retry_policy(fast_retries(4));
+    let result: Result<Vec<Row>, _> = client.fetch("jbjy-vk9h", &SoqlQuery::new()).await;
+
+    match result {
+        Err(SocrataError::RateLimited {
+            attempts,
+            retry_after,
+        }) => {
+            assert_eq!(attempts, 4);
+            assert_eq!(retry_after, Some(Duration::ZERO));
+        }
+        other => panic!("expected RateLimited, got {:?}", other),
+    }
+    assert_eq!(server.received_requests().await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_client_errors_are_not_retried() {
+    let server = MockServer::start().await;
+    mount_failures(&server, 404, 10).await;
+
+    let client = SocrataClient::new(&server.uri(), None).with_retry_policy(fast_retries(5));
+    let result: Result<Vec<Row>, _> = client.fetch("xxxx-xxxx", &SoqlQuery::new()).await;
+
+    assert!(matches!(result, Err(SocrataError::ApiError(status, _)) if status == 404));
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_retries_dropped_connections() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // Script: drop the first connection without answering, then serve a page.
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        drop(socket);
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4096];
+        let _ = socket.read(&mut buf).await.unwrap();
+        let body = r#"[{"id_contrato":"CO1.PCCNTR.1","fecha_de_firma":"2024-03-01T00:00:00.000"}]"#;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+    });
+
+    let client = SocrataClient::new(&format!("http://{}", addr), None)
+        .with_retry_policy(fast_retries(3));
+    let rows: Vec<Row> = client.fetch("jbjy-vk9h", &SoqlQuery::new()).await.unwrap();
+
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].id_contrato, "CO1.PCCNTR.1");
+}
+
+#[tokio::test]
+async fn test_metadata_and_schema_drift() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/views/jbjy-vk9h.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "jbjy-vk9h",
+            "name": "SECOP II - Contratos Electrónicos",
+            "columns": [
+                { "id": 1, "name": "ID Contrato", "fieldName": "id_contrato", "dataTypeName": "text" },
+                { "id": 2, "name": "Valor del Contrato", "fieldName": "valor_del_contrato", "dataTypeName": "text" },
+                { "id": 3, "name": "Documento Proveedor", "fieldName": "documento_proveedor", "dataTypeName": "text" }
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let client = SocrataClient::new(&server.uri(), None);
+    let metadata = client.metadata("jbjy-vk9h").await.unwrap();
+    assert_eq!(metadata.columns.len(), 3);
+    assert_eq!(metadata.column("valor_del_contrato").unwrap().name, "Valor del Contrato");
+
+    let diff = ExpectedSchema::new()
+        .required("id_contrato", "text")
+        .required("valor_del_contrato", "number")
+        .required("fecha_de_firma", "calendar_date")
+        .optional("nit_contratista")
+        .compare(&metadata);
+
+    assert_eq!(diff.added[0].field_name, "documento_proveedor");
+    assert_eq!(diff.removed.len(), 2);
+    assert_eq!(diff.type_changed[0].actual, "text");
+    assert!(diff.check(DriftPolicy::Warn).is_ok());
+    assert!(matches!(
+        diff.check(DriftPolicy::Fail),
+        Err(SocrataError::SchemaDrift { fields, .. })
+            if fields == vec!["fecha_de_firma".to_string(), "valor_del_contrato".to_string()]
+    ));
+}
+
+#[tokio::test]
+async fn test_metadata_freshness_and_row_count() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/views/jbjy-vk9h.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "jbjy-vk9h",
+            "name": "SECOP II - Contratos Electrónicos",
+            "category": "Gastos Gubernamentales",
+            "tags": ["secop", "contratos"],
+            "rowsUpdatedAt": 1_717_200_000,
+            "viewLastModified": 1_717_100_000,
+            "columns": []
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/resource/jbjy-vk9h.json"))
+        .and(query_param("$select", "count(*) AS count"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{ "count": "5234871" }])))
+        .mount(&server)
+        .await;
+
+    let client = SocrataClient::new(&server.uri(), None);
+    let metadata = client.metadata_with_row_count("jbjy-vk9h").await.unwrap();
+    assert_eq!(metadata.row_count, Some(5_234_871));
+    assert_eq!(metadata.category.as_deref(), Some("Gastos Gubernamentales"));
+    assert_eq!(metadata.tags, vec!["secop", "contratos"]);
+
+    let last_sync = UNIX_EPOCH + Duration::from_secs(1_717_150_000);
+    assert!(metadata.updated_since(last_sync));
+    let later_sync = UNIX_EPOCH + Duration::from_secs(1_717_300_000);
+    assert!(!metadata.updated_since(later_sync));
+}
+
+#[tokio::test]
+async fn test_search_catalog() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/catalog/v1"))
+        .and(query_param("search_context", "127.0.0.1"))
+        .and(query_param("q", "sanciones"))
+        .and(query_param("only", "dataset"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [{
+                "resource": {
+                    "id": "it5q-hg94",
+                    "name": "Multas y Sanciones SECOP I",
+                    "description": "",
+                    "type": "dataset",
+                    "columns_field_name": ["nit_entidad", "documento_contratista", "valor_sancion"],
+                    "data_updated_at": "2024-06-01T05:00:00.000Z",
+                    "metadata_updated_at": "2024-05-20T12:00:00.000Z"
+                },
+                "classification": {
+                    "domain_category": "Gastos Gubernamentales",
+                    "domain_tags": ["sanciones"]
+                },
+                "permalink": "https://www.datos.gov.co/d/it5q-hg94"
+            }],
+            "resultSetSize": 1
+        })))
+        .mount(&server)
+        .await;
+
+    let client = SocrataClient::new(&server.uri(), None);
+    let page = client
+        .search_catalog(&CatalogQuery::new().text("sanciones").only("dataset"))
+        .await
+        .unwrap();
+
+    assert_eq!(page.total, 1);
+    let entry = &page.results[0];
+    assert_eq!(entry.id, "it5q-hg94");
+    assert_eq!(entry.description, None);
+    assert_eq!(entry.category.as_deref(), Some("Gastos Gubernamentales"));
+    assert!(entry.columns.contains(&"documento_contratista".to_string()));
+}
+
+#[tokio::test]
+async fn test_grouped_aggregate() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/resource/jbjy-vk9h.json"))
+        .and(query_param("$group", "departamento"))
+        .and(query_param("$order", "valor_total DESC, departamento ASC"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            { "departamento": "Distrito Capital de Bogotá", "contratos": "120", "valor_total": "9.5E9" },
+            { "departamento": "Antioquia", "contratos": "80", "valor_total": "4200000000" }
+        ])))
+        .mount(&server)
+        .await;
+
+    let client = SocrataClient::new(&server.uri(), None);
+    let rows = client
+        .aggregate(
+            "jbjy-vk9h",
+            &AggregateQuery::new()
+                .group_by("departamento")
+                .count("contratos")
+                .sum("valor_del_contrato", "valor_total")
+                .order_by("valor_total", socrata_sdk::SortOrder::Desc),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].key("departamento"), Some("Distrito Capital de Bogotá"));
+    assert_eq!(rows[0].value("valor_total"), Some(9.5e9));
+    assert_eq!(rows[1].value("contratos"), Some(80.0));
+}
+
+#[tokio::test]
+async fn test_ungrouped_aggregate_into_typed_row() {
+    #[derive(Deserialize)]
+    struct Totals {
+        #[serde(deserialize_with = "socrata_sdk::aggregate::number")]
+        contratos: f64,
+        #[serde(deserialize_with = "socrata_sdk::aggregate::optional_number")]
+        valor_total: Option<f64>,
+    }
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/resource/jbjy-vk9h.json"))
+        .and(query_param(
+            "$select",
+            "count(*) AS contratos, sum(valor_del_contrato) AS valor_total",
+        ))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!([{ "contratos": "5234871", "valor_total": "1.2E15" }])),
+        )
+        .mount(&server)
+        .await;
+
+    let client = SocrataClient::new(&server.uri(), None);
+    let totals: Vec<Totals> = client
+        .aggregate_as(
+            "jbjy-vk9h",
+            &AggregateQuery::new()
+                .count("contratos")
+                .sum("valor_del_contrato", "valor_total"),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(totals[0].contratos, 5_234_871.0);
+    assert_eq!(totals[0].valor_total, Some(1.2e15));
+}
+
+#[tokio::test]
+async fn test_record_then_replay_offline() {
+    let fixtures = std::env::temp_dir().join(format!("socrata_fixtures_{}", std::process::id()));
+    let query = SoqlQuery::new()
+        .filter(Condition::eq("departamento", "Nariño"))
+        .limit(2);
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/resource/jbjy-vk9h.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            { "id_contrato": "CO1.1", "fecha_de_firma": "2024-03-01T00:00:00.000" },
+            { "id_contrato": "CO1.2", "fecha_de_firma": "2024-03-02T00:00:00.000" }
+        ])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let recorder = SocrataClient::new(&server.uri(), None)
+        .with_replay(ReplayMode::Record(fixtures.clone()));
+    let recorded: Vec<Row> = recorder.fetch("jbjy-vk9h", &query).await.unwrap();
+    drop(server);
+
+    // Different host, same path and query: served from disk
+    let replayer = SocrataClient::new("https://www.datos.gov.co", None)
+        .with_replay(ReplayMode::Replay(fixtures.clone()));
+    let replayed: Vec<Row> = replayer.fetch("jbjy-vk9h", &query).await.unwrap();
+    assert_eq!(replayed.len(), recorded.len());
+    assert_eq!(replayed[1].id_contrato, "CO1.2");
+
+    let missing = replayer.fetch::<Row>("jbjy-vk9h", &query.clone().limit(3)).await;
+    assert!(matches!(missing, Err(SocrataError::FixtureMissing { .. })));
+
+    std::fs::remove_dir_all(&fixtures).ok();
+}
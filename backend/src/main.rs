@@ -1,6 +1,7 @@
 use anyhow::Result;
 use dotenvy::dotenv;
 use tracing::{info, warn};
+use polars::prelude::IntoLazy;
 use std::env;
 
 mod obs;
@@ -19,100 +20,224 @@ async fn main() -> Result<()> {
 
     info!("Iniciando ingestión de datos desde SECOP II...");
 
-    // Run data ingestion
-    let mut contracts = obs::ingest::run(&socrata_token).await?;
+    // Run data ingestion (incremental unless --full-resync)
+    let ingest_options = obs::ingest::IngestOptions::from_args(env::args().skip(1));
+    // Only this run's delta is kept in memory; the full history is in the Parquet snapshot
+    let obs::ingest::IngestRun { delta: mut contracts, total_records, capped } =
+        obs::ingest::run(&socrata_token, &ingest_options).await?;
+    info!(
+        "{} contratos nuevos o modificados, {} en el snapshot{}",
+        contracts.len(),
+        total_records,
+        if capped { " (carga limitada por --max-records)" } else { "" }
+    );
+    let contracts_df = obs::storage::read_snapshot(&ingest_options.contratos_dir())?;
 
 
-    // Initialize NLP engine
-    info!("Inicializando motor de IA (Candle + BERT)...");
-    let nlp_engine = obs::nlp::BertInference::new().await;
-
-    match nlp_engine {
-        Ok(engine) => {
-             if let Some(first_contract) = contracts.first() {
-                if let Some(obj) = &first_contract.objeto_del_contrato {
+    // Initialize NLP engine (its weights come from the Hub, so replay runs skip it)
+    if matches!(obs::ingest::replay_mode()?, socrata_sdk::ReplayMode::Replay(_)) {
+        info!("Modo replay sin red: se omite el motor de IA.");
+    } else {
+        info!("Inicializando motor de IA (Candle + BERT)...");
+        match obs::nlp::BertInference::new().await {
+            Ok(engine) => {
+                if let Some(obj) = contracts.first().and_then(|c| c.objeto_del_contrato.as_ref()) {
                     info!("Generando embedding para: {:.50}...", obj);
                     match engine.embed(obj) {
                         Ok(emb) => info!("Embedding generado: shape {:?}", emb.shape()),
                         Err(e) => warn!("Error al generar embedding: {}", e),
                     }
                 }
-             }
-        },
-        Err(e) => warn!("Falló la carga del modelo NLP: {}. Continuando sin IA.", e),
+            }
+            Err(e) => warn!("Falló la carga del modelo NLP: {}. Continuando sin IA.", e),
+        }
     }
 
     // Output: Generate daily_report.json
     let output_path = "../frontend/public/daily_report.json";
     info!("Guardando reporte en: {}", output_path);
 
-    // Generate aggregated statistics & enrich contracts
+    // Generate aggregated statistics over the whole snapshot
+    let contract_values: Vec<f64> = contracts_df
+        .column("valor_del_contrato")?
+        .f64()?
+        .into_iter()
+        .flatten()
+        .collect();
     let mut total_value = 0.0;
-    let mut red_flags_count = 0;
-    let mut undefined_object_count = 0;
-    let mut zero_value_count = 0;
 
     // Histogram buckets: <10M, 10M-50M, 50M-100M, 100M-500M, >500M
     let mut histogram = [0u32; 5];
 
-    for contract in &mut contracts {
-        let mut flags = Vec::new();
-        let mut val = 0.0;
-
-        // Parse value
-        if let Some(val_str) = &contract.valor_del_contrato {
-             if let Ok(v) = val_str.parse::<f64>() {
-                 val = v;
-                 total_value += val;
-
-                 // Zero value check
-                 if val == 0.0 {
-                     zero_value_count += 1;
-                     flags.push("Valor Cero".to_string());
-                 }
-
-                 // Histogram buckets
-                 if val < 10_000_000.0 { histogram[0] += 1; }
-                 else if val < 50_000_000.0 { histogram[1] += 1; }
-                 else if val < 100_000_000.0 { histogram[2] += 1; }
-                 else if val < 500_000_000.0 { histogram[3] += 1; }
-                 else { histogram[4] += 1; }
-             }
+    for &val in &contract_values {
+        total_value += val;
+
+        if val < 10_000_000.0 { histogram[0] += 1; }
+        else if val < 50_000_000.0 { histogram[1] += 1; }
+        else if val < 100_000_000.0 { histogram[2] += 1; }
+        else if val < 500_000_000.0 { histogram[3] += 1; }
+        else { histogram[4] += 1; }
+    }
+
+    // Values in this run's delta that were present but could not be parsed, per field
+    let normalized: Vec<_> = contracts.iter().map(obs::normalize::normalize).collect();
+    let mut parse_warnings: std::collections::BTreeMap<&str, usize> = std::collections::BTreeMap::new();
+    let mut contracts_with_warnings = 0;
+
+    for record in &normalized {
+        if !record.warnings.is_empty() {
+            contracts_with_warnings += 1;
         }
+        for warning in &record.warnings {
+            *parse_warnings.entry(warning.field).or_default() += 1;
+        }
+    }
 
-        // Check object
-        if let Some(obj) = &contract.objeto_del_contrato {
-            let obj_lower = obj.to_lowercase();
-            if obj_lower == "no definido" || obj_lower.contains("objeto a contratar") {
-                undefined_object_count += 1;
-                flags.push("Objeto Indefinido".to_string());
-            }
-        } else {
-             undefined_object_count += 1;
-             flags.push("Objeto Faltante".to_string());
+    if contracts_with_warnings > 0 {
+        warn!(
+            "{} contratos con valores no interpretables: {:?}",
+            contracts_with_warnings, parse_warnings
+        );
+    }
+
+    // Data-quality report over this run's delta, compared with the previous run
+    let quality_path = std::path::Path::new("../frontend/public/data_quality.json");
+    let mut quality = obs::quality::QualityReport::compute(&contracts, &normalized, chrono::Utc::now().date_naive());
+    quality.capped = capped;
+    let snapshot_summary = obs::quality::SnapshotSummary::from_frame(&contracts_df)?;
+    quality.snapshot = Some(snapshot_summary.clone());
+    if let Some(previous) = obs::quality::QualityReport::load(quality_path).await {
+        quality.compare_with(&previous);
+    }
+
+    // National totals computed by Socrata, to cross-check the snapshot totals
+    let socrata_client = obs::ingest::SocrataClient::from_env(&socrata_token)?;
+    let server_totals = match socrata_client.server_totals().await {
+        Ok(totals) => {
+            quality.cross_check(&snapshot_summary, &totals);
+            Some(totals)
+        }
+        Err(e) => {
+            warn!("No se pudieron obtener los totales del servidor: {}", e);
+            None
         }
+    };
+    for alert in &quality.alerts {
+        warn!("Calidad de datos: {}", alert);
+    }
+    if contracts.is_empty() {
+        // An empty delta has no quality to measure and would skew the next comparison
+        info!("Sin contratos nuevos; se conserva {}", quality_path.display());
+    } else {
+        info!("Guardando reporte de calidad en: {}", quality_path.display());
+        quality.save(quality_path).await?;
+    }
 
-        // Set Risk Level
-        let level = if flags.len() >= 2 {
-            "Alto"
-        } else if !flags.is_empty() {
-            "Medio"
-        } else {
-            "Bajo"
-        };
-
-        contract.risk_level = Some(level.to_string());
-        contract.red_flags = Some(flags);
+    // Golden records: resolve every contractor against the persisted MDM store,
+    // so contractor UUIDs stay stable across runs
+    let golden_dir = ingest_options.golden_records_dir();
+    let mut resolver = mdm_core::resolver::EntityResolver::load(&golden_dir)?;
+    let (resolved, resolution) = resolver.resolve_frame_with_summary(&contracts_df, &mdm_core::batch::BatchColumns::default())?;
+    obs::storage::attach_resolution(&mut contracts, &resolved)?;
+    info!(
+        "Resolución de contratistas: {} exactos, {} aproximados, {} nuevos, {} sin datos ({} registros maestros)",
+        resolution.exact, resolution.fuzzy, resolution.created, resolution.skipped, resolver.len()
+    );
+    if resolution.invalid_check_digits > 0 {
+        warn!(
+            "{} contratistas con dígito de verificación incorrecto; se resolvieron por el número base",
+            resolution.invalid_check_digits
+        );
     }
+    resolver.save(&golden_dir, mdm_core::store::StoreFormat::Parquet)?;
 
-    red_flags_count = zero_value_count + undefined_object_count;
+    // Risk scoring (rules configured in risk_rules.toml)
+    let risk_config_path = env::var("RISK_RULES_PATH").unwrap_or_else(|_| "risk_rules.toml".to_string());
+    info!("Cargando reglas de riesgo desde: {}", risk_config_path);
+    let risk_config = obs::risk::RiskConfig::load(std::path::Path::new(&risk_config_path))?;
+    let mut risk_engine = obs::risk::RiskEngine::new(risk_config);
+
+    if risk_engine.is_enabled("contract_splitting") {
+        let options: obs::analyze::SplittingOptions = risk_engine.params("contract_splitting")?;
+        let findings = obs::analyze::detect_contract_splitting(&contracts_df, &options)?;
+        risk_engine.register(Box::new(obs::risk::FlaggedContracts::from_splitting("contract_splitting", &findings)));
+    }
+
+    if risk_engine.is_enabled("price_outlier") {
+        let options: obs::analyze::PriceOutlierOptions = risk_engine.params("price_outlier")?;
+        let snapshot = obs::storage::scan_snapshot(&ingest_options.contratos_dir())?;
+        match obs::analyze::detect_price_outliers(snapshot, &options) {
+            Ok(flagged) => risk_engine.register(Box::new(obs::risk::FlaggedContracts::from_frame("price_outlier", &flagged)?)),
+            Err(e) => warn!("No se pudo analizar precios atípicos sobre el snapshot: {}", e),
+        }
+    }
+
+    match obs::storage::scan_procesos(&ingest_options.procesos_dir()) {
+        Ok(procesos) => {
+            let combined = obs::storage::combined_view(contracts_df.clone().lazy(), procesos).collect()?;
+
+            if risk_engine.is_enabled("single_bidder") {
+                let flagged = obs::analyze::detect_single_bidders(&combined)?;
+                risk_engine.register(Box::new(obs::risk::FlaggedContracts::from_frame("single_bidder", &flagged)?));
+            }
+            if risk_engine.is_enabled("short_timeline") {
+                let thresholds: obs::analyze::TimelineThresholds = risk_engine.params("short_timeline")?;
+                let flagged = obs::analyze::detect_short_timelines(&combined, &thresholds)?;
+                risk_engine.register(Box::new(obs::risk::FlaggedContracts::from_frame("short_timeline", &flagged)?));
+            }
+        }
+        Err(e) => warn!("Procesos no disponibles, se omiten reglas que dependen de ellos: {}", e),
+    }
+
+    // The delta is scored for the daily report; the dashboard counts cover the
+    // whole snapshot, like total_contracts
+    risk_engine.apply(&mut contracts);
+    let rule_counts = risk_engine.count(obs::storage::risk_view(&contracts_df)?);
+    info!("Reglas de riesgo sobre el snapshot: {:?}", rule_counts);
+    let red_flags_count: usize = rule_counts.values().sum();
+    let zero_value_count = rule_counts.get("zero_value").copied().unwrap_or(0);
+    // The dashboard groups placeholder and missing objects under one bar
+    let undefined_object_count = ["undefined_object", "missing_object"]
+        .iter()
+        .filter_map(|rule| rule_counts.get(*rule))
+        .sum::<usize>();
+
+    // Market concentration (HHI) per contracting entity
+    let concentration_options = obs::analyze::ConcentrationOptions::default();
+    let concentration = obs::analyze::concentration_by_entity(&contracts_df, &concentration_options)?;
+    let concentration = obs::analyze::concentration_records(&concentration)?;
+    let concentrated: Vec<_> = concentration.iter().filter(|c| c.concentrada).take(50).collect();
+
+    // Benford's Law, globally and per contracting entity
+    let benford = obs::analyze::check_benford_df(&contracts_df, "valor_del_contrato");
+    let digit_tests: Vec<_> = [
+        obs::analyze::DigitTest::SecondDigit,
+        obs::analyze::DigitTest::FirstTwoDigits,
+        obs::analyze::DigitTest::LastTwoDigits,
+    ]
+    .into_iter()
+    .map(|test| {
+        let result = obs::analyze::benford_test(&contract_values, test);
+        let spikes = obs::analyze::overrepresented_digits(&result, 3.0);
+        serde_json::json!({ "result": result, "overrepresented_digits": spikes })
+    })
+    .collect();
+    let benford_ranking = obs::analyze::benford_by_entity(
+        &contracts_df,
+        "valor_del_contrato",
+        obs::analyze::BENFORD_MIN_SAMPLE,
+    )?;
 
     let stats = serde_json::json!({
-        "total_contracts": contracts.len(),
+        "total_contracts": contracts_df.height(),
+        "delta_contracts": contracts.len(),
         "total_value": total_value,
         "red_flags_count": red_flags_count,
         "zero_value_count": zero_value_count,
         "undefined_object_count": undefined_object_count,
+        "rule_counts": rule_counts,
+        "contracts_with_parse_warnings": contracts_with_warnings,
+        "parse_warnings": parse_warnings,
         "histogram": {
             "0-10M": histogram[0],
             "10M-50M": histogram[1],
@@ -120,16 +245,41 @@ async fn main() -> Result<()> {
             "100M-500M": histogram[3],
             ">500M": histogram[4]
         },
+        "concentration": {
+            "moderate_threshold": concentration_options.moderate_threshold,
+            "high_threshold": concentration_options.high_threshold,
+            "entities_analyzed": concentration.len(),
+            "concentrated_count": concentration.iter().filter(|c| c.concentrada).count(),
+            "top_concentrated": concentrated
+        },
+        "benford": {
+            "global": benford,
+            "digit_tests": digit_tests,
+            "entities_tested": benford_ranking.len(),
+            "min_sample": obs::analyze::BENFORD_MIN_SAMPLE
+        },
+        "national": server_totals,
         "last_updated": chrono::Utc::now().to_rfc3339()
     });
 
-    let json_data = serde_json::to_string_pretty(&contracts)?;
-    tokio::fs::write(output_path, json_data).await?;
+    // The report carries the latest contracts of this run, scored; with no
+    // delta the previous report is still current
+    if contracts.is_empty() {
+        info!("Sin contratos nuevos; se conserva {}", output_path);
+    } else {
+        let report = obs::ingest::latest(&contracts, obs::ingest::REPORT_SIZE);
+        tokio::fs::write(output_path, serde_json::to_string_pretty(&report)?).await?;
+    }
 
     let stats_path = "../frontend/public/stats.json";
     info!("Guardando estadísticas en: {}", stats_path);
     tokio::fs::write(stats_path, serde_json::to_string_pretty(&stats)?).await?;
 
+    let benford_path = "../frontend/public/benford_ranking.json";
+    info!("Guardando ranking de Benford en: {}", benford_path);
+    let benford_top: Vec<_> = benford_ranking.iter().take(50).collect();
+    tokio::fs::write(benford_path, serde_json::to_string_pretty(&benford_top)?).await?;
+
     // Hugging Face Sync (After file is written)
     let hf_token = env::var("HF_TOKEN").unwrap_or_default();
     let hf_repo = "iberi22/veeduria-secop-ii";
@@ -151,6 +301,17 @@ async fn main() -> Result<()> {
         if let Err(e) = lake.upload_file(stats_path_buf, "stats.json").await {
              warn!("Error al subir stats.json a HF: {}", e);
         }
+
+        // Upload data-quality report
+        if let Err(e) = lake.upload_file(quality_path.to_path_buf(), "data_quality.json").await {
+             warn!("Error al subir data_quality.json a HF: {}", e);
+        }
+
+        // Upload Benford ranking
+        let benford_path_buf = std::path::PathBuf::from(benford_path);
+        if let Err(e) = lake.upload_file(benford_path_buf, "benford_ranking.json").await {
+             warn!("Error al subir benford_ranking.json a HF: {}", e);
+        }
     }
 
     info!("Pipeline de ingestión completado.");
@@ -1,97 +1,1017 @@
+use crate::obs::calendar::HolidayCalendar;
+use chrono::{Datelike, NaiveDate};
 use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use statrs::distribution::{ChiSquared, ContinuousCDF};
+use std::collections::HashMap;
 use tracing::info;
 
-/// Expected Benford distribution for first digits 1-9
+/// Expected Benford distribution for first digits 1-9 (log10(1 + 1/d))
 const BENFORD_EXPECTED: [f64; 9] = [
-    0.301, 0.176, 0.125, 0.097, 0.079, 0.067, 0.058, 0.051, 0.046,
+    std::f64::consts::LOG10_2, 0.17609, 0.12494, 0.09691, 0.07918, 0.06695, 0.05799, 0.05115, 0.04576,
 ];
 
-/// Extract first digit from a numeric value
+/// Significance level for the chi-squared test
+const BENFORD_ALPHA: f64 = 0.05;
+
+/// Entities with fewer valid values are not tested
+pub const BENFORD_MIN_SAMPLE: u32 = 100;
+
+/// Extract first digit from a numeric value (sign is ignored)
 fn first_digit(value: f64) -> Option<u8> {
-    if value <= 0.0 {
+    if value == 0.0 || !value.is_finite() {
         return None;
     }
     let abs_val = value.abs();
     let first = abs_val / 10_f64.powf(abs_val.log10().floor());
-    Some(first as u8)
+    // Guard against log10 rounding (e.g. 1000 -> 0.999...)
+    Some((first as u8).clamp(1, 9))
 }
 
-/// Analyze a series of monetary values for Benford's Law conformance
-pub fn analyze_benford(values: &[f64]) -> BenfordResult {
-    let mut digit_counts = [0u32; 9];
-    let mut valid_count = 0u32;
-
-    for &val in values {
-        if let Some(digit) = first_digit(val) {
-            if (1..=9).contains(&digit) {
-                digit_counts[(digit - 1) as usize] += 1;
-                valid_count += 1;
+/// Nigrini's MAD conformity ranges
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BenfordConformity {
+    Close,
+    Acceptable,
+    Marginal,
+    Nonconformity,
+}
+
+impl BenfordConformity {
+    /// Classify a MAD given the upper bounds of the close, acceptable and marginal ranges
+    pub fn from_mad(mad: f64, bands: [f64; 3]) -> Self {
+        if mad <= bands[0] {
+            BenfordConformity::Close
+        } else if mad <= bands[1] {
+            BenfordConformity::Acceptable
+        } else if mad <= bands[2] {
+            BenfordConformity::Marginal
+        } else {
+            BenfordConformity::Nonconformity
+        }
+    }
+}
+
+/// Which digits of each value are tested
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DigitTest {
+    /// First digit, 1-9
+    FirstDigit,
+    /// Second digit, 0-9
+    SecondDigit,
+    /// First two digits, 10-99; sensitive to values parked under round thresholds
+    FirstTwoDigits,
+    /// Last two digits of the integer part, 00-99, expected uniform; detects rounding
+    LastTwoDigits,
+}
+
+impl DigitTest {
+    /// Digit values tested, in the order of the result distributions
+    pub fn digits(&self) -> Vec<u8> {
+        match self {
+            DigitTest::FirstDigit => (1..=9).collect(),
+            DigitTest::SecondDigit => (0..=9).collect(),
+            DigitTest::FirstTwoDigits => (10..=99).collect(),
+            DigitTest::LastTwoDigits => (0..=99).collect(),
+        }
+    }
+
+    /// Expected proportion of each digit value
+    pub fn expected(&self) -> Vec<f64> {
+        match self {
+            DigitTest::FirstDigit => BENFORD_EXPECTED.to_vec(),
+            DigitTest::SecondDigit => (0..10)
+                .map(|d2| {
+                    (1..10)
+                        .map(|d1| (1.0 + 1.0 / (10 * d1 + d2) as f64).log10())
+                        .sum()
+                })
+                .collect(),
+            DigitTest::FirstTwoDigits => (10..100)
+                .map(|d| (1.0 + 1.0 / d as f64).log10())
+                .collect(),
+            DigitTest::LastTwoDigits => vec![0.01; 100],
+        }
+    }
+
+    /// Nigrini's MAD bands; none are published for the last-two-digits test
+    fn mad_bands(&self) -> Option<[f64; 3]> {
+        match self {
+            DigitTest::FirstDigit => Some([0.006, 0.012, 0.015]),
+            DigitTest::SecondDigit => Some([0.008, 0.010, 0.012]),
+            DigitTest::FirstTwoDigits => Some([0.0012, 0.0018, 0.0022]),
+            DigitTest::LastTwoDigits => None,
+        }
+    }
+
+    /// Position of `value` in the distribution, or None if it cannot be tested
+    fn bucket(&self, value: f64) -> Option<usize> {
+        match self {
+            DigitTest::FirstDigit => first_digit(value).map(|d| (d - 1) as usize),
+            DigitTest::SecondDigit => leading_two_digits(value).map(|d| (d % 10) as usize),
+            DigitTest::FirstTwoDigits => leading_two_digits(value).map(|d| (d - 10) as usize),
+            DigitTest::LastTwoDigits => {
+                // Values under 100 have no meaningful last two digits
+                let integer = value.abs().trunc();
+                (value.is_finite() && integer >= 100.0).then(|| (integer % 100.0) as usize)
             }
         }
     }
+}
 
-    if valid_count == 0 {
+/// First two significant digits (10-99); single-digit values get a 0 second digit
+fn leading_two_digits(value: f64) -> Option<u8> {
+    if value == 0.0 || !value.is_finite() {
+        return None;
+    }
+    // Scientific notation gives the exact shortest mantissa, free of log10 rounding
+    let formatted = format!("{:e}", value.abs());
+    let mut digits = formatted.chars().filter(|c| c.is_ascii_digit());
+    let first = digits.next()?.to_digit(10)?;
+    let mantissa_len = formatted.find('e')?;
+    let second = match formatted[..mantissa_len].chars().nth(2) {
+        Some(c) => c.to_digit(10)?,
+        None => 0,
+    };
+    Some((first * 10 + second) as u8)
+}
+
+/// Goodness of fit of observed digit proportions against the expected ones
+fn digit_test(test: DigitTest, counts: &[u32]) -> BenfordResult {
+    let n: u32 = counts.iter().sum();
+    if n == 0 {
         return BenfordResult::default();
     }
+    let n_f = n as f64;
+    let expected = test.expected();
 
-    // Calculate observed distribution
-    let observed: Vec<f64> = digit_counts
-        .iter()
-        .map(|&c| c as f64 / valid_count as f64)
-        .collect();
+    let observed: Vec<f64> = counts.iter().map(|&c| c as f64 / n_f).collect();
 
-    // Chi-squared statistic
     let chi_squared: f64 = observed
         .iter()
-        .zip(BENFORD_EXPECTED.iter())
-        .map(|(&obs, &exp)| {
-            let expected_count = exp * valid_count as f64;
-            if expected_count > 0.0 {
-                (obs * valid_count as f64 - expected_count).powi(2) / expected_count
-            } else {
-                0.0
-            }
-        })
+        .zip(&expected)
+        .map(|(&obs, &exp)| (obs - exp).powi(2) * n_f / exp)
         .sum();
 
-    // Critical value for chi-squared with 8 degrees of freedom at 0.05 significance
-    let critical_value = 15.507;
-    let is_anomalous = chi_squared > critical_value;
+    let degrees_of_freedom = (expected.len() - 1) as f64;
+    let p_value = ChiSquared::new(degrees_of_freedom)
+        .map(|dist| dist.sf(chi_squared))
+        .unwrap_or(f64::NAN);
 
-    info!(
-        "Benford Analysis: chi² = {:.2}, critical = {:.2}, anomalous = {}",
-        chi_squared, critical_value, is_anomalous
-    );
+    let mad = observed
+        .iter()
+        .zip(&expected)
+        .map(|(obs, exp)| (obs - exp).abs())
+        .sum::<f64>()
+        / expected.len() as f64;
+
+    // Nigrini's z-statistic with continuity correction
+    let z_scores = observed
+        .iter()
+        .zip(&expected)
+        .map(|(&obs, &exp)| {
+            let diff = (obs - exp).abs();
+            let correction = 1.0 / (2.0 * n_f);
+            let numerator = if correction < diff { diff - correction } else { diff };
+            numerator / (exp * (1.0 - exp) / n_f).sqrt()
+        })
+        .collect();
 
     BenfordResult {
+        test: Some(test),
+        digits: test.digits(),
         observed_distribution: observed,
-        expected_distribution: BENFORD_EXPECTED.to_vec(),
+        expected_distribution: expected,
         chi_squared,
-        is_anomalous,
-        sample_size: valid_count,
+        p_value,
+        mad,
+        conformity: test.mad_bands().map(|bands| BenfordConformity::from_mad(mad, bands)),
+        z_scores,
+        is_anomalous: p_value < BENFORD_ALPHA,
+        sample_size: n,
     }
 }
 
-#[derive(Debug, Default)]
+/// Run one digit test over a series of values
+pub fn benford_test(values: &[f64], test: DigitTest) -> BenfordResult {
+    let mut counts = vec![0u32; test.digits().len()];
+    for bucket in values.iter().filter_map(|&v| test.bucket(v)) {
+        counts[bucket] += 1;
+    }
+    digit_test(test, &counts)
+}
+
+/// Analyze a series of monetary values for Benford's Law conformance
+pub fn analyze_benford(values: &[f64]) -> BenfordResult {
+    let result = benford_test(values, DigitTest::FirstDigit);
+
+    info!(
+        "Benford Analysis: chi² = {:.2}, p = {:.4}, MAD = {:.4} ({:?}), anomalous = {}",
+        result.chi_squared, result.p_value, result.mad, result.conformity, result.is_anomalous
+    );
+
+    result
+}
+
+/// Digits whose observed share exceeds the expected one with |z| above `z_threshold`.
+///
+/// On the first-two-digits test these are the spikes that reveal values
+/// clustered just under a round threshold (e.g. many contracts starting with 49).
+pub fn overrepresented_digits(result: &BenfordResult, z_threshold: f64) -> Vec<u8> {
+    result
+        .digits
+        .iter()
+        .zip(&result.z_scores)
+        .zip(result.observed_distribution.iter().zip(&result.expected_distribution))
+        .filter(|((_, z), (obs, exp))| **z > z_threshold && obs > exp)
+        .map(|((digit, _), _)| *digit)
+        .collect()
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct BenfordResult {
+    pub test: Option<DigitTest>,
+    /// Digit value represented by each position of the distributions
+    pub digits: Vec<u8>,
     pub observed_distribution: Vec<f64>,
     pub expected_distribution: Vec<f64>,
     pub chi_squared: f64,
+    /// Probability of a chi-squared statistic at least this large under Benford
+    pub p_value: f64,
+    /// Mean absolute deviation between observed and expected proportions
+    pub mad: f64,
+    pub conformity: Option<BenfordConformity>,
+    /// Per-digit z-statistics; above 1.96 is significant at 5%
+    pub z_scores: Vec<f64>,
     pub is_anomalous: bool,
     pub sample_size: u32,
 }
 
+/// First-digit Benford test for a single entity
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityBenford {
+    pub nit_entidad: String,
+    pub nombre_entidad: Option<String>,
+    #[serde(flatten)]
+    pub result: BenfordResult,
+}
+
+/// Run the first-digit Benford test per `nit_entidad` on `column`.
+///
+/// Entities with fewer than `min_sample` non-zero values are skipped, since
+/// the test has no power on small samples. Results are ranked from least to
+/// most conforming (descending MAD).
+pub fn benford_by_entity(
+    df: &DataFrame,
+    column: &str,
+    min_sample: u32,
+) -> PolarsResult<Vec<EntityBenford>> {
+    let grouped = df
+        .clone()
+        .lazy()
+        .filter(
+            col("nit_entidad")
+                .is_not_null()
+                .and(col(column).is_not_null())
+                .and(col(column).neq(lit(0.0))),
+        )
+        .group_by([col("nit_entidad")])
+        .agg([
+            col("nombre_entidad").first(),
+            col(column).cast(DataType::Float64).alias("valores"),
+        ])
+        .filter(col("valores").list().len().gt_eq(lit(min_sample)))
+        .collect()?;
+
+    let nits = grouped.column("nit_entidad")?.str()?;
+    let names = grouped.column("nombre_entidad")?.str()?;
+    let values = grouped.column("valores")?.list()?;
+
+    let mut ranking = Vec::with_capacity(grouped.height());
+    for ((nit, name), series) in nits.into_iter().zip(names).zip(values) {
+        let (Some(nit), Some(series)) = (nit, series) else {
+            continue;
+        };
+        let values: Vec<f64> = series.f64()?.into_iter().flatten().collect();
+        ranking.push(EntityBenford {
+            nit_entidad: nit.to_string(),
+            nombre_entidad: name.map(String::from),
+            result: benford_test(&values, DigitTest::FirstDigit),
+        });
+    }
+
+    ranking.sort_by(|a, b| b.result.mad.total_cmp(&a.result.mad));
+    info!(
+        "Benford per entity: {} entities tested, {} nonconforming",
+        ranking.len(),
+        ranking
+            .iter()
+            .filter(|e| e.result.conformity == Some(BenfordConformity::Nonconformity))
+            .count()
+    );
+    Ok(ranking)
+}
+
+/// Column holding the contracting modality (contracts, processes and the combined view)
+pub const MODALITY_COLUMN: &str = "modalidad_de_contratacion";
+/// Column holding the number of offers received by the process
+pub const OFFERS_COLUMN: &str = "numero_de_ofertas";
+/// Column added to every flagged row explaining why it was flagged
+pub const REASON_COLUMN: &str = "razon";
+/// Date the process was published (Date column)
+pub const PUBLICATION_COLUMN: &str = "fecha_de_publicacion";
+/// Deadline for receiving offers (Date column)
+pub const CLOSING_COLUMN: &str = "fecha_de_cierre";
+/// Business days between publication and closing, added to flagged rows
+pub const DURATION_COLUMN: &str = "dias_habiles";
+/// Minimum business days applied to the row, added to flagged rows
+pub const THRESHOLD_COLUMN: &str = "umbral_dias";
+
+/// Lowercase and strip Spanish accents so free text can be compared
+fn fold_text(raw: &str) -> String {
+    raw.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' => 'a',
+            'é' => 'e',
+            'í' => 'i',
+            'ó' => 'o',
+            'ú' | 'ü' => 'u',
+            _ => c,
+        })
+        .collect()
+}
+
+/// Contracting modality as defined by Ley 80/1993 and Ley 1150/2007
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Modalidad {
+    LicitacionPublica,
+    SeleccionAbreviada,
+    ConcursoMeritos,
+    MinimaCuantia,
+    ContratacionDirecta,
+    RegimenEspecial,
+    Otra,
+}
+
+impl Modalidad {
+    /// Classify a free-text SECOP modality ("Selección Abreviada de Menor Cuantía", ...)
+    pub fn from_secop(raw: &str) -> Self {
+        let normalized = fold_text(raw);
+
+        if normalized.contains("contratacion directa") {
+            Modalidad::ContratacionDirecta
+        } else if normalized.contains("licitacion") {
+            Modalidad::LicitacionPublica
+        } else if normalized.contains("seleccion abreviada") {
+            Modalidad::SeleccionAbreviada
+        } else if normalized.contains("concurso de meritos") {
+            Modalidad::ConcursoMeritos
+        } else if normalized.contains("minima cuantia") {
+            Modalidad::MinimaCuantia
+        } else if normalized.contains("regimen especial") {
+            Modalidad::RegimenEspecial
+        } else {
+            Modalidad::Otra
+        }
+    }
+
+    /// Modalities where the law expects several competing offers
+    pub fn is_competitive(&self) -> bool {
+        matches!(
+            self,
+            Modalidad::LicitacionPublica | Modalidad::SeleccionAbreviada | Modalidad::ConcursoMeritos
+        )
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Modalidad::LicitacionPublica => "Licitación pública",
+            Modalidad::SeleccionAbreviada => "Selección abreviada",
+            Modalidad::ConcursoMeritos => "Concurso de méritos",
+            Modalidad::MinimaCuantia => "Mínima cuantía",
+            Modalidad::ContratacionDirecta => "Contratación directa",
+            Modalidad::RegimenEspecial => "Régimen especial",
+            Modalidad::Otra => "Otra",
+        }
+    }
+}
+
 /// Red Flag: Detect single-bidder contracts
+///
+/// Keeps rows whose modality is competitive (licitación pública, selección
+/// abreviada, concurso de méritos) and that received exactly one offer.
+/// Contratación directa and other modalities where a single offer is legal
+/// are never flagged. A `razon` column is added to the result.
 pub fn detect_single_bidders(df: &DataFrame) -> PolarsResult<DataFrame> {
-    // This would filter contracts where numero_oferentes == 1
-    // For now, return a placeholder
-    df.clone().lazy().collect()
+    let modalities = df.column(MODALITY_COLUMN)?.str()?;
+    let offers = df.column(OFFERS_COLUMN)?.cast(&DataType::Int64)?;
+    let offers = offers.i64()?;
+
+    let mut mask = Vec::with_capacity(df.height());
+    let mut reasons: Vec<Option<String>> = Vec::with_capacity(df.height());
+
+    for (modality, n_offers) in modalities.into_iter().zip(offers) {
+        let modalidad = modality.map_or(Modalidad::Otra, Modalidad::from_secop);
+        let flagged = modalidad.is_competitive() && n_offers == Some(1);
+
+        mask.push(flagged);
+        reasons.push(flagged.then(|| {
+            format!("{} con un único oferente", modalidad.label())
+        }));
+    }
+
+    let mut flagged = df.clone();
+    flagged.with_column(Series::new(REASON_COLUMN.into(), reasons))?;
+    let flagged = flagged.filter(&BooleanChunked::from_slice("mask".into(), &mask))?;
+
+    info!(
+        "Single-bidder check: {} of {} rows flagged",
+        flagged.height(),
+        df.height()
+    );
+    Ok(flagged)
 }
 
+/// Minimum publication-to-closing period, in business days, per modality.
+///
+/// Modalities without an entry (contratación directa, régimen especial) are
+/// never checked. Deserializable so it can be loaded from configuration, e.g.
+/// `{"min_business_days": {"licitacion_publica": 10}}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimelineThresholds {
+    pub min_business_days: HashMap<Modalidad, i64>,
+}
+
+impl Default for TimelineThresholds {
+    /// Minimum terms from Decreto 1082 de 2015 for each competitive modality
+    fn default() -> Self {
+        Self {
+            min_business_days: HashMap::from([
+                (Modalidad::LicitacionPublica, 10),
+                (Modalidad::SeleccionAbreviada, 5),
+                (Modalidad::ConcursoMeritos, 5),
+                (Modalidad::MinimaCuantia, 1),
+            ]),
+        }
+    }
+}
+
+impl TimelineThresholds {
+    pub fn get(&self, modalidad: Modalidad) -> Option<i64> {
+        self.min_business_days.get(&modalidad).copied()
+    }
+}
+
+fn date_values(df: &DataFrame, column: &str) -> PolarsResult<Vec<Option<NaiveDate>>> {
+    let days = df.column(column)?.cast(&DataType::Int32)?;
+    Ok(days
+        .i32()?
+        .into_iter()
+        .map(|d| d.and_then(|d| NaiveDate::from_num_days_from_ce_opt(d + EPOCH_DAYS_FROM_CE)))
+        .collect())
+}
+
+/// Days from 0001-01-01 to 1970-01-01, the offset between chrono and Polars dates
+const EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
 /// Red Flag: Detect unusually short procurement timelines
-pub fn detect_short_timelines(df: &DataFrame, _min_days: i64) -> PolarsResult<DataFrame> {
-    // Filter contracts where (fecha_cierre - fecha_publicacion) < min_days
-    df.clone().lazy().collect()
+///
+/// Counts business days (weekends and Colombian holidays excluded) between
+/// `fecha_de_publicacion` and `fecha_de_cierre` and keeps rows below the
+/// minimum configured for their modality. Rows with missing dates, or with a
+/// closing date before publication, are skipped. The result carries
+/// `dias_habiles`, `umbral_dias` and `razon` columns.
+pub fn detect_short_timelines(
+    df: &DataFrame,
+    thresholds: &TimelineThresholds,
+) -> PolarsResult<DataFrame> {
+    let modalities = df.column(MODALITY_COLUMN)?.str()?;
+    let published = date_values(df, PUBLICATION_COLUMN)?;
+    let closing = date_values(df, CLOSING_COLUMN)?;
+
+    let years = published.iter().chain(&closing).flatten().map(|d| d.year());
+    let first = years.clone().min().unwrap_or_default();
+    let last = years.max().unwrap_or_default();
+    let calendar = HolidayCalendar::colombia(first..=last);
+
+    let mut mask = Vec::with_capacity(df.height());
+    let mut durations: Vec<Option<i64>> = Vec::with_capacity(df.height());
+    let mut limits: Vec<Option<i64>> = Vec::with_capacity(df.height());
+    let mut reasons: Vec<Option<String>> = Vec::with_capacity(df.height());
+
+    for ((modality, start), end) in modalities.into_iter().zip(published).zip(closing) {
+        let modalidad = modality.map_or(Modalidad::Otra, Modalidad::from_secop);
+        let check = thresholds.get(modalidad).zip(start.zip(end));
+        let flagged = check.and_then(|(limit, (start, end))| {
+            let days = calendar.business_days_between(start, end);
+            (0..limit).contains(&days).then_some((days, limit))
+        });
+
+        mask.push(flagged.is_some());
+        durations.push(flagged.map(|(days, _)| days));
+        limits.push(flagged.map(|(_, limit)| limit));
+        reasons.push(flagged.map(|(days, limit)| {
+            format!(
+                "{} con {} días hábiles entre publicación y cierre (mínimo {})",
+                modalidad.label(),
+                days,
+                limit
+            )
+        }));
+    }
+
+    let mut flagged = df.clone();
+    flagged.with_column(Series::new(DURATION_COLUMN.into(), durations))?;
+    flagged.with_column(Series::new(THRESHOLD_COLUMN.into(), limits))?;
+    flagged.with_column(Series::new(REASON_COLUMN.into(), reasons))?;
+    let flagged = flagged.filter(&BooleanChunked::from_slice("mask".into(), &mask))?;
+
+    info!(
+        "Short-timeline check: {} of {} rows flagged",
+        flagged.height(),
+        df.height()
+    );
+    Ok(flagged)
+}
+
+/// Options for [`concentration_by_entity`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConcentrationOptions {
+    /// Compute one row per entity and signing year
+    pub by_year: bool,
+    /// Compute one row per entity and `tipo_de_contrato`
+    pub by_contract_type: bool,
+    /// How many of the largest contractors make up `top_share`
+    pub top_n: u32,
+    /// HHI above which a market is moderately concentrated
+    pub moderate_threshold: f64,
+    /// HHI above which a market is highly concentrated and flagged
+    pub high_threshold: f64,
+    /// Groups with fewer contracts are reported but never flagged
+    pub min_contracts: u32,
+}
+
+impl Default for ConcentrationOptions {
+    /// Thresholds from the DOJ/FTC Horizontal Merger Guidelines (2010)
+    fn default() -> Self {
+        Self {
+            by_year: false,
+            by_contract_type: false,
+            top_n: 3,
+            moderate_threshold: 1500.0,
+            high_threshold: 2500.0,
+            min_contracts: 5,
+        }
+    }
+}
+
+/// Market concentration of one entity (and optionally year / contract type)
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityConcentration {
+    pub nit_entidad: String,
+    pub nombre_entidad: Option<String>,
+    pub anio: Option<i32>,
+    pub tipo_de_contrato: Option<String>,
+    pub contratos: u32,
+    pub contratistas: u32,
+    pub valor_total: f64,
+    pub hhi: f64,
+    pub top_share: f64,
+    pub nivel: String,
+    pub concentrada: bool,
+}
+
+/// Herfindahl-Hirschman index of contracted value per `nit_entidad`.
+///
+/// Each contractor's share is its summed `valor_del_contrato` over the
+/// entity total; HHI is the sum of squared percentage shares (0-10,000).
+/// Rows without an entity NIT, contractor NIT or a positive value are
+/// ignored. Returns one row per group with `contratos`, `contratistas`,
+/// `valor_total`, `hhi`, `top_share` (fraction held by the `top_n` largest
+/// contractors), `nivel` and `concentrada`, sorted by descending HHI.
+pub fn concentration_by_entity(
+    df: &DataFrame,
+    options: &ConcentrationOptions,
+) -> PolarsResult<DataFrame> {
+    let mut keys = vec![col("nit_entidad")];
+    if options.by_year {
+        keys.push(col("anio"));
+    }
+    if options.by_contract_type {
+        keys.push(col("tipo_de_contrato"));
+    }
+    let mut contractor_keys = keys.clone();
+    contractor_keys.push(col("nit_contratista"));
+
+    let per_contractor = df
+        .clone()
+        .lazy()
+        .filter(
+            col("nit_entidad")
+                .is_not_null()
+                .and(col("nit_contratista").is_not_null())
+                .and(col("valor_del_contrato").gt(lit(0.0))),
+        )
+        .with_column(col("fecha_de_firma").dt().year().alias("anio"))
+        .group_by(contractor_keys)
+        .agg([
+            col("valor_del_contrato").sum().alias("valor"),
+            len().cast(DataType::UInt32).alias("contratos"),
+            col("nombre_entidad").first(),
+        ]);
+
+    let hhi = (col("valor").pow(2).sum() / col("valor").sum().pow(2)) * lit(10_000.0);
+    let top_share = col("valor")
+        .sort(SortOptions::default().with_order_descending(true))
+        .head(Some(options.top_n as usize))
+        .sum()
+        / col("valor").sum();
+
+    let nivel = when(col("hhi").gt(lit(options.high_threshold)))
+        .then(lit("Alta"))
+        .when(col("hhi").gt(lit(options.moderate_threshold)))
+        .then(lit("Moderada"))
+        .otherwise(lit("Baja"));
+    let concentrada = col("hhi")
+        .gt(lit(options.high_threshold))
+        .and(col("contratos").gt_eq(lit(options.min_contracts)));
+
+    let result = per_contractor
+        .group_by(keys)
+        .agg([
+            col("nombre_entidad").first(),
+            col("contratos").sum(),
+            len().cast(DataType::UInt32).alias("contratistas"),
+            col("valor").sum().alias("valor_total"),
+            hhi.alias("hhi"),
+            top_share.alias("top_share"),
+        ])
+        .with_columns([nivel.alias("nivel"), concentrada.alias("concentrada")])
+        .sort(
+            ["hhi", "valor_total"],
+            SortMultipleOptions::default().with_order_descending(true),
+        )
+        .collect()?;
+
+    info!(
+        "HHI: {} groups, {} highly concentrated",
+        result.height(),
+        result.column("concentrada")?.bool()?.sum().unwrap_or(0)
+    );
+    Ok(result)
+}
+
+/// Convert the output of [`concentration_by_entity`] into serializable records
+pub fn concentration_records(df: &DataFrame) -> PolarsResult<Vec<EntityConcentration>> {
+    let optional_str = |name: &str| -> PolarsResult<Vec<Option<String>>> {
+        match df.column(name) {
+            Ok(c) => Ok(c.str()?.into_iter().map(|v| v.map(String::from)).collect()),
+            Err(_) => Ok(vec![None; df.height()]),
+        }
+    };
+    let anio: Vec<Option<i32>> = match df.column("anio") {
+        Ok(c) => c.i32()?.into_iter().collect(),
+        Err(_) => vec![None; df.height()],
+    };
+    let nombres = optional_str("nombre_entidad")?;
+    let tipos = optional_str("tipo_de_contrato")?;
+    let nits = df.column("nit_entidad")?.str()?;
+    let contratos = df.column("contratos")?.u32()?;
+    let contratistas = df.column("contratistas")?.u32()?;
+    let valor = df.column("valor_total")?.f64()?;
+    let hhi = df.column("hhi")?.f64()?;
+    let top_share = df.column("top_share")?.f64()?;
+    let nivel = df.column("nivel")?.str()?;
+    let concentrada = df.column("concentrada")?.bool()?;
+
+    Ok((0..df.height())
+        .map(|i| EntityConcentration {
+            nit_entidad: nits.get(i).unwrap_or_default().to_string(),
+            nombre_entidad: nombres[i].clone(),
+            anio: anio[i],
+            tipo_de_contrato: tipos[i].clone(),
+            contratos: contratos.get(i).unwrap_or(0),
+            contratistas: contratistas.get(i).unwrap_or(0),
+            valor_total: valor.get(i).unwrap_or(0.0),
+            hhi: hhi.get(i).unwrap_or(0.0),
+            top_share: top_share.get(i).unwrap_or(0.0),
+            nivel: nivel.get(i).unwrap_or_default().to_string(),
+            concentrada: concentrada.get(i).unwrap_or(false),
+        })
+        .collect())
+}
+
+/// Options for [`detect_contract_splitting`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplittingOptions {
+    /// Cuantía limits in COP a split purchase would be trying to stay under
+    pub thresholds: Vec<f64>,
+    /// How far below a limit a value may sit to count, as a fraction of the limit
+    pub margin: f64,
+    /// Maximum span in days between the first and last contract of a cluster
+    pub window_days: i64,
+    /// Minimum contracts in a cluster
+    pub min_contracts: usize,
+}
+
+impl Default for SplittingOptions {
+    /// Mínima cuantía (10% of menor cuantía) and menor cuantía (1,000 SMMLV)
+    /// for the largest entities, at the 2024 minimum wage of $1,300,000.
+    /// Smaller entities have lower limits and should pass their own.
+    fn default() -> Self {
+        Self {
+            thresholds: vec![130_000_000.0, 1_300_000_000.0],
+            margin: 0.1,
+            window_days: 30,
+            min_contracts: 2,
+        }
+    }
+}
+
+/// A group of contracts that looks like one purchase split to stay under a limit
+#[derive(Debug, Clone, Serialize)]
+pub struct SplittingFinding {
+    pub nit_entidad: String,
+    pub nit_contratista: String,
+    pub id_contratos: Vec<String>,
+    pub desde: NaiveDate,
+    pub hasta: NaiveDate,
+    pub valor_total: f64,
+    pub umbral: f64,
+    pub razon: String,
+}
+
+struct SplitCandidate {
+    id: String,
+    fecha: NaiveDate,
+    valor: f64,
+}
+
+/// Red Flag: Detect contract splitting (fraccionamiento)
+///
+/// Groups contracts by `nit_entidad` + `nit_contratista` and, for each
+/// configured limit, looks for at least `min_contracts` contracts signed
+/// within `window_days` whose values each sit just below the limit (within
+/// `margin`) while their sum exceeds it. Clusters do not overlap: each
+/// contract is reported at most once per limit.
+pub fn detect_contract_splitting(
+    df: &DataFrame,
+    options: &SplittingOptions,
+) -> PolarsResult<Vec<SplittingFinding>> {
+    let ids = df.column("id_contrato")?.str()?;
+    let entities = df.column("nit_entidad")?.str()?;
+    let contractors = df.column("nit_contratista")?.str()?;
+    let values = df.column("valor_del_contrato")?.cast(&DataType::Float64)?;
+    let values = values.f64()?;
+    let dates = date_values(df, "fecha_de_firma")?;
+
+    let mut groups: HashMap<(&str, &str), Vec<SplitCandidate>> = HashMap::new();
+    for (i, fecha) in dates.into_iter().enumerate() {
+        if let (Some(id), Some(entity), Some(contractor), Some(valor), Some(fecha)) = (
+            ids.get(i),
+            entities.get(i),
+            contractors.get(i),
+            values.get(i),
+            fecha,
+        ) {
+            groups.entry((entity, contractor)).or_default().push(SplitCandidate {
+                id: id.to_string(),
+                fecha,
+                valor,
+            });
+        }
+    }
+
+    let mut findings = Vec::new();
+    for ((entity, contractor), mut contracts) in groups {
+        contracts.sort_by_key(|c| c.fecha);
+
+        for &limit in &options.thresholds {
+            let floor = limit * (1.0 - options.margin);
+            let near_limit: Vec<&SplitCandidate> = contracts
+                .iter()
+                .filter(|c| c.valor >= floor && c.valor < limit)
+                .collect();
+
+            let mut start = 0;
+            while start < near_limit.len() {
+                let first = near_limit[start].fecha;
+                let end = near_limit[start..]
+                    .iter()
+                    .take_while(|c| (c.fecha - first).num_days() <= options.window_days)
+                    .count()
+                    + start;
+                let cluster = &near_limit[start..end];
+                let total: f64 = cluster.iter().map(|c| c.valor).sum();
+
+                if cluster.len() >= options.min_contracts && total > limit {
+                    findings.push(SplittingFinding {
+                        nit_entidad: entity.to_string(),
+                        nit_contratista: contractor.to_string(),
+                        id_contratos: cluster.iter().map(|c| c.id.clone()).collect(),
+                        desde: first,
+                        hasta: cluster[cluster.len() - 1].fecha,
+                        valor_total: total,
+                        umbral: limit,
+                        razon: format!(
+                            "{} contratos justo por debajo de ${:.0} suman ${:.0}",
+                            cluster.len(),
+                            limit,
+                            total
+                        ),
+                    });
+                    start = end;
+                } else {
+                    start += 1;
+                }
+            }
+        }
+    }
+
+    findings.sort_by(|a, b| b.valor_total.total_cmp(&a.valor_total));
+    info!("Splitting check: {} suspicious clusters", findings.len());
+    Ok(findings)
+}
+
+/// Robust rule used by [`detect_price_outliers`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlierMethod {
+    /// |log(v) - median| > k · 1.4826 · MAD
+    Mad,
+    /// log(v) outside [Q1 - k · IQR, Q3 + k · IQR], quartiles as Tukey hinges
+    Iqr,
+}
+
+/// Options for [`detect_price_outliers`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriceOutlierOptions {
+    pub method: OutlierMethod,
+    /// Multiplier of the MAD or IQR
+    pub k: f64,
+    /// Groups with fewer comparable contracts are not evaluated
+    pub min_peers: u32,
+    /// Leading keywords of `objeto_del_contrato` that form the description cluster
+    pub cluster_words: usize,
+}
+
+impl Default for PriceOutlierOptions {
+    fn default() -> Self {
+        Self {
+            method: OutlierMethod::Mad,
+            k: 3.5,
+            min_peers: 10,
+            cluster_words: 2,
+        }
+    }
+}
+
+/// Words ignored when clustering contract descriptions
+const DESCRIPTION_STOPWORDS: [&str; 16] = [
+    "de", "del", "la", "las", "el", "los", "para", "por", "con", "en", "y", "al", "un", "una",
+    "que", "sus",
+];
+
+/// Coarse description cluster: the first significant keywords of the text
+/// ("Prestación de servicios profesionales ..." -> "prestacion servicios")
+pub fn description_cluster(text: &str, words: usize) -> String {
+    fold_text(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() > 2 && !DESCRIPTION_STOPWORDS.contains(w))
+        .filter(|w| !w.chars().all(|c| c.is_ascii_digit()))
+        .take(words)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Keys that define a group of comparable contracts
+const PEER_KEYS: [&str; 4] = [
+    "tipo_de_contrato",
+    MODALITY_COLUMN,
+    "departamento",
+    "grupo_descripcion",
+];
+
+/// Red Flag: Detect prices far from comparable contracts
+///
+/// Contracts are grouped by type, modality, department and description
+/// cluster; within each group the robust rule from `options` is applied to
+/// the log of `valor_del_contrato`. Returns the outliers (above or below)
+/// with `grupo_descripcion`, `grupo_tamano` (peer group size),
+/// `mediana_grupo` (median value in COP), `percentil` (rank within the
+/// group, 0-100) and `razon` columns.
+pub fn detect_price_outliers(
+    contratos: LazyFrame,
+    options: &PriceOutlierOptions,
+) -> PolarsResult<DataFrame> {
+    let words = options.cluster_words;
+    let peers = || PEER_KEYS.map(col);
+
+    let log_valor = || col("log_valor");
+    let median = || log_valor().median().over(peers());
+    let mad = (log_valor() - log_valor().median()).abs().median().over(peers());
+    let lower_hinge = log_valor()
+        .filter(log_valor().lt_eq(log_valor().median()))
+        .median()
+        .over(peers());
+    let upper_hinge = log_valor()
+        .filter(log_valor().gt_eq(log_valor().median()))
+        .median()
+        .over(peers());
+
+    let is_outlier = match options.method {
+        OutlierMethod::Mad => {
+            let scale = col("mad") * lit(1.4826);
+            scale
+                .clone()
+                .gt(lit(0.0))
+                .and((log_valor() - col("log_mediana")).abs().gt(scale * lit(options.k)))
+        }
+        OutlierMethod::Iqr => {
+            let iqr = col("q3") - col("q1");
+            log_valor()
+                .lt(col("q1") - iqr.clone() * lit(options.k))
+                .or(log_valor().gt(col("q3") + iqr * lit(options.k)))
+        }
+    };
+
+    let rank = log_valor()
+        .arg_sort(SortOptions::default())
+        .arg_sort(SortOptions::default())
+        .over(peers());
+
+    let outliers = contratos
+        .filter(col("valor_del_contrato").gt(lit(0.0)))
+        .with_column(
+            col("objeto_del_contrato")
+                .map(
+                    move |c| {
+                        let clusters: StringChunked = c
+                            .str()?
+                            .into_iter()
+                            .map(|t| t.map(|t| description_cluster(t, words)))
+                            .collect();
+                        Ok(Some(clusters.with_name(c.name().clone()).into_column()))
+                    },
+                    GetOutput::from_type(DataType::String),
+                )
+                .alias("grupo_descripcion"),
+        )
+        .filter(col("grupo_descripcion").neq(lit("")))
+        .with_columns([
+            col("valor_del_contrato").log(std::f64::consts::E).alias("log_valor"),
+            len().over(peers()).cast(DataType::UInt32).alias("grupo_tamano"),
+        ])
+        .filter(col("grupo_tamano").gt_eq(lit(options.min_peers)))
+        .with_columns([
+            median().alias("log_mediana"),
+            mad.alias("mad"),
+            lower_hinge.alias("q1"),
+            upper_hinge.alias("q3"),
+            ((rank.cast(DataType::Float64) + lit(1.0)) * lit(100.0)
+                / col("grupo_tamano").cast(DataType::Float64))
+            .alias("percentil"),
+        ])
+        .filter(is_outlier)
+        .with_column(col("log_mediana").exp().alias("mediana_grupo"))
+        .drop(["log_valor", "log_mediana", "mad", "q1", "q3"])
+        .collect()?;
+
+    let valores = outliers.column("valor_del_contrato")?.f64()?;
+    let medianas = outliers.column("mediana_grupo")?.f64()?;
+    let tamanos = outliers.column("grupo_tamano")?.u32()?;
+    let reasons: Vec<Option<String>> = valores
+        .into_iter()
+        .zip(medianas)
+        .zip(tamanos)
+        .map(|((valor, mediana), n)| {
+            let ratio = valor? / mediana?;
+            let diff = if ratio >= 1.0 {
+                format!("{:.0}% por encima", (ratio - 1.0) * 100.0)
+            } else {
+                format!("{:.0}% por debajo", (1.0 - ratio) * 100.0)
+            };
+            Some(format!(
+                "Valor {} de la mediana de {} contratos comparables",
+                diff,
+                n?
+            ))
+        })
+        .collect();
+
+    let mut outliers = outliers;
+    outliers.with_column(Series::new(REASON_COLUMN.into(), reasons))?;
+
+    info!("Price outliers: {} contracts flagged", outliers.height());
+    Ok(outliers)
 }
 
 /// Analyze a Polars DataFrame for Benford's Law
@@ -117,14 +1037,440 @@ mod tests {
         assert_eq!(first_digit(999.0), Some(9));
         assert_eq!(first_digit(0.0), None);
         assert_eq!(first_digit(-50.0), Some(5));
+        assert_eq!(first_digit(1000.0), Some(1));
+        assert_eq!(first_digit(0.042), Some(4));
+        assert_eq!(first_digit(f64::NAN), None);
+    }
+
+    fn bidders_df(modalidades: &[&str], ofertas: &[Option<i64>]) -> DataFrame {
+        df!(
+            "id_contrato" => (0..modalidades.len()).map(|i| format!("CO1.{}", i)).collect::<Vec<_>>(),
+            MODALITY_COLUMN => modalidades,
+            OFFERS_COLUMN => ofertas,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_modalidad_classification() {
+        assert_eq!(Modalidad::from_secop("Licitación pública"), Modalidad::LicitacionPublica);
+        assert_eq!(
+            Modalidad::from_secop("Selección Abreviada de Menor Cuantía"),
+            Modalidad::SeleccionAbreviada
+        );
+        assert_eq!(Modalidad::from_secop("Concurso de méritos abierto"), Modalidad::ConcursoMeritos);
+        assert_eq!(Modalidad::from_secop("Mínima cuantía"), Modalidad::MinimaCuantia);
+        assert_eq!(
+            Modalidad::from_secop("Contratación Directa (con ofertas)"),
+            Modalidad::ContratacionDirecta
+        );
+        assert_eq!(Modalidad::from_secop("Contratación régimen especial"), Modalidad::RegimenEspecial);
+    }
+
+    #[test]
+    fn test_single_bidder_competitive_modalities() {
+        let df = bidders_df(
+            &[
+                "Licitación pública",
+                "Selección abreviada subasta inversa",
+                "Concurso de méritos abierto",
+                "Licitación pública",
+            ],
+            &[Some(1), Some(1), Some(1), Some(4)],
+        );
+
+        let flagged = detect_single_bidders(&df).unwrap();
+        assert_eq!(flagged.height(), 3);
+        let reasons = flagged.column(REASON_COLUMN).unwrap().str().unwrap();
+        assert_eq!(reasons.get(0), Some("Licitación pública con un único oferente"));
+        assert_eq!(reasons.get(1), Some("Selección abreviada con un único oferente"));
+        assert_eq!(reasons.get(2), Some("Concurso de méritos con un único oferente"));
+    }
+
+    #[test]
+    fn test_single_bidder_ignores_legal_single_offers() {
+        let df = bidders_df(
+            &[
+                "Contratación directa",
+                "Contratación Directa (con ofertas)",
+                "Contratación régimen especial",
+                "Mínima cuantía",
+            ],
+            &[Some(1), Some(1), Some(1), Some(1)],
+        );
+        assert_eq!(detect_single_bidders(&df).unwrap().height(), 0);
+    }
+
+    #[test]
+    fn test_single_bidder_missing_offer_count() {
+        let df = bidders_df(&["Licitación pública", "Selección abreviada"], &[None, Some(0)]);
+        assert_eq!(detect_single_bidders(&df).unwrap().height(), 0);
+    }
+
+    fn timelines_df(rows: &[(&str, Option<&str>, Option<&str>)]) -> DataFrame {
+        let date = |d: Option<&str>| d.map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap());
+        df!(
+            MODALITY_COLUMN => rows.iter().map(|r| r.0).collect::<Vec<_>>(),
+            PUBLICATION_COLUMN => rows.iter().map(|r| date(r.1)).collect::<Vec<_>>(),
+            CLOSING_COLUMN => rows.iter().map(|r| date(r.2)).collect::<Vec<_>>(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_short_timeline_counts_business_days() {
+        let df = timelines_df(&[
+            // Holy Week 2024: only 4 business days despite 11 calendar days
+            ("Selección abreviada de menor cuantía", Some("2024-03-22"), Some("2024-04-02")),
+            // 5 business days, exactly the minimum
+            ("Selección abreviada de menor cuantía", Some("2024-04-01"), Some("2024-04-08")),
+            ("Licitación pública", Some("2024-04-01"), Some("2024-04-08")),
+        ]);
+
+        let flagged = detect_short_timelines(&df, &TimelineThresholds::default()).unwrap();
+        assert_eq!(flagged.height(), 2);
+
+        let days = flagged.column(DURATION_COLUMN).unwrap().i64().unwrap();
+        let limits = flagged.column(THRESHOLD_COLUMN).unwrap().i64().unwrap();
+        assert_eq!((days.get(0), limits.get(0)), (Some(4), Some(5)));
+        assert_eq!((days.get(1), limits.get(1)), (Some(5), Some(10)));
+        assert_eq!(
+            flagged.column(REASON_COLUMN).unwrap().str().unwrap().get(1),
+            Some("Licitación pública con 5 días hábiles entre publicación y cierre (mínimo 10)")
+        );
+    }
+
+    #[test]
+    fn test_short_timeline_thresholds_are_configurable() {
+        let df = timelines_df(&[
+            ("Contratación directa", Some("2024-04-01"), Some("2024-04-01")),
+            ("Mínima cuantía", Some("2024-04-01"), Some("2024-04-03")),
+            ("Licitación pública", None, Some("2024-04-03")),
+            ("Licitación pública", Some("2024-04-03"), Some("2024-04-01")),
+        ]);
+        assert_eq!(detect_short_timelines(&df, &TimelineThresholds::default()).unwrap().height(), 0);
+
+        let mut strict = TimelineThresholds::default();
+        strict.min_business_days.insert(Modalidad::MinimaCuantia, 3);
+        assert_eq!(detect_short_timelines(&df, &strict).unwrap().height(), 1);
+
+        let parsed: TimelineThresholds =
+            serde_json::from_str(r#"{"min_business_days": {"contratacion_directa": 1}}"#).unwrap();
+        assert_eq!(parsed.get(Modalidad::ContratacionDirecta), Some(1));
+        assert_eq!(parsed.get(Modalidad::LicitacionPublica), None);
+        assert_eq!(detect_short_timelines(&df, &parsed).unwrap().height(), 1);
+    }
+
+    fn market_df(rows: &[(&str, &str, &str, f64)]) -> DataFrame {
+        let firma = NaiveDate::from_ymd_opt(2024, 5, 2).unwrap();
+        df!(
+            "nit_entidad" => rows.iter().map(|r| r.0).collect::<Vec<_>>(),
+            "nombre_entidad" => rows.iter().map(|r| format!("Entidad {}", r.0)).collect::<Vec<_>>(),
+            "tipo_de_contrato" => rows.iter().map(|r| r.1).collect::<Vec<_>>(),
+            "nit_contratista" => rows.iter().map(|r| r.2).collect::<Vec<_>>(),
+            "valor_del_contrato" => rows.iter().map(|r| r.3).collect::<Vec<_>>(),
+            "fecha_de_firma" => vec![firma; rows.len()],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_hhi_per_entity() {
+        let df = market_df(&[
+            // Monopoly: one contractor, five contracts
+            ("800", "Obra", "A", 10.0),
+            ("800", "Obra", "A", 20.0),
+            ("800", "Obra", "A", 30.0),
+            ("800", "Obra", "A", 15.0),
+            ("800", "Obra", "A", 25.0),
+            // Four equal contractors: HHI 2500, not above the threshold
+            ("900", "Obra", "A", 50.0),
+            ("900", "Obra", "B", 50.0),
+            ("900", "Suministro", "C", 50.0),
+            ("900", "Suministro", "D", 50.0),
+            ("900", "Suministro", "D", 0.0),
+        ]);
+
+        let result = concentration_by_entity(&df, &ConcentrationOptions::default()).unwrap();
+        let records = concentration_records(&result).unwrap();
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(records[0].nit_entidad, "800");
+        assert!((records[0].hhi - 10_000.0).abs() < 1e-6);
+        assert_eq!((records[0].contratos, records[0].contratistas), (5, 1));
+        assert!(records[0].concentrada);
+
+        assert_eq!(records[1].nit_entidad, "900");
+        assert!((records[1].hhi - 2_500.0).abs() < 1e-6);
+        assert!((records[1].top_share - 0.75).abs() < 1e-9);
+        assert_eq!(records[1].nivel, "Moderada");
+        assert!(!records[1].concentrada);
+    }
+
+    #[test]
+    fn test_hhi_by_contract_type_and_min_contracts() {
+        let df = market_df(&[
+            ("900", "Obra", "A", 50.0),
+            ("900", "Obra", "B", 50.0),
+            ("900", "Suministro", "C", 80.0),
+            ("900", "Suministro", "C", 20.0),
+        ]);
+        let options = ConcentrationOptions {
+            by_year: true,
+            by_contract_type: true,
+            min_contracts: 2,
+            ..Default::default()
+        };
+
+        let records = concentration_records(&concentration_by_entity(&df, &options).unwrap()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].tipo_de_contrato.as_deref(), Some("Suministro"));
+        assert_eq!(records[0].anio, Some(2024));
+        assert!(records[0].concentrada);
+        assert!((records[1].hhi - 5_000.0).abs() < 1e-6);
+        assert_eq!(records[1].nivel, "Alta");
+        assert!(records[1].concentrada);
+
+        let strict = ConcentrationOptions { min_contracts: 3, ..options };
+        let records = concentration_records(&concentration_by_entity(&df, &strict).unwrap()).unwrap();
+        assert!(records.iter().all(|r| !r.concentrada));
+    }
+
+    fn splitting_df(rows: &[(&str, &str, f64, &str)]) -> DataFrame {
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        df!(
+            "id_contrato" => rows.iter().map(|r| r.0).collect::<Vec<_>>(),
+            "nit_entidad" => vec!["800"; rows.len()],
+            "nit_contratista" => rows.iter().map(|r| r.1).collect::<Vec<_>>(),
+            "valor_del_contrato" => rows.iter().map(|r| r.2).collect::<Vec<_>>(),
+            "fecha_de_firma" => rows.iter().map(|r| date(r.3)).collect::<Vec<_>>(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_splitting_cluster_below_threshold() {
+        let df = splitting_df(&[
+            ("CO1.1", "A", 125_000_000.0, "2024-03-01"),
+            ("CO1.2", "A", 128_000_000.0, "2024-03-10"),
+            ("CO1.3", "A", 129_500_000.0, "2024-03-25"),
+            // Outside the 30-day window of the cluster
+            ("CO1.4", "A", 127_000_000.0, "2024-05-01"),
+            // Different contractor
+            ("CO1.5", "B", 126_000_000.0, "2024-03-02"),
+        ]);
+
+        let findings = detect_contract_splitting(&df, &SplittingOptions::default()).unwrap();
+        assert_eq!(findings.len(), 1);
+        let finding = &findings[0];
+        assert_eq!(finding.nit_contratista, "A");
+        assert_eq!(finding.id_contratos, vec!["CO1.1", "CO1.2", "CO1.3"]);
+        assert_eq!(finding.desde, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(finding.hasta, NaiveDate::from_ymd_opt(2024, 3, 25).unwrap());
+        assert_eq!(finding.valor_total, 382_500_000.0);
+        assert_eq!(finding.umbral, 130_000_000.0);
+    }
+
+    #[test]
+    fn test_splitting_ignores_values_far_from_threshold() {
+        let df = splitting_df(&[
+            ("CO1.1", "A", 60_000_000.0, "2024-03-01"),
+            ("CO1.2", "A", 80_000_000.0, "2024-03-02"),
+            ("CO1.3", "A", 131_000_000.0, "2024-03-03"),
+        ]);
+        assert!(detect_contract_splitting(&df, &SplittingOptions::default()).unwrap().is_empty());
+
+        let loose = SplittingOptions { margin: 0.6, ..Default::default() };
+        let findings = detect_contract_splitting(&df, &loose).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].id_contratos, vec!["CO1.1", "CO1.2"]);
+    }
+
+    /// Values whose first digits follow Benford exactly (proportions out of `n`)
+    fn benford_sample(n: usize) -> Vec<f64> {
+        BENFORD_EXPECTED
+            .iter()
+            .enumerate()
+            .flat_map(|(i, p)| {
+                let count = (p * n as f64).round() as usize;
+                (0..count).map(move |k| (i + 1) as f64 * 1_000_000.0 + k as f64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_benford_conforming_sample() {
+        let result = analyze_benford(&benford_sample(10_000));
+        assert!(result.p_value > 0.99);
+        assert!(result.mad < 0.001);
+        assert_eq!(result.conformity, Some(BenfordConformity::Close));
+        assert!(!result.is_anomalous);
+        assert!(result.z_scores.iter().all(|z| *z < 1.96));
     }
 
     #[test]
     fn test_benford_uniform() {
-        // Uniform distribution should be flagged as anomalous
-        let uniform: Vec<f64> = (1..=9).map(|d| d as f64 * 100.0).collect();
+        // Uniform first digits are far from Benford
+        let uniform: Vec<f64> = (0..900).map(|i| ((i % 9) + 1) as f64 * 100.0).collect();
         let result = analyze_benford(&uniform);
-        // Small sample, may not be statistically significant
-        assert!(result.sample_size > 0);
+        assert_eq!(result.sample_size, 900);
+        assert!(result.p_value < 1e-10);
+        assert!(result.is_anomalous);
+        assert_eq!(result.conformity, Some(BenfordConformity::Nonconformity));
+        // Digit 1 is heavily under-represented
+        assert!(result.z_scores[0] > 10.0);
+    }
+
+    #[test]
+    fn test_leading_two_digits() {
+        assert_eq!(leading_two_digits(123.45), Some(12));
+        assert_eq!(leading_two_digits(1000.0), Some(10));
+        assert_eq!(leading_two_digits(7.0), Some(70));
+        assert_eq!(leading_two_digits(-0.0495), Some(49));
+        assert_eq!(leading_two_digits(0.0), None);
+    }
+
+    #[test]
+    fn test_digit_test_expectations_sum_to_one() {
+        for test in [
+            DigitTest::FirstDigit,
+            DigitTest::SecondDigit,
+            DigitTest::FirstTwoDigits,
+            DigitTest::LastTwoDigits,
+        ] {
+            let expected = test.expected();
+            assert_eq!(expected.len(), test.digits().len());
+            assert!((expected.iter().sum::<f64>() - 1.0).abs() < 1e-4, "{:?}", test);
+        }
+        // Second digit 0 is the most likely at about 12%
+        assert!((DigitTest::SecondDigit.expected()[0] - 0.11968).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_first_two_digits_spots_threshold_clustering() {
+        // Log-uniform background (Benford on every digit) plus values parked just under 50M
+        let mut values: Vec<f64> = (0..5_000).map(|i| 10_f64.powf(6.0 + i as f64 / 5_000.0)).collect();
+        values.extend((0..400).map(|i| 49_000_000.0 + i as f64 * 1_000.0));
+
+        let result = benford_test(&values, DigitTest::FirstTwoDigits);
+        assert!(result.is_anomalous);
+        // A single spike barely moves the MAD over 90 bins; the z-score isolates it
+        assert_ne!(result.conformity, Some(BenfordConformity::Close));
+        assert_eq!(overrepresented_digits(&result, 3.0), vec![49]);
+    }
+
+    #[test]
+    fn test_last_two_digits_detects_rounding() {
+        let uniform: Vec<f64> = (0..10_000).map(|i| 1_000.0 + i as f64).collect();
+        let result = benford_test(&uniform, DigitTest::LastTwoDigits);
+        assert!(!result.is_anomalous);
+        assert_eq!(result.conformity, None);
+
+        // Rounded to the thousand, plus values too small to test
+        let rounded: Vec<f64> = (1..=500).map(|i| i as f64 * 1_000.0).chain([42.0]).collect();
+        let result = benford_test(&rounded, DigitTest::LastTwoDigits);
+        assert_eq!(result.sample_size, 500);
+        assert!(result.is_anomalous);
+        assert_eq!(overrepresented_digits(&result, 3.0), vec![0]);
+    }
+
+    #[test]
+    fn test_second_digit() {
+        let values = [10.0, 105.0, 2_000.0, 31.0, 92.5];
+        let result = benford_test(&values, DigitTest::SecondDigit);
+        assert_eq!(result.sample_size, 5);
+        assert_eq!(result.observed_distribution[0], 0.6);
+        assert_eq!(result.observed_distribution[1], 0.2);
+        assert_eq!(result.observed_distribution[2], 0.2);
+    }
+
+    #[test]
+    fn test_description_cluster() {
+        assert_eq!(
+            description_cluster("Prestación de servicios profesionales para la oficina", 2),
+            "prestacion servicios"
+        );
+        assert_eq!(description_cluster("SUMINISTRO DE 500 resmas de papel", 3), "suministro resmas papel");
+        assert_eq!(description_cluster("de la y", 2), "");
+    }
+
+    fn prices_df(valores: &[f64]) -> DataFrame {
+        let n = valores.len();
+        df!(
+            "id_contrato" => (0..n).map(|i| format!("CO1.{}", i)).collect::<Vec<_>>(),
+            "tipo_de_contrato" => vec!["Suministro"; n],
+            MODALITY_COLUMN => vec!["Mínima cuantía"; n],
+            "departamento" => vec!["Boyacá"; n],
+            "objeto_del_contrato" => vec!["Suministro de papelería para la oficina"; n],
+            "valor_del_contrato" => valores,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_price_outliers_mad() {
+        let mut valores: Vec<f64> = (0..20).map(|i| 10_000_000.0 + i as f64 * 250_000.0).collect();
+        valores.push(95_000_000.0);
+        valores.push(0.0);
+
+        let outliers = detect_price_outliers(prices_df(&valores).lazy(), &PriceOutlierOptions::default()).unwrap();
+        assert_eq!(outliers.height(), 1);
+        assert_eq!(outliers.column("id_contrato").unwrap().str().unwrap().get(0), Some("CO1.20"));
+        assert_eq!(outliers.column("grupo_tamano").unwrap().u32().unwrap().get(0), Some(21));
+        assert_eq!(outliers.column("percentil").unwrap().f64().unwrap().get(0), Some(100.0));
+        assert_eq!(
+            outliers.column("grupo_descripcion").unwrap().str().unwrap().get(0),
+            Some("suministro papeleria")
+        );
+        let mediana = outliers.column("mediana_grupo").unwrap().f64().unwrap().get(0).unwrap();
+        assert!((mediana - 12_500_000.0).abs() < 1.0);
+        assert!(outliers
+            .column(REASON_COLUMN)
+            .unwrap()
+            .str()
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .contains("por encima de la mediana de 21 contratos"));
+    }
+
+    #[test]
+    fn test_price_outliers_iqr_and_min_peers() {
+        let mut valores: Vec<f64> = (0..20).map(|i| 10_000_000.0 + i as f64 * 250_000.0).collect();
+        valores.push(1_000_000.0);
+
+        let iqr = PriceOutlierOptions { method: OutlierMethod::Iqr, k: 1.5, ..Default::default() };
+        let outliers = detect_price_outliers(prices_df(&valores).lazy(), &iqr).unwrap();
+        assert_eq!(outliers.height(), 1);
+        assert_eq!(outliers.column("percentil").unwrap().f64().unwrap().get(0), Some(100.0 / 21.0));
+
+        let strict = PriceOutlierOptions { min_peers: 50, ..iqr };
+        assert_eq!(detect_price_outliers(prices_df(&valores).lazy(), &strict).unwrap().height(), 0);
+    }
+
+    #[test]
+    fn test_benford_by_entity_ranks_and_guards_sample() {
+        let conforming = benford_sample(1_000);
+        let uniform: Vec<f64> = (0..900).map(|i| ((i % 9) + 1) as f64 * 100.0).collect();
+        let small = vec![5.0; 10];
+
+        let mut nits = Vec::new();
+        let mut valores = Vec::new();
+        for (nit, values) in [("800", &conforming), ("900", &uniform), ("700", &small)] {
+            nits.extend(std::iter::repeat_n(nit, values.len()));
+            valores.extend(values.iter().copied());
+        }
+        let df = df!(
+            "nit_entidad" => nits.clone(),
+            "nombre_entidad" => nits,
+            "valor_del_contrato" => valores,
+        )
+        .unwrap();
+
+        let ranking = benford_by_entity(&df, "valor_del_contrato", BENFORD_MIN_SAMPLE).unwrap();
+        assert_eq!(ranking.len(), 2);
+        assert_eq!(ranking[0].nit_entidad, "900");
+        assert_eq!(ranking[1].nit_entidad, "800");
+        assert!(ranking[0].result.mad > ranking[1].result.mad);
     }
 }
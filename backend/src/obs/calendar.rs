@@ -0,0 +1,132 @@
+//! Colombian business-day calendar.
+//!
+//! Holidays follow Ley 51 de 1983 ("Ley Emiliani"): some are fixed, others
+//! move to the following Monday, and a group is anchored on Easter Sunday.
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+/// Easter Sunday (Gregorian calendar, anonymous algorithm)
+pub fn easter_sunday(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).expect("valid Easter date")
+}
+
+/// Move a date to the next Monday unless it already is one
+fn next_monday(date: NaiveDate) -> NaiveDate {
+    let offset = (7 - date.weekday().num_days_from_monday()) % 7;
+    date + Duration::days(offset as i64)
+}
+
+/// All national holidays for a given year
+pub fn colombian_holidays(year: i32) -> Vec<NaiveDate> {
+    let ymd = |month: u32, day: u32| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let easter = easter_sunday(year);
+
+    let mut holidays = vec![
+        // Fixed
+        ymd(1, 1),
+        ymd(5, 1),
+        ymd(7, 20),
+        ymd(8, 7),
+        ymd(12, 8),
+        ymd(12, 25),
+        // Moved to Monday
+        next_monday(ymd(1, 6)),
+        next_monday(ymd(3, 19)),
+        next_monday(ymd(6, 29)),
+        next_monday(ymd(8, 15)),
+        next_monday(ymd(10, 12)),
+        next_monday(ymd(11, 1)),
+        next_monday(ymd(11, 11)),
+        // Easter-based
+        easter - Duration::days(3),
+        easter - Duration::days(2),
+        easter + Duration::days(43),
+        easter + Duration::days(64),
+        easter + Duration::days(71),
+    ];
+    holidays.sort();
+    holidays.dedup();
+    holidays
+}
+
+/// Business-day calculator over a range of years
+pub struct HolidayCalendar {
+    holidays: HashSet<NaiveDate>,
+}
+
+impl HolidayCalendar {
+    pub fn colombia(years: RangeInclusive<i32>) -> Self {
+        Self {
+            holidays: years.flat_map(colombian_holidays).collect(),
+        }
+    }
+
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !self.holidays.contains(&date)
+    }
+
+    /// Business days in `(start, end]`, i.e. counted from the day after `start`.
+    /// Negative when `end` is before `start`.
+    pub fn business_days_between(&self, start: NaiveDate, end: NaiveDate) -> i64 {
+        if end < start {
+            return -self.business_days_between(end, start);
+        }
+        start
+            .iter_days()
+            .skip(1)
+            .take_while(|d| *d <= end)
+            .filter(|d| self.is_business_day(*d))
+            .count() as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_easter() {
+        assert_eq!(easter_sunday(2024), date(2024, 3, 31));
+        assert_eq!(easter_sunday(2025), date(2025, 4, 20));
+    }
+
+    #[test]
+    fn test_holidays_2024() {
+        let holidays = colombian_holidays(2024);
+        assert_eq!(holidays.len(), 18);
+        assert!(holidays.contains(&date(2024, 1, 8))); // Reyes, moved from Saturday
+        assert!(holidays.contains(&date(2024, 3, 28))); // Jueves Santo
+        assert!(holidays.contains(&date(2024, 5, 13))); // Ascensión
+        assert!(holidays.contains(&date(2024, 6, 3))); // Corpus Christi
+        assert!(holidays.contains(&date(2024, 11, 11))); // Independencia de Cartagena
+    }
+
+    #[test]
+    fn test_business_days_skip_weekends_and_holy_week() {
+        let calendar = HolidayCalendar::colombia(2024..=2024);
+        // Fri 22 Mar -> Tue 2 Apr: San José, Holy Thursday/Friday and two weekends excluded
+        assert_eq!(calendar.business_days_between(date(2024, 3, 22), date(2024, 4, 2)), 4);
+        assert_eq!(calendar.business_days_between(date(2024, 3, 22), date(2024, 3, 22)), 0);
+        assert_eq!(calendar.business_days_between(date(2024, 4, 2), date(2024, 3, 22)), -4);
+    }
+}
@@ -1,13 +1,33 @@
 use anyhow::{Context, Result};
-use reqwest::Client;
+use futures_util::TryStreamExt;
 use serde::{Deserialize, Serialize};
+use socrata_sdk::{
+    AggregateQuery, Condition, DatasetMetadata, DriftPolicy, ExpectedSchema, PaginationOptions,
+    ReplayMode, SoqlQuery,
+};
+use std::path::{Path, PathBuf};
 use tracing::{info, warn};
 
+use crate::obs::risk::FiredRule;
+use crate::obs::storage;
+use crate::obs::sync_state::{DatasetCursor, SyncState};
+
 const SECOP_CONTRATOS_ID: &str = "jbjy-vk9h";
 const SECOP_PROCESOS_ID: &str = "p6dx-8zbt";
-const SOCRATA_BASE_URL: &str = "https://www.datos.gov.co/resource";
+const SOCRATA_BASE_URL: &str = "https://www.datos.gov.co";
+
+const SYNC_STATE_FILE: &str = "sync_state.json";
+const PARQUET_DIR: &str = "contratos";
+const PROCESOS_PARQUET_DIR: &str = "procesos";
+const GOLDEN_RECORDS_DIR: &str = "golden_records";
+
+/// Portfolio IDs per `$where ... in(...)` request, to keep URLs short
+const PROCESOS_BATCH_SIZE: usize = 100;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Contracts written to the daily report
+pub const REPORT_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ContratoSecop {
     #[serde(default)]
     pub id_contrato: Option<String>,
@@ -37,23 +57,214 @@ pub struct ContratoSecop {
     pub fecha_de_inicio_del_contrato: Option<String>,
     #[serde(default)]
     pub duracion: Option<String>,
+    /// Portfolio ID of the originating process (joins `ProcesoSecop::id_del_portafolio`)
+    #[serde(default)]
+    pub proceso_de_compra: Option<String>,
+    /// Socrata system field `:updated_at`, requested via `$select=:*, *`
+    #[serde(default, alias = ":updated_at")]
+    pub updated_at: Option<String>,
+    /// Socrata system field `:id`; breaks ties between rows sharing `:updated_at`
+    #[serde(default, alias = ":id")]
+    pub row_id: Option<String>,
     #[serde(default)]
     pub risk_level: Option<String>,
     #[serde(default)]
     pub red_flags: Option<Vec<String>>,
+    /// Sum of the weights of the risk rules that fired
+    #[serde(default)]
+    pub risk_score: Option<f64>,
+    /// Rules that fired, with their evidence
+    #[serde(default)]
+    pub risk_evidence: Option<Vec<FiredRule>>,
+    /// Golden record of the contractor in the MDM store
+    #[serde(default)]
+    pub contractor_uuid: Option<String>,
+    /// How the contractor was resolved: "exact", "fuzzy" or "new"
+    #[serde(default)]
+    pub match_type: Option<String>,
+    #[serde(default)]
+    pub match_confidence: Option<f64>,
+}
+
+/// A procurement process from SECOP II Procesos (p6dx-8zbt).
+///
+/// Socrata truncates long column names, so the raw names are accepted as
+/// aliases and the struct serializes with readable ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcesoSecop {
+    #[serde(default)]
+    pub id_del_proceso: Option<String>,
+    /// Join key: matches `ContratoSecop::proceso_de_compra`
+    #[serde(default)]
+    pub id_del_portafolio: Option<String>,
+    #[serde(default)]
+    pub referencia_del_proceso: Option<String>,
+    #[serde(default)]
+    pub entidad: Option<String>,
+    #[serde(default)]
+    pub nit_entidad: Option<String>,
+    #[serde(default)]
+    pub nombre_del_procedimiento: Option<String>,
+    #[serde(default)]
+    pub modalidad_de_contratacion: Option<String>,
+    #[serde(default)]
+    pub estado_del_procedimiento: Option<String>,
+    #[serde(default, alias = "fecha_de_publicacion_del")]
+    pub fecha_de_publicacion: Option<String>,
+    /// Deadline for receiving offers
+    #[serde(default, alias = "fecha_de_recepcion_de")]
+    pub fecha_de_cierre: Option<String>,
+    /// Unique suppliers that submitted a response
+    #[serde(default, alias = "proveedores_unicos_con")]
+    pub numero_de_oferentes: Option<String>,
+    /// Total responses (offers) received
+    #[serde(default, alias = "respuestas_al_procedimiento")]
+    pub numero_de_ofertas: Option<String>,
+    /// Estimated price of the process
+    #[serde(default)]
+    pub precio_base: Option<String>,
+}
+
+/// Fields of the contracts dataset read into [`ContratoSecop`]
+fn contratos_schema() -> ExpectedSchema {
+    ExpectedSchema::new()
+        .required("id_contrato", "text")
+        .required("nit_entidad", "text")
+        .required("valor_del_contrato", "number")
+        .required("fecha_de_firma", "calendar_date")
+        .optional("nombre_entidad")
+        .optional("departamento")
+        .optional("ciudad")
+        .optional("objeto_del_contrato")
+        .optional("tipo_de_contrato")
+        .optional("modalidad_de_contratacion")
+        .optional("nombre_contratista")
+        .optional("nit_contratista")
+        .optional("fecha_de_inicio_del_contrato")
+        .optional("duracion")
+        .optional("proceso_de_compra")
+}
+
+/// Fields of the processes dataset read into [`ProcesoSecop`], by their raw names
+fn procesos_schema() -> ExpectedSchema {
+    ExpectedSchema::new()
+        .required("id_del_proceso", "text")
+        .required("id_del_portafolio", "text")
+        .optional("referencia_del_proceso")
+        .optional("entidad")
+        .optional("nit_entidad")
+        .optional("nombre_del_procedimiento")
+        .optional("modalidad_de_contratacion")
+        .optional("estado_del_procedimiento")
+        .optional("fecha_de_publicacion_del")
+        .optional("fecha_de_recepcion_de")
+        .optional("proveedores_unicos_con")
+        .optional("respuestas_al_procedimiento")
+        .optional("precio_base")
+}
+
+/// Totals computed by Socrata over the whole contracts dataset
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerTotals {
+    pub contratos: u64,
+    pub valor_total: f64,
+    pub por_departamento: Vec<DepartmentTotals>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepartmentTotals {
+    pub departamento: Option<String>,
+    pub contratos: u64,
+    pub valor_total: f64,
 }
 
 pub struct SocrataClient {
-    client: Client,
-    app_token: Option<String>,
+    inner: socrata_sdk::SocrataClient,
 }
 
 impl SocrataClient {
-    pub fn new(app_token: Option<String>) -> Self {
+    pub fn new(app_token: Option<String>, replay: ReplayMode) -> Self {
         Self {
-            client: Client::new(),
-            app_token,
+            inner: socrata_sdk::SocrataClient::new(SOCRATA_BASE_URL, app_token).with_replay(replay),
+        }
+    }
+
+    /// Client for the given token ("ANONYMOUS" means none), recording or
+    /// replaying responses as selected by `SOCRATA_REPLAY`
+    pub fn from_env(token: &str) -> Result<Self> {
+        let replay = replay_mode()?;
+        if replay != ReplayMode::Live {
+            info!("Socrata en modo {:?}", replay);
+        }
+        Ok(Self::new((token != "ANONYMOUS").then(|| token.to_string()), replay))
+    }
+
+    /// Contract count and value per department, aggregated by Socrata
+    pub async fn server_totals(&self) -> Result<ServerTotals> {
+        let query = AggregateQuery::new()
+            .group_by("departamento")
+            .count("contratos")
+            .sum("valor_del_contrato", "valor_total");
+        let rows = self
+            .inner
+            .aggregate(SECOP_CONTRATOS_ID, &query)
+            .await
+            .context("Failed to aggregate contracts on the server")?;
+
+        let por_departamento: Vec<DepartmentTotals> = rows
+            .iter()
+            .map(|row| DepartmentTotals {
+                departamento: row.key("departamento").map(str::to_string),
+                contratos: row.value("contratos").unwrap_or(0.0) as u64,
+                valor_total: row.value("valor_total").unwrap_or(0.0),
+            })
+            .collect();
+
+        Ok(ServerTotals {
+            contratos: por_departamento.iter().map(|d| d.contratos).sum(),
+            valor_total: por_departamento.iter().map(|d| d.valor_total).sum(),
+            por_departamento,
+        })
+    }
+
+    /// Compare the live columns of both datasets with the fields we read.
+    ///
+    /// Breaking changes fail the run under [`DriftPolicy::Fail`]; a failure to
+    /// fetch the metadata itself is only logged. Returns the contracts
+    /// dataset's metadata, with its row count, when it could be fetched.
+    pub async fn check_schemas(&self, policy: DriftPolicy) -> Result<Option<DatasetMetadata>> {
+        let mut contratos_metadata = None;
+        for (dataset_id, schema) in [
+            (SECOP_CONTRATOS_ID, contratos_schema()),
+            (SECOP_PROCESOS_ID, procesos_schema()),
+        ] {
+            let metadata = if dataset_id == SECOP_CONTRATOS_ID {
+                self.inner.metadata_with_row_count(dataset_id).await
+            } else {
+                self.inner.metadata(dataset_id).await
+            };
+            match metadata {
+                Ok(metadata) => {
+                    schema
+                        .compare(&metadata)
+                        .check(policy)
+                        .with_context(|| format!("Esquema de {} cambió", dataset_id))?;
+                    if let Some(rows) = metadata.row_count {
+                        info!("{}: {} filas en el servidor", dataset_id, rows);
+                    }
+                    if dataset_id == SECOP_CONTRATOS_ID {
+                        contratos_metadata = Some(metadata);
+                    }
+                }
+                Err(e) => warn!("No se pudo verificar el esquema de {}: {}", dataset_id, e),
+            }
         }
+        Ok(contratos_metadata)
+    }
+
+    /// Base query for the contracts dataset, including Socrata system fields
+    fn contratos_query() -> SoqlQuery {
+        SoqlQuery::new().select([":*", "*"])
     }
 
     /// Fetch contracts from SECOP II with pagination
@@ -63,40 +274,22 @@ impl SocrataClient {
         offset: u32,
         since_date: Option<&str>,
     ) -> Result<Vec<ContratoSecop>> {
-        let mut url = format!(
-            "{}/{}.json?$limit={}&$offset={}&$order=fecha_de_firma DESC",
-            SOCRATA_BASE_URL, SECOP_CONTRATOS_ID, limit, offset
-        );
+        let mut query = Self::contratos_query()
+            .order_desc("fecha_de_firma")
+            .limit(limit)
+            .offset(offset);
 
         if let Some(date) = since_date {
-            url.push_str(&format!("&$where=fecha_de_firma > '{}'", date));
+            query = query.filter(Condition::gt("fecha_de_firma", date));
         }
 
         info!("Fetching SECOP II contracts: limit={}, offset={}", limit, offset);
 
-        let mut request = self.client.get(&url);
-
-        if let Some(token) = &self.app_token {
-            request = request.header("X-App-Token", token);
-        } else {
-            warn!("No App Token provided. Rate limits may apply.");
-        }
-
-        let response = request
-            .send()
-            .await
-            .context("Failed to send request to Socrata API")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("Socrata API error: {} - {}", status, body);
-        }
-
-        let contratos: Vec<ContratoSecop> = response
-            .json()
+        let contratos: Vec<ContratoSecop> = self
+            .inner
+            .fetch(SECOP_CONTRATOS_ID, &query)
             .await
-            .context("Failed to parse Socrata response")?;
+            .context("Failed to fetch contracts from Socrata API")?;
 
         info!("Fetched {} contracts", contratos.len());
         Ok(contratos)
@@ -108,64 +301,548 @@ impl SocrataClient {
         since_date: Option<&str>,
         max_records: Option<u32>,
     ) -> Result<Vec<ContratoSecop>> {
-        let mut all_contratos = Vec::new();
-        let page_size = 1000;
-        let mut offset = 0;
+        let mut query = Self::contratos_query().order_desc("fecha_de_firma");
+        if let Some(date) = since_date {
+            query = query.filter(Condition::gt("fecha_de_firma", date));
+        }
 
-        loop {
-            let batch = self.fetch_contratos(page_size, offset, since_date).await?;
-            let batch_len = batch.len();
-            all_contratos.extend(batch);
+        let mut options = PaginationOptions::default();
+        if let Some(max) = max_records {
+            options = options.max_records(max as u64);
+        }
 
-            if batch_len < page_size as usize {
-                break; // No more records
-            }
+        let all_contratos: Vec<ContratoSecop> = self
+            .inner
+            .stream(SECOP_CONTRATOS_ID, query, options)
+            .try_collect()
+            .await
+            .context("Failed to fetch contracts from Socrata API")?;
+
+        info!("Total contracts fetched: {}", all_contratos.len());
+        Ok(all_contratos)
+    }
+
+    /// Fetch every contract created or modified since `cursor` (all rows when `None`)
+    pub async fn fetch_delta(
+        &self,
+        cursor: Option<&DatasetCursor>,
+        max_records: Option<u64>,
+    ) -> Result<Vec<ContratoSecop>> {
+        let query = delta_query(cursor);
+
+        let mut options = PaginationOptions::default();
+        if let Some(max) = max_records {
+            options = options.max_records(max);
+        }
+
+        let delta: Vec<ContratoSecop> = self
+            .inner
+            .stream(SECOP_CONTRATOS_ID, query, options)
+            .try_collect()
+            .await
+            .context("Failed to fetch contract delta from Socrata API")?;
+
+        info!("Delta contracts fetched: {}", delta.len());
+        Ok(delta)
+    }
+
+    /// Fetch the SECOP II processes with the given portfolio IDs
+    pub async fn fetch_procesos(&self, portafolio_ids: &[String]) -> Result<Vec<ProcesoSecop>> {
+        let mut procesos = Vec::new();
+
+        for chunk in portafolio_ids.chunks(PROCESOS_BATCH_SIZE) {
+            let query = SoqlQuery::new().filter(Condition::in_list("id_del_portafolio", chunk));
+            let batch: Vec<ProcesoSecop> = self
+                .inner
+                .stream(SECOP_PROCESOS_ID, query, PaginationOptions::default())
+                .try_collect()
+                .await
+                .context("Failed to fetch processes from Socrata API")?;
+            procesos.extend(batch);
+        }
+
+        info!(
+            "Fetched {} processes for {} portfolio IDs",
+            procesos.len(),
+            portafolio_ids.len()
+        );
+        Ok(procesos)
+    }
+}
 
-            offset += page_size;
+/// Replay mode selected by `SOCRATA_REPLAY`; fixtures default to the ones
+/// committed under `backend/fixtures/socrata`, wherever the binary runs from
+pub fn replay_mode() -> Result<ReplayMode> {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/socrata");
+    Ok(ReplayMode::from_env_or(fixtures)?)
+}
+
+/// Build the delta query from a sync cursor.
+///
+/// Prefers `:updated_at` so amended contracts are picked up again; falls back
+/// to `fecha_de_firma`.
+///
+/// Rows always come back in `(:updated_at, :id)` order, and the query resumes
+/// after the last pair seen. A bulk refresh stamps many rows with the same
+/// `:updated_at`, so a fetch cut short by `--max-records` inside that group
+/// continues from its last `:id` instead of fetching the same rows again.
+/// Cursors written before `:id` was tracked fall back to `>=`; duplicates are
+/// removed on merge.
+fn delta_query(cursor: Option<&DatasetCursor>) -> SoqlQuery {
+    let query = SocrataClient::contratos_query()
+        .order_asc(":updated_at")
+        .order_asc(":id");
+
+    match cursor {
+        Some(DatasetCursor {
+            last_updated_at: Some(updated_at),
+            last_id: Some(last_id),
+            ..
+        }) => query.filter(
+            Condition::gt(":updated_at", updated_at)
+                .or(Condition::eq(":updated_at", updated_at).and(Condition::gt(":id", last_id))),
+        ),
+        Some(DatasetCursor {
+            last_updated_at: Some(updated_at),
+            ..
+        }) => query.filter(Condition::gte(":updated_at", updated_at)),
+        Some(DatasetCursor {
+            last_sync_date: Some(date),
+            ..
+        }) => query.filter(Condition::gte("fecha_de_firma", date)),
+        _ => query,
+    }
+}
+
+/// Cursor after a run that merged `delta` into the snapshot covered by `previous`.
+///
+/// A capped fetch stopped partway through the `(:updated_at, :id)` order; every
+/// row left behind sorts after the last one fetched, so the cursor still covers
+/// them. It leaves `rows_updated_at` unset, which keeps the dataset stale until
+/// the rest has been fetched.
+fn next_cursor(
+    previous: Option<&DatasetCursor>,
+    delta: &[ContratoSecop],
+    capped: bool,
+    total_records: usize,
+    rows_updated_at: Option<u64>,
+) -> DatasetCursor {
+    let mut cursor = previous.cloned().unwrap_or_default();
+    for contrato in delta {
+        cursor.observe(
+            contrato.fecha_de_firma.as_deref(),
+            contrato.updated_at.as_deref(),
+            contrato.row_id.as_deref(),
+        );
+    }
+    cursor.last_run = Some(chrono::Utc::now());
+    cursor.total_records = total_records;
+    cursor.rows_updated_at = if capped { None } else { rows_updated_at };
+    cursor
+}
+
+/// The `n` most recently signed contracts
+pub fn latest(contratos: &[ContratoSecop], n: usize) -> Vec<&ContratoSecop> {
+    let mut latest: Vec<&ContratoSecop> = contratos.iter().collect();
+    latest.sort_by(|a, b| b.fecha_de_firma.cmp(&a.fecha_de_firma));
+    latest.truncate(n);
+    latest
+}
+
+/// Distinct portfolio IDs referenced by the given contracts
+fn linked_portafolios(contratos: &[ContratoSecop]) -> Vec<String> {
+    let mut ids: Vec<String> = contratos
+        .iter()
+        .filter_map(|c| c.proceso_de_compra.clone())
+        .filter(|id| !id.trim().is_empty())
+        .collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+/// Options controlling an ingestion run
+#[derive(Debug, Clone)]
+pub struct IngestOptions {
+    /// Directory holding the Parquet snapshots, golden records and the sync state file
+    pub data_dir: PathBuf,
+    /// Ignore the stored cursor and snapshot and reload the dataset from scratch
+    pub full_resync: bool,
+    /// Cap on rows fetched in a single run
+    pub max_records: Option<u64>,
+    /// Whether a required column disappearing upstream fails the run
+    pub schema_drift: DriftPolicy,
+}
+
+impl Default for IngestOptions {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("data"),
+            full_resync: false,
+            max_records: None,
+            schema_drift: DriftPolicy::Fail,
+        }
+    }
+}
 
-            if let Some(max) = max_records {
-                if all_contratos.len() >= max as usize {
-                    all_contratos.truncate(max as usize);
-                    break;
+impl IngestOptions {
+    /// Parse `--full-resync`, `--data-dir=<path>`, `--max-records=<n>` and
+    /// `--schema-drift=warn|fail` from CLI args
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut options = Self::default();
+
+        for arg in args {
+            if arg == "--full-resync" {
+                options.full_resync = true;
+            } else if let Some(dir) = arg.strip_prefix("--data-dir=") {
+                options.data_dir = PathBuf::from(dir);
+            } else if let Some(max) = arg.strip_prefix("--max-records=") {
+                match max.parse() {
+                    Ok(n) => options.max_records = Some(n),
+                    Err(_) => warn!("Valor inválido para --max-records: {}", max),
+                }
+            } else if let Some(policy) = arg.strip_prefix("--schema-drift=") {
+                match policy {
+                    "warn" => options.schema_drift = DriftPolicy::Warn,
+                    "fail" => options.schema_drift = DriftPolicy::Fail,
+                    _ => warn!("Valor inválido para --schema-drift: {}", policy),
                 }
+            } else {
+                warn!("Argumento desconocido ignorado: {}", arg);
             }
-
-            // Respect rate limits - small delay between pages
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         }
 
-        info!("Total contracts fetched: {}", all_contratos.len());
-        Ok(all_contratos)
+        options
+    }
+
+    /// Parquet directory holding the partitioned contracts snapshot
+    pub fn contratos_dir(&self) -> PathBuf {
+        self.data_dir.join(PARQUET_DIR)
+    }
+
+    /// Parquet directory holding the SECOP II processes
+    pub fn procesos_dir(&self) -> PathBuf {
+        self.data_dir.join(PROCESOS_PARQUET_DIR)
+    }
+
+    /// Directory holding the persisted contractor golden records
+    pub fn golden_records_dir(&self) -> PathBuf {
+        self.data_dir.join(GOLDEN_RECORDS_DIR)
     }
 }
 
-    /// Main entry point for data ingestion
-pub async fn run(token: &str) -> Result<Vec<ContratoSecop>> {
-    let app_token = if token == "ANONYMOUS" {
+/// Outcome of an ingestion run
+#[derive(Debug, Clone, Default)]
+pub struct IngestRun {
+    /// Contracts created or modified since the last run (empty when the
+    /// dataset has not changed). The full history is in the Parquet snapshot.
+    pub delta: Vec<ContratoSecop>,
+    /// Contracts in the stored snapshot after the run
+    pub total_records: usize,
+    /// Whether `--max-records` cut the fetch short
+    pub capped: bool,
+}
+
+/// Main entry point for data ingestion
+///
+/// Loads the sync cursor, fetches only what changed since it (plus the SECOP II
+/// processes those contracts belong to), merges it by `id_contrato` into the
+/// Parquet snapshot and only then advances the cursor. Returns the delta;
+/// readers of the full history scan the Parquet snapshot.
+pub async fn run(token: &str, options: &IngestOptions) -> Result<IngestRun> {
+    let client = SocrataClient::from_env(token)?;
+    let metadata = client.check_schemas(options.schema_drift).await?;
+    let rows_updated_at = metadata.as_ref().and_then(|m| m.rows_updated_at);
+
+    let state_path = options.data_dir.join(SYNC_STATE_FILE);
+    let contratos_dir = options.contratos_dir();
+    let procesos_dir = options.procesos_dir();
+    let mut state = SyncState::load(&state_path).await?;
+
+    let existing = if options.full_resync {
+        info!("--full-resync: ignorando cursor y snapshot existentes");
+        0
+    } else {
+        storage::snapshot_len(&contratos_dir).context("Failed to read Parquet snapshot")?
+    };
+
+    // Without a snapshot to merge into, a cursor would only yield a partial dataset
+    let cursor = if existing == 0 {
         None
     } else {
-        Some(token.to_string())
+        state.cursor(SECOP_CONTRATOS_ID).cloned()
     };
 
-    let client = SocrataClient::new(app_token);
+    if let Some(c) = cursor.as_ref().filter(|c| !c.is_stale(rows_updated_at)) {
+        info!(
+            "{} sin cambios desde la última sincronización ({:?}); se reutiliza el snapshot",
+            SECOP_CONTRATOS_ID, c.last_run
+        );
+        return Ok(IngestRun {
+            total_records: existing,
+            ..IngestRun::default()
+        });
+    }
+
+    match &cursor {
+        Some(c) => info!(
+            "Carga incremental desde fecha_de_firma={:?}, :updated_at={:?}",
+            c.last_sync_date, c.last_updated_at
+        ),
+        None => info!("Sin cursor de sincronización: carga completa"),
+    }
+
+    let delta = client.fetch_delta(cursor.as_ref(), options.max_records).await?;
+    let capped = options.max_records.is_some_and(|max| delta.len() as u64 >= max);
+    if capped {
+        warn!(
+            "Carga limitada a {} registros por --max-records; el resto se traerá en la próxima ejecución",
+            delta.len()
+        );
+    }
+    let new_procesos = client.fetch_procesos(&linked_portafolios(&delta)).await?;
+
+    // Persist the snapshot first; the cursor only moves once the data is safe on disk.
+    // An existing snapshot is updated in place, partition by partition.
+    let total_records = if existing == 0 {
+        storage::write_snapshot(&delta, &contratos_dir)
+            .context("Failed to write Parquet snapshot")?;
+        storage::write_procesos(&new_procesos, &procesos_dir)
+            .context("Failed to write processes Parquet snapshot")?;
+        storage::snapshot_len(&contratos_dir).context("Failed to read Parquet snapshot")?
+    } else {
+        storage::merge_procesos(&new_procesos, &procesos_dir)
+            .context("Failed to update processes Parquet snapshot")?;
+        storage::merge_into_snapshot(&delta, &contratos_dir)
+            .context("Failed to update Parquet snapshot")?
+    };
 
-    // Fetch last 100 contracts for testing
-    let contratos = client.fetch_contratos(100, 0, None).await?;
+    info!(
+        "Snapshot actualizado: {} contratos ({} recibidos en este delta)",
+        total_records,
+        delta.len()
+    );
 
-    info!("Sample contract: {:?}", contratos.first());
+    state.set_cursor(
+        SECOP_CONTRATOS_ID,
+        next_cursor(cursor.as_ref(), &delta, capped, total_records, rows_updated_at),
+    );
+    state.save(&state_path).await?;
 
-    Ok(contratos)
+    Ok(IngestRun {
+        delta,
+        total_records,
+        capped,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::Path;
+
+    fn contrato(id: &str, fecha: &str, valor: &str) -> ContratoSecop {
+        serde_json::from_value(serde_json::json!({
+            "id_contrato": id,
+            "fecha_de_firma": fecha,
+            "valor_del_contrato": valor,
+        }))
+        .unwrap()
+    }
 
     #[tokio::test]
     async fn test_fetch_contratos() {
-        let client = SocrataClient::new(None);
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/socrata");
+        let client = SocrataClient::new(None, ReplayMode::Replay(fixtures));
         let result = client.fetch_contratos(10, 0, None).await;
         assert!(result.is_ok());
         assert!(!result.unwrap().is_empty());
     }
+
+    #[test]
+    fn test_latest_contracts() {
+        let contratos = vec![
+            contrato("CO1.PCCNTR.1", "2024-01-10T00:00:00.000", "100"),
+            contrato("CO1.PCCNTR.3", "2024-01-12T00:00:00.000", "300"),
+            contrato("CO1.PCCNTR.2", "2024-01-11T00:00:00.000", "200"),
+        ];
+        let ids: Vec<_> = latest(&contratos, 2)
+            .iter()
+            .map(|c| c.id_contrato.as_deref().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["CO1.PCCNTR.3", "CO1.PCCNTR.2"]);
+    }
+
+    #[test]
+    fn test_proceso_reads_truncated_socrata_names() {
+        let p: ProcesoSecop = serde_json::from_value(serde_json::json!({
+            "id_del_portafolio": "CO1.BDOS.100",
+            "fecha_de_publicacion_del": "2024-02-01T00:00:00.000",
+            "fecha_de_recepcion_de": "2024-02-05T00:00:00.000",
+            "proveedores_unicos_con": "1",
+            "respuestas_al_procedimiento": "1",
+            "precio_base": "50000000",
+        }))
+        .unwrap();
+        assert_eq!(p.fecha_de_cierre.as_deref(), Some("2024-02-05T00:00:00.000"));
+        assert_eq!(p.numero_de_oferentes.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn test_linked_portafolios_are_unique() {
+        let mut a = contrato("CO1.PCCNTR.1", "2024-01-10T00:00:00.000", "100");
+        let mut b = contrato("CO1.PCCNTR.2", "2024-01-11T00:00:00.000", "200");
+        a.proceso_de_compra = Some("CO1.BDOS.7".to_string());
+        b.proceso_de_compra = Some("CO1.BDOS.7".to_string());
+        let c = contrato("CO1.PCCNTR.3", "2024-01-12T00:00:00.000", "300");
+
+        assert_eq!(linked_portafolios(&[a, b, c]), vec!["CO1.BDOS.7".to_string()]);
+    }
+
+    #[test]
+    fn test_updated_at_system_field_is_read() {
+        let c: ContratoSecop = serde_json::from_value(serde_json::json!({
+            "id_contrato": "CO1.PCCNTR.1",
+            ":updated_at": "2024-03-05T08:00:00.000Z",
+        }))
+        .unwrap();
+        assert_eq!(c.updated_at.as_deref(), Some("2024-03-05T08:00:00.000Z"));
+    }
+
+    #[test]
+    fn test_delta_query_prefers_updated_at() {
+        let cursor = DatasetCursor {
+            last_sync_date: Some("2024-03-01T00:00:00.000".to_string()),
+            last_updated_at: Some("2024-03-05T08:00:00.000Z".to_string()),
+            ..Default::default()
+        };
+        let params = delta_query(Some(&cursor)).to_params();
+        assert!(params.contains(&("$where", ":updated_at >= '2024-03-05T08:00:00.000Z'".to_string())));
+
+        let cursor = DatasetCursor {
+            last_id: Some("row-0042".to_string()),
+            ..cursor
+        };
+        let params = delta_query(Some(&cursor)).to_params();
+        assert!(params.contains(&(
+            "$where",
+            ":updated_at > '2024-03-05T08:00:00.000Z' OR (:updated_at = '2024-03-05T08:00:00.000Z' AND :id > 'row-0042')"
+                .to_string()
+        )));
+
+        let params = delta_query(None).to_params();
+        assert!(!params.iter().any(|(k, _)| *k == "$where"));
+        assert!(params.contains(&("$order", ":updated_at ASC, :id ASC".to_string())));
+    }
+
+    /// What the server returns for a delta query, in its `(:updated_at, :id)` order
+    fn serve(server: &[ContratoSecop], cursor: Option<&DatasetCursor>, max: usize) -> Vec<ContratoSecop> {
+        let params = delta_query(cursor).to_params();
+        assert!(params.contains(&("$order", ":updated_at ASC, :id ASC".to_string())));
+        let key = |c: &ContratoSecop| {
+            (
+                c.updated_at.clone().unwrap_or_default(),
+                c.row_id.clone().unwrap_or_default(),
+            )
+        };
+        let mut rows: Vec<ContratoSecop> = server
+            .iter()
+            .filter(|c| match cursor {
+                Some(DatasetCursor {
+                    last_updated_at: Some(since),
+                    last_id: Some(last_id),
+                    ..
+                }) => key(c) > (since.clone(), last_id.clone()),
+                Some(DatasetCursor {
+                    last_updated_at: Some(since),
+                    ..
+                }) => key(c).0 >= *since,
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        rows.sort_by_key(key);
+        rows.truncate(max);
+        rows
+    }
+
+    #[test]
+    fn test_capped_load_then_delta_fetches_everything() {
+        // Signed in one order, last updated in another
+        let server: Vec<ContratoSecop> = (0..6)
+            .map(|i| {
+                let mut c = contrato(
+                    &format!("CO1.PCCNTR.{}", i),
+                    &format!("2024-01-0{}T00:00:00.000", i + 1),
+                    "100",
+                );
+                c.updated_at = Some(format!("2024-03-0{}T08:00:00.000Z", 6 - i));
+                c.row_id = Some(format!("row-{}", i));
+                c
+            })
+            .collect();
+        let serve = |cursor: Option<&DatasetCursor>, max: usize| serve(&server, cursor, max);
+
+        let mut snapshot = std::collections::BTreeSet::new();
+        let first = serve(None, 4);
+        snapshot.extend(first.iter().filter_map(|c| c.id_contrato.clone()));
+        let cursor = next_cursor(None, &first, true, snapshot.len(), Some(1_717_200_000));
+        assert!(cursor.is_stale(Some(1_717_200_000)));
+
+        let second = serve(Some(&cursor), 4);
+        snapshot.extend(second.iter().filter_map(|c| c.id_contrato.clone()));
+        assert_eq!(snapshot.len(), server.len());
+
+        let cursor = next_cursor(Some(&cursor), &second, false, snapshot.len(), Some(1_717_200_000));
+        assert_eq!(cursor.last_updated_at.as_deref(), Some("2024-03-06T08:00:00.000Z"));
+        assert!(!cursor.is_stale(Some(1_717_200_000)));
+    }
+
+    #[test]
+    fn test_capped_runs_inside_one_timestamp_resume_by_id() {
+        // A bulk refresh stamps every row with the same `:updated_at`
+        let server: Vec<ContratoSecop> = (0..10)
+            .map(|i| {
+                let mut c = contrato(&format!("CO1.PCCNTR.{}", i), "2024-01-01T00:00:00.000", "100");
+                c.updated_at = Some("2024-03-01T08:00:00.000Z".to_string());
+                c.row_id = Some(format!("row-{:04}", 9 - i));
+                c
+            })
+            .collect();
+
+        let mut cursor: Option<DatasetCursor> = None;
+        let mut fetched = Vec::new();
+        for _ in 0..3 {
+            let rows = serve(&server, cursor.as_ref(), 4);
+            fetched.extend(rows.iter().filter_map(|c| c.id_contrato.clone()));
+            cursor = Some(next_cursor(cursor.as_ref(), &rows, rows.len() == 4, fetched.len(), None));
+        }
+
+        let cursor = cursor.unwrap();
+        assert_eq!(cursor.last_id.as_deref(), Some("row-0009"));
+        let distinct: std::collections::BTreeSet<_> = fetched.iter().collect();
+        assert_eq!(fetched.len(), server.len());
+        assert_eq!(distinct.len(), server.len());
+
+        let params = delta_query(Some(&cursor)).to_params();
+        let filter = params.iter().find(|(name, _)| *name == "$where").unwrap();
+        assert!(filter.1.contains(":id > 'row-0009'"), "{}", filter.1);
+        assert!(serve(&server, Some(&cursor), 4).is_empty());
+    }
+
+    #[test]
+    fn test_options_from_args() {
+        let options = IngestOptions::from_args(
+            ["--full-resync", "--data-dir=/tmp/veeduria", "--max-records=500"]
+                .iter()
+                .map(|s| s.to_string()),
+        );
+        assert!(options.full_resync);
+        assert_eq!(options.data_dir, PathBuf::from("/tmp/veeduria"));
+        assert_eq!(options.max_records, Some(500));
+        assert_eq!(options.schema_drift, DriftPolicy::Fail);
+
+        let options = IngestOptions::from_args(["--schema-drift=warn".to_string()]);
+        assert_eq!(options.schema_drift, DriftPolicy::Warn);
+    }
 }
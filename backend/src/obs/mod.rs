@@ -1,5 +1,11 @@
 pub mod ingest;
+pub mod sync_state;
+pub mod calendar;
+pub mod storage;
+pub mod normalize;
+pub mod quality;
 pub mod analyze;
+pub mod risk;
 pub mod hf_hub;
 pub mod nlp;
 pub mod vector_db;
@@ -0,0 +1,321 @@
+//! Typed view of SECOP contracts.
+//!
+//! [`ContratoSecop`] keeps the raw Socrata strings so snapshots round-trip
+//! unchanged. [`normalize`] turns one into a [`ContratoNormalizado`] with
+//! numbers, dates and NITs parsed, recording a [`ParseWarning`] for every
+//! value that was present but could not be interpreted.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use mdm_core::cleaner::nit::LegalId;
+use serde::Serialize;
+
+use crate::obs::ingest::ContratoSecop;
+use crate::obs::storage::parse_socrata_datetime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// The value could not be interpreted at all
+    Unparseable,
+    /// The value parsed but is not plausible (e.g. a negative amount)
+    OutOfRange,
+}
+
+/// A raw value that could not be parsed, or parsed to an implausible value
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParseWarning {
+    pub field: &'static str,
+    pub kind: WarningKind,
+    pub value: String,
+    pub message: String,
+}
+
+/// A contract with typed fields
+#[derive(Debug, Clone, Serialize)]
+pub struct ContratoNormalizado {
+    pub id_contrato: Option<String>,
+    pub nombre_entidad: Option<String>,
+    pub nit_entidad: Option<String>,
+    pub departamento: Option<String>,
+    pub ciudad: Option<String>,
+    pub objeto_del_contrato: Option<String>,
+    pub tipo_de_contrato: Option<String>,
+    pub modalidad_de_contratacion: Option<String>,
+    /// Contract value in COP
+    pub valor_del_contrato: Option<f64>,
+    pub nombre_contratista: Option<String>,
+    pub nit_contratista: Option<String>,
+    pub fecha_de_firma: Option<NaiveDate>,
+    pub fecha_de_inicio_del_contrato: Option<NaiveDate>,
+    /// `duracion` converted to calendar days (months count as 30, years as 365)
+    pub duracion_dias: Option<i64>,
+    pub proceso_de_compra: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub warnings: Vec<ParseWarning>,
+}
+
+/// Parse a Socrata `number` value ("1500000", "123.456"), which always uses
+/// '.' as the decimal point. Anything else was typed by hand and goes through
+/// [`parse_money`].
+pub fn parse_number(raw: &str) -> Option<f64> {
+    match raw.trim().parse::<f64>() {
+        Ok(value) => Some(value).filter(|v| v.is_finite()),
+        Err(_) => parse_money(raw),
+    }
+}
+
+/// Parse a free-text monetary amount ("1500000.50", "$ 1.500.000", "1.500.000,50"),
+/// guessing the thousands and decimal separators
+pub fn parse_money(raw: &str) -> Option<f64> {
+    let cleaned: String = raw
+        .trim()
+        .trim_start_matches('$')
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if cleaned.is_empty() {
+        return None;
+    }
+
+    let normalized = match (cleaned.rfind('.'), cleaned.rfind(',')) {
+        // Both separators: the last one is the decimal mark
+        (Some(dot), Some(comma)) if comma > dot => cleaned.replace('.', "").replace(',', "."),
+        (Some(_), Some(_)) => cleaned.replace(',', ""),
+        // Repeated or three-digit groups are thousands separators
+        (Some(_), None) if is_thousands_grouped(&cleaned, '.') => cleaned.replace('.', ""),
+        (None, Some(_)) if is_thousands_grouped(&cleaned, ',') => cleaned.replace(',', ""),
+        (None, Some(_)) => cleaned.replace(',', "."),
+        _ => cleaned,
+    };
+
+    normalized.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+fn is_thousands_grouped(value: &str, separator: char) -> bool {
+    let mut groups = value.split(separator);
+    let head = groups.next().unwrap_or_default();
+    let tail: Vec<&str> = groups.collect();
+    let head_digits = head.trim_start_matches('-');
+    // "0.500" is a decimal, not five hundred
+    !head_digits.is_empty()
+        && head_digits.len() <= 3
+        && !head_digits.starts_with('0')
+        && tail.iter().all(|g| g.len() == 3 && g.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Parse a SECOP duration ("3 Mes(es)", "45 Dia(s)", "1 Año(s)") into days
+pub fn parse_duration_days(raw: &str) -> Option<i64> {
+    let raw = raw.trim().to_lowercase();
+    let split = raw.find(|c: char| !c.is_ascii_digit() && c != '.' && c != ',')?;
+    let amount: f64 = raw[..split].trim().replace(',', ".").parse().ok()?;
+    let unit = raw[split..].trim();
+
+    let days_per_unit = if unit.starts_with("dia") || unit.starts_with("día") {
+        1.0
+    } else if unit.starts_with("semana") {
+        7.0
+    } else if unit.starts_with("mes") {
+        30.0
+    } else if unit.starts_with("año") || unit.starts_with("ano") {
+        365.0
+    } else if unit.starts_with("hora") {
+        1.0 / 24.0
+    } else {
+        return None;
+    };
+
+    Some((amount * days_per_unit).ceil() as i64)
+}
+
+/// Reduce a NIT or cédula to its canonical base number, as the golden records
+/// do ("NIT 890.000.001-7" -> "890000001"); `None` when it is not an ID
+pub fn normalize_nit(raw: &str) -> Option<String> {
+    LegalId::parse(raw).ok().map(|id| id.base)
+}
+
+/// Collects a [`ParseWarning`] for every value that fails to parse
+#[derive(Debug, Default)]
+pub struct Parser {
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl Parser {
+    /// Parse an optional raw value; a present but unparseable value adds a warning
+    pub fn parse<T>(
+        &mut self,
+        field: &'static str,
+        raw: Option<&str>,
+        parse: impl Fn(&str) -> Option<T>,
+        message: &str,
+    ) -> Option<T> {
+        let raw = raw.map(str::trim).filter(|r| !r.is_empty())?;
+        let parsed = parse(raw);
+        if parsed.is_none() {
+            self.warnings.push(ParseWarning {
+                field,
+                kind: WarningKind::Unparseable,
+                value: raw.to_string(),
+                message: message.to_string(),
+            });
+        }
+        parsed
+    }
+
+    fn date(&mut self, field: &'static str, raw: Option<&str>) -> Option<NaiveDate> {
+        self.parse(field, raw, |r| parse_socrata_datetime(r).map(|d| d.date()), "fecha inválida")
+    }
+
+    /// Canonical NIT; a check digit that does not match is kept but warned about
+    fn nit(&mut self, field: &'static str, raw: Option<&str>) -> Option<String> {
+        let id = self.parse(field, raw, |r| LegalId::parse(r).ok(), "NIT inválido")?;
+        if id.validate().is_err() {
+            self.warnings.push(ParseWarning {
+                field,
+                kind: WarningKind::OutOfRange,
+                value: raw.unwrap_or_default().trim().to_string(),
+                message: "dígito de verificación incorrecto".to_string(),
+            });
+        }
+        Some(id.base)
+    }
+}
+
+/// Parse every typed field of a contract
+pub fn normalize(contrato: &ContratoSecop) -> ContratoNormalizado {
+    let mut p = Parser::default();
+
+    let valor = p.parse(
+        "valor_del_contrato",
+        contrato.valor_del_contrato.as_deref(),
+        parse_number,
+        "valor no numérico",
+    );
+    if let Some(v) = valor.filter(|v| *v < 0.0) {
+        p.warnings.push(ParseWarning {
+            field: "valor_del_contrato",
+            kind: WarningKind::OutOfRange,
+            value: v.to_string(),
+            message: "valor negativo".to_string(),
+        });
+    }
+
+    ContratoNormalizado {
+        id_contrato: contrato.id_contrato.clone(),
+        nombre_entidad: contrato.nombre_entidad.clone(),
+        nit_entidad: p.nit("nit_entidad", contrato.nit_entidad.as_deref()),
+        departamento: contrato.departamento.clone(),
+        ciudad: contrato.ciudad.clone(),
+        objeto_del_contrato: contrato.objeto_del_contrato.clone(),
+        tipo_de_contrato: contrato.tipo_de_contrato.clone(),
+        modalidad_de_contratacion: contrato.modalidad_de_contratacion.clone(),
+        valor_del_contrato: valor,
+        nombre_contratista: contrato.nombre_contratista.clone(),
+        nit_contratista: p.nit("nit_contratista", contrato.nit_contratista.as_deref()),
+        fecha_de_firma: p.date("fecha_de_firma", contrato.fecha_de_firma.as_deref()),
+        fecha_de_inicio_del_contrato: p.date(
+            "fecha_de_inicio_del_contrato",
+            contrato.fecha_de_inicio_del_contrato.as_deref(),
+        ),
+        duracion_dias: p.parse(
+            "duracion",
+            contrato.duracion.as_deref(),
+            parse_duration_days,
+            "duración no reconocida",
+        ),
+        proceso_de_compra: contrato.proceso_de_compra.clone(),
+        updated_at: p.parse(
+            "updated_at",
+            contrato.updated_at.as_deref(),
+            parse_socrata_datetime,
+            "fecha inválida",
+        ),
+        warnings: p.warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_money() {
+        assert_eq!(parse_money("1500000"), Some(1_500_000.0));
+        assert_eq!(parse_money("1500000.50"), Some(1_500_000.5));
+        assert_eq!(parse_money("$ 1.500.000"), Some(1_500_000.0));
+        assert_eq!(parse_money("1.500.000,50"), Some(1_500_000.5));
+        assert_eq!(parse_money("1,500,000.50"), Some(1_500_000.5));
+        assert_eq!(parse_money("12,5"), Some(12.5));
+        assert_eq!(parse_money("0.500"), Some(0.5));
+        assert_eq!(parse_money("no aplica"), None);
+        assert_eq!(parse_money(""), None);
+    }
+
+    #[test]
+    fn test_parse_number_is_strict_for_socrata_values() {
+        assert_eq!(parse_number("123.456"), Some(123.456));
+        assert_eq!(parse_number("1500000"), Some(1_500_000.0));
+        assert_eq!(parse_number("-5"), Some(-5.0));
+        // Not a Socrata number, so read as free text
+        assert_eq!(parse_number("$ 1.500.000"), Some(1_500_000.0));
+        assert_eq!(parse_number("NaN"), None);
+        assert_eq!(parse_number("no aplica"), None);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration_days("3 Mes(es)"), Some(90));
+        assert_eq!(parse_duration_days("45 Dia(s)"), Some(45));
+        assert_eq!(parse_duration_days("1 Año(s)"), Some(365));
+        assert_eq!(parse_duration_days("2 Semana(s)"), Some(14));
+        assert_eq!(parse_duration_days("36 Hora(s)"), Some(2));
+        assert_eq!(parse_duration_days("No definido"), None);
+        assert_eq!(parse_duration_days("5 Lustros"), None);
+    }
+
+    #[test]
+    fn test_normalize_nit() {
+        assert_eq!(normalize_nit("890.000.001-7").as_deref(), Some("890000001"));
+        assert_eq!(normalize_nit(" 890000001 ").as_deref(), Some("890000001"));
+        assert_eq!(normalize_nit("NIT 890.000.001-7").as_deref(), Some("890000001"));
+        assert_eq!(normalize_nit("C.C. 079.123.456").as_deref(), Some("79123456"));
+        assert_eq!(normalize_nit("No Definido"), None);
+    }
+
+    #[test]
+    fn test_wrong_check_digit_is_warned() {
+        let contrato: ContratoSecop = serde_json::from_value(serde_json::json!({
+            "nit_entidad": "800.197.268-4",
+            "nit_contratista": "800.197.268-5",
+        }))
+        .unwrap();
+
+        let normalized = normalize(&contrato);
+        assert_eq!(normalized.nit_contratista.as_deref(), Some("800197268"));
+        assert_eq!(normalized.warnings.len(), 1);
+        assert_eq!(normalized.warnings[0].field, "nit_contratista");
+        assert_eq!(normalized.warnings[0].kind, WarningKind::OutOfRange);
+    }
+
+    #[test]
+    fn test_normalize_collects_warnings() {
+        let contrato: ContratoSecop = serde_json::from_value(serde_json::json!({
+            "id_contrato": "CO1.1",
+            "nit_entidad": "890.000.001-7",
+            "valor_del_contrato": "no aplica",
+            "fecha_de_firma": "2024-03-01T00:00:00.000",
+            "fecha_de_inicio_del_contrato": "31/02/2024",
+            "duracion": "3 Mes(es)",
+        }))
+        .unwrap();
+
+        let normalized = normalize(&contrato);
+        assert_eq!(normalized.nit_entidad.as_deref(), Some("890000001"));
+        assert_eq!(normalized.valor_del_contrato, None);
+        assert_eq!(normalized.fecha_de_firma, NaiveDate::from_ymd_opt(2024, 3, 1));
+        assert_eq!(normalized.duracion_dias, Some(90));
+
+        let fields: Vec<&str> = normalized.warnings.iter().map(|w| w.field).collect();
+        assert_eq!(fields, vec!["valor_del_contrato", "fecha_de_inicio_del_contrato"]);
+        assert_eq!(normalized.warnings[0].value, "no aplica");
+    }
+}
@@ -0,0 +1,543 @@
+//! Data-quality report for each ingestion run.
+//!
+//! Measures how complete and well-formed the contracts fetched by the run
+//! (its delta) are and compares the result with the previous run's report, so
+//! schema or quality drift upstream in SECOP shows up as alerts instead of
+//! silently dropped rows. Totals over the whole Parquet snapshot are kept in
+//! a separate summary and cross-checked against Socrata's own aggregates.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::obs::ingest::{ContratoSecop, DepartmentTotals, ServerTotals};
+use crate::obs::normalize::{ContratoNormalizado, WarningKind};
+
+/// Dates before this are placeholders or typos (SECOP has no earlier records)
+const MIN_PLAUSIBLE_DATE: (i32, u32, u32) = (2000, 1, 1);
+/// Duplicate IDs listed in the report
+const MAX_EXAMPLES: usize = 20;
+/// Absolute increase in a field's null rate that raises an alert
+const NULL_RATE_ALERT: f64 = 0.10;
+/// Absolute increase in the share of unparseable values that raises an alert
+const UNPARSEABLE_RATE_ALERT: f64 = 0.01;
+/// Relative gap between local and server totals that raises an alert
+const SERVER_TOTALS_ALERT: f64 = 0.01;
+
+/// Change in one metric since the previous run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricChange {
+    pub metric: String,
+    pub previous: f64,
+    pub current: f64,
+}
+
+/// Local and server-side totals for one department, or the whole dataset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TotalsComparison {
+    pub departamento: Option<String>,
+    pub contratos_locales: u64,
+    pub contratos_servidor: u64,
+    pub valor_local: f64,
+    pub valor_servidor: f64,
+}
+
+impl TotalsComparison {
+    /// Largest relative gap between the local and server count or value
+    pub fn gap(&self) -> f64 {
+        let relative = |local: f64, server: f64| {
+            if server == 0.0 {
+                if local == 0.0 { 0.0 } else { 1.0 }
+            } else {
+                ((local - server) / server).abs()
+            }
+        };
+        relative(self.contratos_locales as f64, self.contratos_servidor as f64)
+            .max(relative(self.valor_local, self.valor_servidor))
+    }
+}
+
+/// Contract count and value of the whole local snapshot, nationally and per department
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SnapshotSummary {
+    pub contratos: u64,
+    pub valor_total: f64,
+    pub por_departamento: Vec<DepartmentTotals>,
+}
+
+impl SnapshotSummary {
+    /// Summarize a contracts frame (see [`crate::obs::storage::read_snapshot`])
+    pub fn from_frame(df: &DataFrame) -> PolarsResult<Self> {
+        let departamentos = df.column("departamento")?.str()?;
+        let valores = df.column("valor_del_contrato")?.f64()?;
+
+        let mut local: BTreeMap<Option<&str>, (u64, f64)> = BTreeMap::new();
+        for (departamento, valor) in departamentos.into_iter().zip(valores) {
+            let entry = local.entry(departamento).or_default();
+            entry.0 += 1;
+            entry.1 += valor.unwrap_or(0.0);
+        }
+
+        Ok(Self {
+            contratos: df.height() as u64,
+            valor_total: local.values().map(|(_, v)| v).sum(),
+            por_departamento: local
+                .into_iter()
+                .map(|(departamento, (contratos, valor_total))| DepartmentTotals {
+                    departamento: departamento.map(str::to_string),
+                    contratos,
+                    valor_total,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Quality metrics for one ingestion run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityReport {
+    pub generated_at: DateTime<Utc>,
+    /// Records fetched by this run (the delta), which the metrics below cover
+    pub total_records: usize,
+    /// The run stopped at `--max-records`, so the snapshot may be incomplete
+    #[serde(default)]
+    pub capped: bool,
+    /// Totals over the whole snapshot after this run
+    #[serde(default)]
+    pub snapshot: Option<SnapshotSummary>,
+    /// Share of records where each raw field is missing or empty
+    pub null_rates: BTreeMap<String, f64>,
+    /// Values present but impossible to parse, per field
+    pub unparseable: BTreeMap<String, usize>,
+    /// NITs that are not a digit string (with optional verification digit), per field
+    pub invalid_nits: BTreeMap<String, usize>,
+    pub duplicate_ids: usize,
+    pub duplicate_examples: Vec<String>,
+    /// Records without `id_contrato`, which the snapshot cannot store
+    #[serde(default)]
+    pub missing_ids: usize,
+    /// Contracts signed after the run date
+    pub future_dates: usize,
+    /// Signature or start dates before 2000
+    pub impossible_dates: usize,
+    pub negative_values: usize,
+    /// Metrics that changed since the previous report
+    #[serde(default)]
+    pub changes: Vec<MetricChange>,
+    /// Changes large enough to need a look
+    #[serde(default)]
+    pub alerts: Vec<String>,
+    /// Local totals checked against Socrata's own aggregates
+    #[serde(default)]
+    pub national_totals: Option<TotalsComparison>,
+    #[serde(default)]
+    pub department_totals: Vec<TotalsComparison>,
+}
+
+fn raw_fields(c: &ContratoSecop) -> [(&'static str, Option<&str>); 16] {
+    [
+        ("id_contrato", c.id_contrato.as_deref()),
+        ("nombre_entidad", c.nombre_entidad.as_deref()),
+        ("nit_entidad", c.nit_entidad.as_deref()),
+        ("departamento", c.departamento.as_deref()),
+        ("ciudad", c.ciudad.as_deref()),
+        ("objeto_del_contrato", c.objeto_del_contrato.as_deref()),
+        ("tipo_de_contrato", c.tipo_de_contrato.as_deref()),
+        ("modalidad_de_contratacion", c.modalidad_de_contratacion.as_deref()),
+        ("valor_del_contrato", c.valor_del_contrato.as_deref()),
+        ("nombre_contratista", c.nombre_contratista.as_deref()),
+        ("nit_contratista", c.nit_contratista.as_deref()),
+        ("fecha_de_firma", c.fecha_de_firma.as_deref()),
+        ("fecha_de_inicio_del_contrato", c.fecha_de_inicio_del_contrato.as_deref()),
+        ("duracion", c.duracion.as_deref()),
+        ("proceso_de_compra", c.proceso_de_compra.as_deref()),
+        ("updated_at", c.updated_at.as_deref()),
+    ]
+}
+
+impl QualityReport {
+    /// Measure a batch; `normalized` must be the normalization of `contracts`.
+    pub fn compute(
+        contracts: &[ContratoSecop],
+        normalized: &[ContratoNormalizado],
+        today: NaiveDate,
+    ) -> Self {
+        let total = contracts.len();
+        let min_date = NaiveDate::from_ymd_opt(
+            MIN_PLAUSIBLE_DATE.0,
+            MIN_PLAUSIBLE_DATE.1,
+            MIN_PLAUSIBLE_DATE.2,
+        )
+        .expect("valid date");
+
+        let mut nulls: BTreeMap<String, usize> = BTreeMap::new();
+        let mut ids: HashMap<&str, usize> = HashMap::new();
+        let mut missing_ids = 0;
+        for contract in contracts {
+            for (field, value) in raw_fields(contract) {
+                let missing = value.is_none_or(|v| v.trim().is_empty());
+                *nulls.entry(field.to_string()).or_default() += missing as usize;
+            }
+            match contract.id_contrato.as_deref().filter(|id| !id.trim().is_empty()) {
+                Some(id) => *ids.entry(id).or_default() += 1,
+                None => missing_ids += 1,
+            }
+        }
+
+        let mut duplicate_examples: Vec<String> = ids
+            .iter()
+            .filter(|(_, n)| **n > 1)
+            .map(|(id, _)| id.to_string())
+            .collect();
+        duplicate_examples.sort();
+        let duplicate_ids = duplicate_examples.len();
+        duplicate_examples.truncate(MAX_EXAMPLES);
+
+        let mut unparseable: BTreeMap<String, usize> = BTreeMap::new();
+        let mut invalid_nits: BTreeMap<String, usize> = BTreeMap::new();
+        let mut future_dates = 0;
+        let mut impossible_dates = 0;
+        let mut negative_values = 0;
+
+        for record in normalized {
+            for warning in &record.warnings {
+                match warning.kind {
+                    WarningKind::Unparseable => {
+                        *unparseable.entry(warning.field.to_string()).or_default() += 1;
+                        if warning.field.starts_with("nit_") {
+                            *invalid_nits.entry(warning.field.to_string()).or_default() += 1;
+                        }
+                    }
+                    WarningKind::OutOfRange => {}
+                }
+            }
+
+            if record.fecha_de_firma.is_some_and(|d| d > today) {
+                future_dates += 1;
+            }
+            if [record.fecha_de_firma, record.fecha_de_inicio_del_contrato]
+                .iter()
+                .flatten()
+                .any(|d| *d < min_date)
+            {
+                impossible_dates += 1;
+            }
+            if record.valor_del_contrato.is_some_and(|v| v < 0.0) {
+                negative_values += 1;
+            }
+        }
+
+        let null_rates = nulls
+            .into_iter()
+            .map(|(field, n)| (field, if total == 0 { 0.0 } else { n as f64 / total as f64 }))
+            .collect();
+
+        Self {
+            generated_at: Utc::now(),
+            total_records: total,
+            capped: false,
+            snapshot: None,
+            null_rates,
+            unparseable,
+            invalid_nits,
+            duplicate_ids,
+            duplicate_examples,
+            missing_ids,
+            future_dates,
+            impossible_dates,
+            negative_values,
+            changes: Vec::new(),
+            alerts: Vec::new(),
+            national_totals: None,
+            department_totals: Vec::new(),
+        }
+    }
+
+    /// Flat view of every metric, used for run-over-run comparison
+    pub fn metrics(&self) -> BTreeMap<String, f64> {
+        let mut metrics = BTreeMap::from([
+            ("total_records".to_string(), self.total_records as f64),
+            ("duplicate_ids".to_string(), self.duplicate_ids as f64),
+            ("missing_ids".to_string(), self.missing_ids as f64),
+            ("future_dates".to_string(), self.future_dates as f64),
+            ("impossible_dates".to_string(), self.impossible_dates as f64),
+            ("negative_values".to_string(), self.negative_values as f64),
+        ]);
+        for (field, rate) in &self.null_rates {
+            metrics.insert(format!("null_rate.{}", field), *rate);
+        }
+        for (field, n) in &self.unparseable {
+            metrics.insert(format!("unparseable.{}", field), *n as f64);
+        }
+        for (field, n) in &self.invalid_nits {
+            metrics.insert(format!("invalid_nits.{}", field), *n as f64);
+        }
+        metrics
+    }
+
+    /// Record what changed since `previous` and raise alerts for large shifts.
+    pub fn compare_with(&mut self, previous: &QualityReport) {
+        let before = previous.metrics();
+        let after = self.metrics();
+
+        let names: std::collections::BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+        self.changes = names
+            .into_iter()
+            .filter_map(|metric| {
+                let previous = before.get(metric).copied().unwrap_or(0.0);
+                let current = after.get(metric).copied().unwrap_or(0.0);
+                (previous != current).then(|| MetricChange {
+                    metric: metric.clone(),
+                    previous,
+                    current,
+                })
+            })
+            .collect();
+
+        let mut alerts = Vec::new();
+        for (field, rate) in &self.null_rates {
+            let old = previous.null_rates.get(field).copied().unwrap_or(0.0);
+            if rate - old >= NULL_RATE_ALERT {
+                alerts.push(format!(
+                    "Tasa de nulos de {} subió de {:.1}% a {:.1}%",
+                    field,
+                    old * 100.0,
+                    rate * 100.0
+                ));
+            }
+        }
+        let share = |n: usize, total: usize| if total == 0 { 0.0 } else { n as f64 / total as f64 };
+        for (field, n) in &self.unparseable {
+            let old = previous.unparseable.get(field).copied().unwrap_or(0);
+            let (old_share, new_share) = (
+                share(old, previous.total_records),
+                share(*n, self.total_records),
+            );
+            if new_share - old_share >= UNPARSEABLE_RATE_ALERT {
+                alerts.push(format!(
+                    "Valores no interpretables en {} subieron de {:.1}% a {:.1}%",
+                    field,
+                    old_share * 100.0,
+                    new_share * 100.0
+                ));
+            }
+        }
+        // Deltas vary in size from run to run; only the snapshot should never shrink
+        if let (Some(before), Some(after)) = (&previous.snapshot, &self.snapshot) {
+            if after.contratos < before.contratos {
+                alerts.push(format!(
+                    "El número de registros bajó de {} a {}",
+                    before.contratos, after.contratos
+                ));
+            }
+        }
+        if self.duplicate_ids > previous.duplicate_ids {
+            alerts.push(format!(
+                "IDs de contrato duplicados aumentaron de {} a {}",
+                previous.duplicate_ids, self.duplicate_ids
+            ));
+        }
+        if self.missing_ids > previous.missing_ids {
+            alerts.push(format!(
+                "Contratos sin id_contrato (no se guardan en el snapshot) aumentaron de {} a {}",
+                previous.missing_ids, self.missing_ids
+            ));
+        }
+        self.alerts = alerts;
+    }
+
+    /// Compare the snapshot totals with the server's, per department and nationally.
+    ///
+    /// Call after [`compare_with`](Self::compare_with), which resets the alerts.
+    /// A capped run leaves the snapshot short on purpose, so it raises no alerts.
+    pub fn cross_check(&mut self, snapshot: &SnapshotSummary, server: &ServerTotals) {
+        let local: HashMap<Option<&str>, (u64, f64)> = snapshot
+            .por_departamento
+            .iter()
+            .map(|d| (d.departamento.as_deref(), (d.contratos, d.valor_total)))
+            .collect();
+
+        let national = TotalsComparison {
+            departamento: None,
+            contratos_locales: snapshot.contratos,
+            contratos_servidor: server.contratos,
+            valor_local: snapshot.valor_total,
+            valor_servidor: server.valor_total,
+        };
+        let departments: Vec<TotalsComparison> = server
+            .por_departamento
+            .iter()
+            .map(|d| {
+                let (contratos, valor) = local.get(&d.departamento.as_deref()).copied().unwrap_or_default();
+                TotalsComparison {
+                    departamento: d.departamento.clone(),
+                    contratos_locales: contratos,
+                    contratos_servidor: d.contratos,
+                    valor_local: valor,
+                    valor_servidor: d.valor_total,
+                }
+            })
+            .collect();
+
+        let scopes = std::iter::once(("todo el país", &national)).chain(
+            departments
+                .iter()
+                .map(|d| (d.departamento.as_deref().unwrap_or("sin departamento"), d)),
+        );
+        for (scope, comparison) in scopes.filter(|_| !self.capped) {
+            if comparison.gap() >= SERVER_TOTALS_ALERT {
+                self.alerts.push(format!(
+                    "Totales de {} difieren del servidor: {} vs {} contratos, {:.0} vs {:.0} COP",
+                    scope,
+                    comparison.contratos_locales,
+                    comparison.contratos_servidor,
+                    comparison.valor_local,
+                    comparison.valor_servidor
+                ));
+            }
+        }
+        self.national_totals = Some(national);
+        self.department_totals = departments;
+    }
+
+    /// Load a previous report, if one exists and is readable.
+    pub async fn load(path: &Path) -> Option<Self> {
+        let raw = tokio::fs::read_to_string(path).await.ok()?;
+        serde_json::from_str(&raw).ok()
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        tokio::fs::write(path, serde_json::to_string_pretty(self)?)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obs::normalize::normalize;
+    use crate::obs::storage::contratos_to_dataframe;
+
+    fn contrato(value: serde_json::Value) -> ContratoSecop {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn report(contracts: &[ContratoSecop]) -> QualityReport {
+        let normalized: Vec<_> = contracts.iter().map(normalize).collect();
+        QualityReport::compute(contracts, &normalized, NaiveDate::from_ymd_opt(2024, 6, 1).unwrap())
+    }
+
+    #[test]
+    fn test_quality_metrics() {
+        let contracts = vec![
+            contrato(serde_json::json!({
+                "id_contrato": "CO1.1",
+                "nit_entidad": "890.000.001-7",
+                "valor_del_contrato": "1000",
+                "fecha_de_firma": "2024-03-01T00:00:00.000",
+            })),
+            contrato(serde_json::json!({
+                "id_contrato": "CO1.1",
+                "nit_entidad": "No Definido",
+                "valor_del_contrato": "-5",
+                "fecha_de_firma": "2025-01-01T00:00:00.000",
+            })),
+            contrato(serde_json::json!({
+                "id_contrato": "CO1.2",
+                "valor_del_contrato": "n/a",
+                "fecha_de_firma": "1900-01-01T00:00:00.000",
+            })),
+        ];
+
+        let report = report(&contracts);
+        assert_eq!(report.total_records, 3);
+        assert!((report.null_rates["nit_entidad"] - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(report.null_rates["departamento"], 1.0);
+        assert_eq!(report.unparseable.get("valor_del_contrato"), Some(&1));
+        assert_eq!(report.invalid_nits.get("nit_entidad"), Some(&1));
+        assert_eq!(report.duplicate_ids, 1);
+        assert_eq!(report.duplicate_examples, vec!["CO1.1"]);
+        assert_eq!(report.future_dates, 1);
+        assert_eq!(report.impossible_dates, 1);
+        assert_eq!(report.negative_values, 1);
+    }
+
+    #[test]
+    fn test_run_over_run_alerts() {
+        let complete = contrato(serde_json::json!({
+            "id_contrato": "CO1.1",
+            "departamento": "Boyacá",
+            "valor_del_contrato": "1000",
+        }));
+        let previous = report(&[complete.clone(), complete.clone()]);
+
+        let mut missing = complete.clone();
+        missing.departamento = None;
+        missing.valor_del_contrato = Some("n/a".to_string());
+        missing.id_contrato = None;
+        let mut current = report(&[missing]);
+        current.compare_with(&previous);
+
+        assert!(current.changes.iter().any(|c| c.metric == "null_rate.departamento"
+            && c.previous == 0.0
+            && c.current == 1.0));
+        assert!(current.alerts.iter().any(|a| a.contains("departamento")));
+        assert!(current.alerts.iter().any(|a| a.contains("no interpretables en valor_del_contrato")));
+        assert_eq!(current.missing_ids, 1);
+        assert!(current.alerts.iter().any(|a| a.contains("sin id_contrato")));
+        // A smaller delta is not a smaller dataset
+        assert!(!current.alerts.iter().any(|a| a.contains("bajó")));
+    }
+
+    #[test]
+    fn test_snapshot_shrinking_alerts() {
+        let contracts = vec![contrato(serde_json::json!({ "id_contrato": "CO1.1" }))];
+        let mut previous = report(&contracts);
+        previous.snapshot = Some(SnapshotSummary { contratos: 10, ..Default::default() });
+
+        let mut current = report(&contracts);
+        current.snapshot = Some(SnapshotSummary { contratos: 8, ..Default::default() });
+        current.compare_with(&previous);
+
+        assert!(current.alerts.iter().any(|a| a.contains("bajó de 10 a 8")));
+    }
+
+    #[test]
+    fn test_cross_check_with_server_totals() {
+        use crate::obs::ingest::DepartmentTotals;
+
+        let contracts = vec![
+            contrato(serde_json::json!({ "departamento": "Boyacá", "valor_del_contrato": "1000" })),
+            contrato(serde_json::json!({ "departamento": "Cauca", "valor_del_contrato": "500" })),
+        ];
+        let snapshot = SnapshotSummary::from_frame(&contratos_to_dataframe(&contracts).unwrap()).unwrap();
+        let server = ServerTotals {
+            contratos: 3,
+            valor_total: 2500.0,
+            por_departamento: vec![
+                DepartmentTotals { departamento: Some("Boyacá".into()), contratos: 1, valor_total: 1000.0 },
+                DepartmentTotals { departamento: Some("Cauca".into()), contratos: 2, valor_total: 1500.0 },
+            ],
+        };
+
+        let mut report = report(&contracts);
+        report.cross_check(&snapshot, &server);
+
+        assert_eq!(report.national_totals.as_ref().unwrap().valor_local, 1500.0);
+        assert_eq!(report.department_totals[0].gap(), 0.0);
+        assert_eq!(report.alerts.len(), 2);
+        assert!(report.alerts[0].contains("todo el país"));
+        assert!(report.alerts[1].contains("Cauca"));
+
+        // A capped run is expected to lag behind the server
+        let mut capped = self::report(&contracts);
+        capped.capped = true;
+        capped.cross_check(&snapshot, &server);
+        assert!(capped.alerts.is_empty());
+        assert_eq!(capped.department_totals.len(), 2);
+    }
+}
@@ -0,0 +1,443 @@
+//! Rule-based risk scoring for contracts.
+//!
+//! Each red flag is a [`RiskRule`] that inspects one contract and returns the
+//! evidence when it fires. Rules are enabled, weighted and described in a TOML
+//! file (see `risk_rules.toml`); a contract's score is the sum of the weights
+//! of the rules that fired, and its level comes from the configured cut-offs.
+//!
+//! Dataset-level analyses (single bidder, short timelines, splitting) run once
+//! over a DataFrame and are plugged in as [`FlaggedContracts`] rules.
+
+use anyhow::{Context, Result};
+use polars::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tracing::info;
+
+use crate::obs::analyze::{SplittingFinding, REASON_COLUMN};
+use crate::obs::ingest::ContratoSecop;
+use crate::obs::normalize::parse_number;
+
+/// A single red-flag check
+pub trait RiskRule: Send + Sync {
+    /// Identifier matching the `[rules.<id>]` section of the configuration
+    fn id(&self) -> &str;
+
+    /// Evidence for the flag, or None when the contract passes
+    fn evaluate(&self, contract: &ContratoSecop) -> Option<String>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+/// Per-rule settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+    /// Short name shown in `red_flags`
+    pub label: String,
+    /// Sentence explaining why the rule matters
+    #[serde(default)]
+    pub explanation: String,
+    /// Rule-specific parameters, read with [`RiskEngine::params`]
+    #[serde(flatten)]
+    pub params: toml::Table,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+fn default_severity() -> Severity {
+    Severity::Medium
+}
+
+/// Minimum scores for each risk level
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskLevels {
+    pub medio: f64,
+    pub alto: f64,
+}
+
+impl Default for RiskLevels {
+    fn default() -> Self {
+        Self { medio: 1.0, alto: 2.0 }
+    }
+}
+
+impl RiskLevels {
+    pub fn classify(&self, score: f64) -> &'static str {
+        if score >= self.alto {
+            "Alto"
+        } else if score >= self.medio {
+            "Medio"
+        } else {
+            "Bajo"
+        }
+    }
+}
+
+/// Contents of the risk rules TOML file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskConfig {
+    #[serde(default)]
+    pub levels: RiskLevels,
+    #[serde(default)]
+    pub rules: BTreeMap<String, RuleConfig>,
+}
+
+/// Configuration shipped with the backend, used when no file is found
+const DEFAULT_CONFIG: &str = include_str!("../../risk_rules.toml");
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        toml::from_str(DEFAULT_CONFIG).expect("bundled risk_rules.toml is valid")
+    }
+}
+
+impl RiskConfig {
+    /// Load the configuration, falling back to the bundled defaults if the file is missing.
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(raw) => toml::from_str(&raw)
+                .with_context(|| format!("Invalid risk configuration {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+}
+
+/// A rule that fired on a contract
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FiredRule {
+    pub rule: String,
+    pub label: String,
+    pub severity: Severity,
+    pub weight: f64,
+    pub explanation: String,
+    pub evidence: String,
+}
+
+/// Outcome of scoring one contract
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskAssessment {
+    pub score: f64,
+    pub level: &'static str,
+    pub fired: Vec<FiredRule>,
+}
+
+/// Scores contracts against the enabled rules
+pub struct RiskEngine {
+    config: RiskConfig,
+    rules: Vec<Box<dyn RiskRule>>,
+}
+
+impl RiskEngine {
+    /// Engine with the built-in per-contract rules registered
+    pub fn new(config: RiskConfig) -> Self {
+        let mut engine = Self {
+            config,
+            rules: Vec::new(),
+        };
+        engine.register(Box::new(ZeroValue));
+        engine.register(Box::new(UndefinedObject));
+        engine.register(Box::new(MissingObject));
+        engine
+    }
+
+    pub fn is_enabled(&self, rule_id: &str) -> bool {
+        self.config.rules.get(rule_id).is_some_and(|r| r.enabled)
+    }
+
+    /// Add a rule; ignored unless it is configured and enabled.
+    pub fn register(&mut self, rule: Box<dyn RiskRule>) {
+        if self.is_enabled(rule.id()) {
+            self.rules.push(rule);
+        }
+    }
+
+    /// Rule-specific parameters deserialized from its configuration section
+    pub fn params<T: DeserializeOwned>(&self, rule_id: &str) -> Result<T> {
+        let params = self
+            .config
+            .rules
+            .get(rule_id)
+            .map(|r| r.params.clone())
+            .unwrap_or_default();
+        toml::Value::Table(params)
+            .try_into()
+            .with_context(|| format!("Invalid parameters for rule {}", rule_id))
+    }
+
+    pub fn assess(&self, contract: &ContratoSecop) -> RiskAssessment {
+        let fired: Vec<FiredRule> = self
+            .rules
+            .iter()
+            .filter_map(|rule| {
+                let evidence = rule.evaluate(contract)?;
+                let config = &self.config.rules[rule.id()];
+                Some(FiredRule {
+                    rule: rule.id().to_string(),
+                    label: config.label.clone(),
+                    severity: config.severity,
+                    weight: config.weight,
+                    explanation: config.explanation.clone(),
+                    evidence,
+                })
+            })
+            .collect();
+
+        let score = fired.iter().map(|f| f.weight).sum();
+        RiskAssessment {
+            score,
+            level: self.config.levels.classify(score),
+            fired,
+        }
+    }
+
+    fn empty_counts(&self) -> BTreeMap<String, usize> {
+        self.rules.iter().map(|r| (r.id().to_string(), 0)).collect()
+    }
+
+    /// How many times each rule fires over `contracts`, which are not modified
+    pub fn count(&self, contracts: impl IntoIterator<Item = ContratoSecop>) -> BTreeMap<String, usize> {
+        let mut counts = self.empty_counts();
+        for contract in contracts {
+            for fired in self.assess(&contract).fired {
+                *counts.entry(fired.rule).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Score every contract in place; returns how many times each rule fired.
+    pub fn apply(&self, contracts: &mut [ContratoSecop]) -> BTreeMap<String, usize> {
+        let mut counts = self.empty_counts();
+
+        for contract in contracts.iter_mut() {
+            let assessment = self.assess(contract);
+            for fired in &assessment.fired {
+                *counts.entry(fired.rule.clone()).or_default() += 1;
+            }
+            contract.risk_score = Some(assessment.score);
+            contract.risk_level = Some(assessment.level.to_string());
+            contract.red_flags = Some(assessment.fired.iter().map(|f| f.label.clone()).collect());
+            contract.risk_evidence = Some(assessment.fired);
+        }
+
+        info!("Risk engine: {} rules applied, hits {:?}", self.rules.len(), counts);
+        counts
+    }
+}
+
+/// `valor_del_contrato` is exactly zero
+pub struct ZeroValue;
+
+impl RiskRule for ZeroValue {
+    fn id(&self) -> &str {
+        "zero_value"
+    }
+
+    fn evaluate(&self, contract: &ContratoSecop) -> Option<String> {
+        let value = parse_number(contract.valor_del_contrato.as_deref()?)?;
+        (value == 0.0).then(|| "valor_del_contrato = 0".to_string())
+    }
+}
+
+/// `objeto_del_contrato` is a placeholder such as "No definido"
+pub struct UndefinedObject;
+
+impl RiskRule for UndefinedObject {
+    fn id(&self) -> &str {
+        "undefined_object"
+    }
+
+    fn evaluate(&self, contract: &ContratoSecop) -> Option<String> {
+        let obj = contract.objeto_del_contrato.as_deref()?;
+        let obj_lower = obj.to_lowercase();
+        (obj_lower == "no definido" || obj_lower.contains("objeto a contratar"))
+            .then(|| format!("objeto_del_contrato = \"{}\"", obj))
+    }
+}
+
+/// `objeto_del_contrato` is null or empty
+pub struct MissingObject;
+
+impl RiskRule for MissingObject {
+    fn id(&self) -> &str {
+        "missing_object"
+    }
+
+    fn evaluate(&self, contract: &ContratoSecop) -> Option<String> {
+        match contract.objeto_del_contrato.as_deref().map(str::trim) {
+            None => Some("objeto_del_contrato ausente".to_string()),
+            Some("") => Some("objeto_del_contrato vacío".to_string()),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Contracts flagged by a dataset-level analysis, keyed by `id_contrato`
+pub struct FlaggedContracts {
+    id: String,
+    evidence: HashMap<String, String>,
+}
+
+impl FlaggedContracts {
+    pub fn new(id: &str, evidence: HashMap<String, String>) -> Self {
+        Self {
+            id: id.to_string(),
+            evidence,
+        }
+    }
+
+    /// From the output of a detector that adds a `razon` column
+    pub fn from_frame(id: &str, flagged: &DataFrame) -> PolarsResult<Self> {
+        let ids = flagged.column("id_contrato")?.str()?;
+        let reasons = flagged.column(REASON_COLUMN)?.str()?;
+        let evidence = ids
+            .into_iter()
+            .zip(reasons)
+            .filter_map(|(id, reason)| Some((id?.to_string(), reason.unwrap_or_default().to_string())))
+            .collect();
+        Ok(Self::new(id, evidence))
+    }
+
+    /// From contract-splitting findings; every member contract is flagged
+    pub fn from_splitting(id: &str, findings: &[SplittingFinding]) -> Self {
+        let evidence = findings
+            .iter()
+            .flat_map(|f| {
+                let evidence = format!("{} ({} a {})", f.razon, f.desde, f.hasta);
+                f.id_contratos.iter().map(move |id| (id.clone(), evidence.clone()))
+            })
+            .collect();
+        Self::new(id, evidence)
+    }
+}
+
+impl RiskRule for FlaggedContracts {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn evaluate(&self, contract: &ContratoSecop) -> Option<String> {
+        self.evidence.get(contract.id_contrato.as_deref()?).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contrato(id: &str, valor: &str, objeto: Option<&str>) -> ContratoSecop {
+        serde_json::from_value(serde_json::json!({
+            "id_contrato": id,
+            "valor_del_contrato": valor,
+            "objeto_del_contrato": objeto,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_default_config_matches_previous_levels() {
+        let engine = RiskEngine::new(RiskConfig::default());
+
+        let clean = engine.assess(&contrato("CO1.1", "1000", Some("Suministro de papelería")));
+        assert_eq!((clean.score, clean.level), (0.0, "Bajo"));
+
+        let one = engine.assess(&contrato("CO1.2", "0", Some("Suministro de papelería")));
+        assert_eq!(one.level, "Medio");
+        assert_eq!(one.fired[0].rule, "zero_value");
+
+        let two = engine.assess(&contrato("CO1.3", "0", None));
+        assert_eq!(two.level, "Alto");
+        assert_eq!(two.fired[1].evidence, "objeto_del_contrato ausente");
+        // Labels the frontend already shows
+        assert_eq!(two.fired[0].label, "Valor Cero");
+        assert_eq!(two.fired[1].label, "Objeto Faltante");
+
+        let placeholder = engine.assess(&contrato("CO1.4", "1000", Some("NO DEFINIDO")));
+        assert_eq!(placeholder.fired.len(), 1);
+        assert_eq!(placeholder.fired[0].label, "Objeto Indefinido");
+
+        let empty = engine.assess(&contrato("CO1.5", "1000", Some("  ")));
+        assert_eq!(empty.fired[0].rule, "missing_object");
+        assert_eq!(empty.fired[0].label, "Objeto Faltante");
+    }
+
+    #[test]
+    fn test_config_weights_and_disabled_rules() {
+        let config: RiskConfig = toml::from_str(
+            r#"
+            [levels]
+            medio = 2.0
+            alto = 5.0
+
+            [rules.zero_value]
+            weight = 3.0
+            severity = "high"
+            label = "Valor Cero"
+
+            [rules.undefined_object]
+            enabled = false
+            label = "Objeto Indefinido"
+
+            [rules.single_bidder]
+            weight = 2.5
+            label = "Único Oferente"
+            "#,
+        )
+        .unwrap();
+
+        let mut engine = RiskEngine::new(config);
+        engine.register(Box::new(FlaggedContracts::new(
+            "single_bidder",
+            HashMap::from([("CO1.1".to_string(), "Licitación pública con un único oferente".to_string())]),
+        )));
+        // Not configured, so never registered
+        engine.register(Box::new(FlaggedContracts::new("short_timeline", HashMap::new())));
+
+        let mut contracts = vec![contrato("CO1.1", "0", None), contrato("CO1.2", "5", None)];
+        let counts = engine.apply(&mut contracts);
+
+        assert_eq!(contracts[0].risk_score, Some(5.5));
+        assert_eq!(contracts[0].risk_level.as_deref(), Some("Alto"));
+        assert_eq!(
+            contracts[0].red_flags.as_deref(),
+            Some(&["Valor Cero".to_string(), "Único Oferente".to_string()][..])
+        );
+        assert_eq!(contracts[1].risk_level.as_deref(), Some("Bajo"));
+        assert_eq!(counts.get("zero_value"), Some(&1));
+        assert!(!counts.contains_key("undefined_object"));
+        assert!(!counts.contains_key("short_timeline"));
+        assert_eq!(engine.count(contracts), counts);
+    }
+
+    #[test]
+    fn test_rule_params() {
+        let engine = RiskEngine::new(RiskConfig::default());
+        let thresholds: crate::obs::analyze::TimelineThresholds =
+            engine.params("short_timeline").unwrap();
+        assert_eq!(
+            thresholds.get(crate::obs::analyze::Modalidad::LicitacionPublica),
+            Some(10)
+        );
+    }
+}
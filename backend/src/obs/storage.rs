@@ -0,0 +1,833 @@
+//! Parquet snapshot store for ingested SECOP contracts.
+//!
+//! Contracts are written as a Hive-partitioned dataset keyed on the year and
+//! month of `fecha_de_firma` (`anio=2024/mes=3/data.parquet`), so analysis code
+//! can scan the full history lazily instead of reloading JSON. The dataset is
+//! the source of truth: an incremental run only rewrites the partitions its
+//! delta touches ([`merge_into_snapshot`]). SECOP II processes are stored
+//! alongside as a single file and can be joined onto the contracts with
+//! [`combined_view`].
+
+use chrono::{NaiveDate, NaiveDateTime};
+use mdm_core::batch;
+use polars::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::obs::ingest::{ContratoSecop, ProcesoSecop};
+use crate::obs::normalize::{normalize_nit, parse_number, ParseWarning, Parser};
+
+const PARTITION_FILE: &str = "data.parquet";
+/// Directory value Polars reads back as a null partition key
+const NULL_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// Parse a Socrata floating timestamp ("2024-03-01T00:00:00.000") or plain date
+pub fn parse_socrata_datetime(raw: &str) -> Option<NaiveDateTime> {
+    let raw = raw.trim().trim_end_matches('Z');
+    NaiveDateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S%.f"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+}
+
+fn days_since_epoch(raw: Option<&str>) -> Option<i32> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+    let date = parse_socrata_datetime(raw?)?.date();
+    Some((date - epoch).num_days() as i32)
+}
+
+fn millis_since_epoch(raw: Option<&str>) -> Option<i64> {
+    Some(parse_socrata_datetime(raw?)?.and_utc().timestamp_millis())
+}
+
+fn parse_count(raw: Option<&str>) -> Option<i64> {
+    raw?.trim().parse::<f64>().ok().map(|v| v as i64)
+}
+
+fn date_column(name: &str, values: Vec<Option<i32>>) -> PolarsResult<Column> {
+    Ok(Series::new(name.into(), values).cast(&DataType::Date)?.into())
+}
+
+fn text_column<'a>(name: &str, values: impl Iterator<Item = Option<&'a str>>) -> Column {
+    Column::new(name.into(), values.collect::<Vec<_>>())
+}
+
+/// NITs reduced to their digits so the same entity groups together
+fn nit_column<'a>(name: &str, values: impl Iterator<Item = Option<&'a str>>) -> Column {
+    Column::new(
+        name.into(),
+        values.map(|v| v.and_then(normalize_nit)).collect::<Vec<_>>(),
+    )
+}
+
+fn build_frame(rows: &[&ContratoSecop]) -> PolarsResult<DataFrame> {
+    let valor: Vec<Option<f64>> = rows
+        .iter()
+        .map(|c| {
+            c.valor_del_contrato.as_deref().and_then(parse_number)
+        })
+        .collect();
+
+    let fecha_firma: Vec<Option<i32>> = rows
+        .iter()
+        .map(|c| days_since_epoch(c.fecha_de_firma.as_deref()))
+        .collect();
+    let fecha_inicio: Vec<Option<i32>> = rows
+        .iter()
+        .map(|c| days_since_epoch(c.fecha_de_inicio_del_contrato.as_deref()))
+        .collect();
+    let updated_at: Vec<Option<i64>> = rows
+        .iter()
+        .map(|c| millis_since_epoch(c.updated_at.as_deref()))
+        .collect();
+
+    DataFrame::new(vec![
+        text_column("id_contrato", rows.iter().map(|c| c.id_contrato.as_deref())),
+        text_column("nombre_entidad", rows.iter().map(|c| c.nombre_entidad.as_deref())),
+        nit_column("nit_entidad", rows.iter().map(|c| c.nit_entidad.as_deref())),
+        text_column("departamento", rows.iter().map(|c| c.departamento.as_deref())),
+        text_column("ciudad", rows.iter().map(|c| c.ciudad.as_deref())),
+        text_column(
+            "objeto_del_contrato",
+            rows.iter().map(|c| c.objeto_del_contrato.as_deref()),
+        ),
+        text_column("tipo_de_contrato", rows.iter().map(|c| c.tipo_de_contrato.as_deref())),
+        text_column(
+            "modalidad_de_contratacion",
+            rows.iter().map(|c| c.modalidad_de_contratacion.as_deref()),
+        ),
+        Column::new("valor_del_contrato".into(), valor),
+        text_column(
+            "nombre_contratista",
+            rows.iter().map(|c| c.nombre_contratista.as_deref()),
+        ),
+        nit_column("nit_contratista", rows.iter().map(|c| c.nit_contratista.as_deref())),
+        date_column("fecha_de_firma", fecha_firma)?,
+        date_column("fecha_de_inicio_del_contrato", fecha_inicio)?,
+        text_column("duracion", rows.iter().map(|c| c.duracion.as_deref())),
+        text_column(
+            "proceso_de_compra",
+            rows.iter().map(|c| c.proceso_de_compra.as_deref()),
+        ),
+        Series::new("updated_at".into(), updated_at)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+            .into(),
+    ])
+}
+
+/// Convert contracts into a typed DataFrame (values as f64, dates as `Date`/`Datetime`)
+pub fn contratos_to_dataframe(contratos: &[ContratoSecop]) -> PolarsResult<DataFrame> {
+    let rows: Vec<&ContratoSecop> = contratos.iter().collect();
+    build_frame(&rows)
+}
+
+/// Rows of a contracts frame with only the fields the per-contract risk rules
+/// read (`id_contrato`, `valor_del_contrato`, `objeto_del_contrato`), built
+/// one at a time so the whole snapshot can be scored without copying it
+pub fn risk_view(df: &DataFrame) -> PolarsResult<impl Iterator<Item = ContratoSecop> + '_> {
+    let ids = df.column("id_contrato")?.str()?;
+    let valores = df.column("valor_del_contrato")?.f64()?;
+    let objetos = df.column("objeto_del_contrato")?.str()?;
+    Ok(ids.into_iter().zip(valores).zip(objetos).map(|((id, valor), objeto)| ContratoSecop {
+        id_contrato: id.map(str::to_string),
+        valor_del_contrato: valor.map(|v| v.to_string()),
+        objeto_del_contrato: objeto.map(str::to_string),
+        ..Default::default()
+    }))
+}
+
+/// Copy the contractor resolution of `resolved` (a snapshot frame returned by
+/// `EntityResolver::resolve_frame`) onto the contracts with the same `id_contrato`
+pub fn attach_resolution(contratos: &mut [ContratoSecop], resolved: &DataFrame) -> PolarsResult<()> {
+    let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, contrato) in contratos.iter().enumerate() {
+        if let Some(id) = &contrato.id_contrato {
+            positions.entry(id.clone()).or_default().push(i);
+        }
+    }
+
+    let ids = resolved.column("id_contrato")?.str()?;
+    let uuids = resolved.column(batch::UUID_COLUMN)?.str()?;
+    let match_types = resolved.column(batch::MATCH_TYPE_COLUMN)?.str()?;
+    let confidences = resolved.column(batch::CONFIDENCE_COLUMN)?.f64()?;
+    for row in 0..resolved.height() {
+        let Some(rows) = ids.get(row).and_then(|id| positions.get(id)) else {
+            continue;
+        };
+        for &i in rows {
+            contratos[i].contractor_uuid = uuids.get(row).map(str::to_string);
+            contratos[i].match_type = match_types.get(row).map(str::to_string);
+            contratos[i].match_confidence = confidences.get(row);
+        }
+    }
+    Ok(())
+}
+
+/// One record per key, the last one winning; records without a key are all kept
+fn dedup_by_key<T>(records: &[T], key: impl Fn(&T) -> Option<&str>) -> Vec<&T> {
+    let mut deduped: Vec<&T> = Vec::with_capacity(records.len());
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for record in records {
+        match key(record) {
+            Some(id) => match positions.get(id) {
+                Some(&pos) => deduped[pos] = record,
+                None => {
+                    positions.insert(id, deduped.len());
+                    deduped.push(record);
+                }
+            },
+            None => deduped.push(record),
+        }
+    }
+    deduped
+}
+
+/// The contracts the snapshot can hold, one per `id_contrato`, the last one
+/// winning. Contracts without an ID could never be replaced by a later delta,
+/// so they are left out (the quality report counts them)
+fn storable(contratos: &[ContratoSecop]) -> Vec<&ContratoSecop> {
+    let deduped = dedup_by_key(contratos, |c| c.id_contrato.as_deref());
+    let total = deduped.len();
+    let storable: Vec<&ContratoSecop> = deduped.into_iter().filter(|c| has_id(c.id_contrato.as_deref())).collect();
+    if storable.len() < total {
+        warn!(
+            "{} contratos sin id_contrato no se guardan en el snapshot",
+            total - storable.len()
+        );
+    }
+    storable
+}
+
+fn has_id(id: Option<&str>) -> bool {
+    id.is_some_and(|id| !id.trim().is_empty())
+}
+
+/// Rows of `df` whose `column` is not in `keys` (null keys are kept)
+fn without_keys(df: &DataFrame, column: &str, keys: &HashSet<&str>) -> PolarsResult<DataFrame> {
+    let mask: BooleanChunked = df
+        .column(column)?
+        .str()?
+        .into_iter()
+        .map(|key| Some(key.is_none_or(|k| !keys.contains(k))))
+        .collect();
+    df.filter(&mask)
+}
+
+fn partition_key(contrato: &ContratoSecop) -> Option<(i32, u32)> {
+    use chrono::Datelike;
+    let date = parse_socrata_datetime(contrato.fecha_de_firma.as_deref()?)?;
+    Some((date.year(), date.month()))
+}
+
+fn partition_dir(key: Option<(i32, u32)>) -> String {
+    match key {
+        Some((year, month)) => format!("anio={}/mes={}", year, month),
+        None => format!("anio={}/mes={}", NULL_PARTITION, NULL_PARTITION),
+    }
+}
+
+fn fresh_staging_dir(root: &Path) -> PolarsResult<PathBuf> {
+    let staging = root.with_extension("staging");
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::create_dir_all(&staging)?;
+    Ok(staging)
+}
+
+/// Write `df` as `dir/data.parquet`, through a temp file so readers never see
+/// a partial file
+fn write_parquet(df: &mut DataFrame, dir: &Path) -> PolarsResult<()> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(PARTITION_FILE);
+    let tmp = path.with_extension("parquet.tmp");
+    let file = std::fs::File::create(&tmp)?;
+    ParquetWriter::new(file)
+        .with_compression(ParquetCompression::Zstd(None))
+        .finish(df)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+fn read_parquet(path: &Path) -> PolarsResult<DataFrame> {
+    ParquetReader::new(std::fs::File::open(path)?).finish()
+}
+
+/// Partition directories under `root`, relative to it (`anio=2024/mes=3`)
+fn partition_dirs(root: &Path) -> PolarsResult<Vec<String>> {
+    let mut dirs = Vec::new();
+    if !root.exists() {
+        return Ok(dirs);
+    }
+    for year in std::fs::read_dir(root)? {
+        let year = year?;
+        if !year.file_type()?.is_dir() {
+            continue;
+        }
+        for month in std::fs::read_dir(year.path())? {
+            let month = month?;
+            if month.path().join(PARTITION_FILE).exists() {
+                dirs.push(format!(
+                    "{}/{}",
+                    year.file_name().to_string_lossy(),
+                    month.file_name().to_string_lossy()
+                ));
+            }
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Replace `root` with the fully written `staging` directory
+fn swap_into_place(staging: &Path, root: &Path) -> PolarsResult<()> {
+    let previous = root.with_extension("old");
+    if previous.exists() {
+        std::fs::remove_dir_all(&previous)?;
+    }
+    if root.exists() {
+        std::fs::rename(root, &previous)?;
+    }
+    std::fs::rename(staging, root)?;
+    if previous.exists() {
+        std::fs::remove_dir_all(&previous)?;
+    }
+    Ok(())
+}
+
+/// Write the full snapshot under `root`, one Parquet file per year/month of
+/// `fecha_de_firma`. Contracts without a parseable date go to a null partition.
+///
+/// The dataset is built in a staging directory and swapped in at the end, so a
+/// failed write leaves the previous snapshot intact. Returns the number of
+/// partitions written.
+/// A contract listed more than once is written once, as its last occurrence;
+/// contracts without `id_contrato` are skipped.
+pub fn write_snapshot(contratos: &[ContratoSecop], root: &Path) -> PolarsResult<usize> {
+    let mut partitions: BTreeMap<Option<(i32, u32)>, Vec<&ContratoSecop>> = BTreeMap::new();
+    for contrato in storable(contratos) {
+        partitions
+            .entry(partition_key(contrato))
+            .or_default()
+            .push(contrato);
+    }
+
+    let staging = fresh_staging_dir(root)?;
+    for (key, rows) in &partitions {
+        let mut df = build_frame(rows)?;
+        write_parquet(&mut df, &staging.join(partition_dir(*key)))?;
+    }
+
+    swap_into_place(&staging, root)?;
+
+    info!(
+        "Snapshot Parquet escrito en {}: {} contratos, {} particiones",
+        root.display(),
+        contratos.len(),
+        partitions.len()
+    );
+    Ok(partitions.len())
+}
+
+/// Merge `delta` into the snapshot under `root`, replacing contracts with the
+/// same `id_contrato`, and return the number of contracts stored afterwards.
+/// Contracts without `id_contrato` are skipped, and partitions still holding
+/// ones stored by earlier versions are rewritten without them.
+///
+/// Only the partitions the delta touches are rewritten: the ones its contracts
+/// fall into, and the ones still holding an older copy of a contract whose
+/// `fecha_de_firma` moved to another month. Each partition file is replaced
+/// atomically; a run interrupted between partitions is repaired by merging the
+/// same delta again, which is what happens since the sync cursor has not moved.
+pub fn merge_into_snapshot(delta: &[ContratoSecop], root: &Path) -> PolarsResult<usize> {
+    let delta = storable(delta);
+    let ids: HashSet<&str> = delta.iter().filter_map(|c| c.id_contrato.as_deref()).collect();
+
+    let mut incoming: BTreeMap<String, Vec<&ContratoSecop>> = BTreeMap::new();
+    for contrato in &delta {
+        incoming
+            .entry(partition_dir(partition_key(contrato)))
+            .or_default()
+            .push(contrato);
+    }
+
+    // Partitions holding a contract of the delta, or a row without ID to drop;
+    // only the ID column is read
+    let mut touched: BTreeSet<String> = incoming.keys().cloned().collect();
+    for dir in partition_dirs(root)? {
+        if touched.contains(&dir) {
+            continue;
+        }
+        let file = std::fs::File::open(root.join(&dir).join(PARTITION_FILE))?;
+        let stored = ParquetReader::new(file)
+            .with_columns(Some(vec!["id_contrato".to_string()]))
+            .finish()?;
+        let hit = stored
+            .column("id_contrato")?
+            .str()?
+            .into_iter()
+            .any(|id| !has_id(id) || id.is_some_and(|id| ids.contains(id)));
+        if hit {
+            touched.insert(dir);
+        }
+    }
+
+    for dir in &touched {
+        let path = root.join(dir);
+        let mut df = if path.join(PARTITION_FILE).exists() {
+            let stored = without_keys(&read_parquet(&path.join(PARTITION_FILE))?, "id_contrato", &ids)?;
+            let keyed: BooleanChunked = stored.column("id_contrato")?.str()?.into_iter().map(|id| Some(has_id(id))).collect();
+            stored.filter(&keyed)?
+        } else {
+            build_frame(&[])?
+        };
+        if let Some(rows) = incoming.get(dir) {
+            df.vstack_mut(&build_frame(rows)?)?;
+        }
+
+        if df.height() == 0 {
+            std::fs::remove_dir_all(&path)?;
+        } else {
+            df.align_chunks_par();
+            write_parquet(&mut df, &path)?;
+        }
+    }
+
+    let total = snapshot_len(root)?;
+    info!(
+        "Snapshot Parquet actualizado en {}: {} contratos del delta, {} particiones reescritas, {} contratos en total",
+        root.display(),
+        delta.len(),
+        touched.len(),
+        total
+    );
+    Ok(total)
+}
+
+/// Contracts stored under `root` (0 when there is no snapshot yet)
+pub fn snapshot_len(root: &Path) -> PolarsResult<usize> {
+    if partition_dirs(root)?.is_empty() {
+        return Ok(0);
+    }
+    let counts = scan_snapshot(root)?.select([len().alias("n")]).collect()?;
+    Ok(counts.column("n")?.u32()?.get(0).unwrap_or(0) as usize)
+}
+
+/// Lazily scan every partition of the snapshot. The Hive keys `anio` and
+/// `mes` are exposed as columns, so filters on them prune whole files.
+pub fn scan_snapshot(root: &Path) -> PolarsResult<LazyFrame> {
+    LazyFrame::scan_parquet(
+        root.join("**").join("*.parquet"),
+        ScanArgsParquet::default(),
+    )
+}
+
+/// Read the whole snapshot into memory. A missing or empty snapshot gives an
+/// empty frame with the contract columns.
+pub fn read_snapshot(root: &Path) -> PolarsResult<DataFrame> {
+    if partition_dirs(root)?.is_empty() {
+        return contratos_to_dataframe(&[]);
+    }
+    scan_snapshot(root)?.collect()
+}
+
+/// Convert processes into a typed DataFrame (counts as i64, prices as f64, dates as `Date`),
+/// with a warning for every `precio_base` that could not be parsed
+pub fn procesos_to_dataframe(procesos: &[ProcesoSecop]) -> PolarsResult<(DataFrame, Vec<ParseWarning>)> {
+    let rows: Vec<&ProcesoSecop> = procesos.iter().collect();
+    procesos_frame(&rows)
+}
+
+fn procesos_frame(procesos: &[&ProcesoSecop]) -> PolarsResult<(DataFrame, Vec<ParseWarning>)> {
+    let mut parser = Parser::default();
+    let publicacion: Vec<Option<i32>> = procesos
+        .iter()
+        .map(|p| days_since_epoch(p.fecha_de_publicacion.as_deref()))
+        .collect();
+    let cierre: Vec<Option<i32>> = procesos
+        .iter()
+        .map(|p| days_since_epoch(p.fecha_de_cierre.as_deref()))
+        .collect();
+    let oferentes: Vec<Option<i64>> = procesos
+        .iter()
+        .map(|p| parse_count(p.numero_de_oferentes.as_deref()))
+        .collect();
+    let ofertas: Vec<Option<i64>> = procesos
+        .iter()
+        .map(|p| parse_count(p.numero_de_ofertas.as_deref()))
+        .collect();
+    let precio: Vec<Option<f64>> = procesos
+        .iter()
+        .map(|p| parser.parse("precio_base", p.precio_base.as_deref(), parse_number, "valor no numérico"))
+        .collect();
+
+    let df = DataFrame::new(vec![
+        text_column("id_del_proceso", procesos.iter().map(|p| p.id_del_proceso.as_deref())),
+        text_column(
+            "id_del_portafolio",
+            procesos.iter().map(|p| p.id_del_portafolio.as_deref()),
+        ),
+        text_column(
+            "referencia_del_proceso",
+            procesos.iter().map(|p| p.referencia_del_proceso.as_deref()),
+        ),
+        text_column("entidad", procesos.iter().map(|p| p.entidad.as_deref())),
+        text_column("nit_entidad", procesos.iter().map(|p| p.nit_entidad.as_deref())),
+        text_column(
+            "modalidad_de_contratacion",
+            procesos.iter().map(|p| p.modalidad_de_contratacion.as_deref()),
+        ),
+        text_column(
+            "estado_del_procedimiento",
+            procesos.iter().map(|p| p.estado_del_procedimiento.as_deref()),
+        ),
+        date_column("fecha_de_publicacion", publicacion)?,
+        date_column("fecha_de_cierre", cierre)?,
+        Column::new("numero_de_oferentes".into(), oferentes),
+        Column::new("numero_de_ofertas".into(), ofertas),
+        Column::new("precio_base".into(), precio),
+    ])?;
+    Ok((df, parser.warnings))
+}
+
+fn log_warnings(what: &str, warnings: &[ParseWarning]) {
+    if let Some(example) = warnings.first() {
+        warn!(
+            "{} {} con valores no interpretables (p. ej. {}={:?})",
+            warnings.len(),
+            what,
+            example.field,
+            example.value
+        );
+    }
+}
+
+/// Write the processes snapshot as a single Parquet file under `root`
+pub fn write_procesos(procesos: &[ProcesoSecop], root: &Path) -> PolarsResult<()> {
+    let (mut df, warnings) = procesos_to_dataframe(procesos)?;
+    log_warnings("procesos", &warnings);
+    let staging = fresh_staging_dir(root)?;
+    write_parquet(&mut df, &staging)?;
+    swap_into_place(&staging, root)?;
+
+    info!(
+        "Procesos Parquet escritos en {}: {} procesos",
+        root.display(),
+        procesos.len()
+    );
+    Ok(())
+}
+
+/// Merge `procesos` into the processes snapshot under `root`, replacing
+/// processes with the same `id_del_portafolio` (or `id_del_proceso` when it
+/// has none). Returns the number of processes stored afterwards.
+pub fn merge_procesos(procesos: &[ProcesoSecop], root: &Path) -> PolarsResult<usize> {
+    let procesos = dedup_by_key(procesos, |p| {
+        p.id_del_portafolio.as_deref().or(p.id_del_proceso.as_deref())
+    });
+    let path = root.join(PARTITION_FILE);
+
+    let (mut df, warnings) = procesos_frame(&procesos)?;
+    log_warnings("procesos", &warnings);
+    if path.exists() {
+        let portafolios: HashSet<&str> = procesos.iter().filter_map(|p| p.id_del_portafolio.as_deref()).collect();
+        let procesos_ids: HashSet<&str> = procesos
+            .iter()
+            .filter(|p| p.id_del_portafolio.is_none())
+            .filter_map(|p| p.id_del_proceso.as_deref())
+            .collect();
+        let stored = read_parquet(&path)?;
+        let stored = without_keys(&stored, "id_del_portafolio", &portafolios)?;
+        let mut stored = without_keys(&stored, "id_del_proceso", &procesos_ids)?;
+        stored.vstack_mut(&df)?;
+        df = stored;
+    }
+    df.align_chunks_par();
+    write_parquet(&mut df, root)?;
+
+    info!(
+        "Procesos Parquet actualizados en {}: {} recibidos, {} en total",
+        root.display(),
+        procesos.len(),
+        df.height()
+    );
+    Ok(df.height())
+}
+
+pub fn scan_procesos(root: &Path) -> PolarsResult<LazyFrame> {
+    LazyFrame::scan_parquet(root.join(PARTITION_FILE), ScanArgsParquet::default())
+}
+
+/// Process columns added to each contract by [`combined_view`]
+const PROCESO_COLUMNS: [&str; 7] = [
+    "id_del_portafolio",
+    "referencia_del_proceso",
+    "estado_del_procedimiento",
+    "fecha_de_publicacion",
+    "fecha_de_cierre",
+    "numero_de_oferentes",
+    "numero_de_ofertas",
+];
+
+/// Left-join processes onto contracts via `proceso_de_compra = id_del_portafolio`.
+///
+/// Contract columns are kept as-is; the process modality is exposed as
+/// `modalidad_del_proceso` and its estimated price as `precio_base`.
+pub fn combined_view(contratos: LazyFrame, procesos: LazyFrame) -> LazyFrame {
+    let mut proceso_cols: Vec<Expr> = PROCESO_COLUMNS.iter().map(|c| col(*c)).collect();
+    proceso_cols.push(col("modalidad_de_contratacion").alias("modalidad_del_proceso"));
+    proceso_cols.push(col("precio_base"));
+
+    contratos.left_join(
+        procesos.select(proceso_cols),
+        col("proceso_de_compra"),
+        col("id_del_portafolio"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contrato(id: &str, fecha: Option<&str>, valor: &str) -> ContratoSecop {
+        serde_json::from_value(serde_json::json!({
+            "id_contrato": id,
+            "nit_entidad": "890000001",
+            "fecha_de_firma": fecha,
+            "valor_del_contrato": valor,
+            ":updated_at": "2024-03-05T08:00:00.000Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_socrata_datetime() {
+        assert!(parse_socrata_datetime("2024-03-01T00:00:00.000").is_some());
+        assert!(parse_socrata_datetime("2024-03-01T10:20:30").is_some());
+        assert!(parse_socrata_datetime("2024-03-01").is_some());
+        assert!(parse_socrata_datetime("no definida").is_none());
+    }
+
+    #[test]
+    fn test_dataframe_is_typed() {
+        let df = contratos_to_dataframe(&[
+            contrato("CO1.1", Some("2024-03-01T00:00:00.000"), "1500000"),
+            contrato("CO1.2", None, "no aplica"),
+        ])
+        .unwrap();
+
+        assert_eq!(df.height(), 2);
+        assert_eq!(df.column("valor_del_contrato").unwrap().dtype(), &DataType::Float64);
+        assert_eq!(df.column("fecha_de_firma").unwrap().dtype(), &DataType::Date);
+        assert_eq!(
+            df.column("updated_at").unwrap().dtype(),
+            &DataType::Datetime(TimeUnit::Milliseconds, None)
+        );
+        assert_eq!(df.column("valor_del_contrato").unwrap().null_count(), 1);
+    }
+
+    #[test]
+    fn test_combined_view_joins_processes() {
+        let mut with_process = contrato("CO1.1", Some("2024-03-01T00:00:00.000"), "100");
+        with_process.proceso_de_compra = Some("CO1.BDOS.7".to_string());
+        let without_process = contrato("CO1.2", Some("2024-03-02T00:00:00.000"), "200");
+        let contratos = contratos_to_dataframe(&[with_process, without_process]).unwrap();
+
+        let proceso: ProcesoSecop = serde_json::from_value(serde_json::json!({
+            "id_del_portafolio": "CO1.BDOS.7",
+            "modalidad_de_contratacion": "Licitación pública",
+            "fecha_de_publicacion_del": "2024-02-01T00:00:00.000",
+            "fecha_de_recepcion_de": "2024-02-05T00:00:00.000",
+            "proveedores_unicos_con": "1",
+        }))
+        .unwrap();
+        let (procesos, _) = procesos_to_dataframe(&[proceso]).unwrap();
+
+        let df = combined_view(contratos.lazy(), procesos.lazy())
+            .sort(["id_contrato"], Default::default())
+            .collect()
+            .unwrap();
+
+        assert_eq!(df.height(), 2);
+        let oferentes = df.column("numero_de_oferentes").unwrap().i64().unwrap();
+        assert_eq!(oferentes.get(0), Some(1));
+        assert_eq!(oferentes.get(1), None);
+        assert_eq!(
+            df.column("modalidad_del_proceso").unwrap().str().unwrap().get(0),
+            Some("Licitación pública")
+        );
+    }
+
+    #[test]
+    fn test_precio_base_is_parsed_with_warnings() {
+        let procesos: Vec<ProcesoSecop> = ["123.456", "no aplica"]
+            .iter()
+            .map(|precio| serde_json::from_value(serde_json::json!({ "precio_base": precio })).unwrap())
+            .collect();
+
+        let (df, warnings) = procesos_to_dataframe(&procesos).unwrap();
+        let precios: Vec<_> = df.column("precio_base").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(precios, [Some(123.456), None]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!((warnings[0].field, warnings[0].value.as_str()), ("precio_base", "no aplica"));
+    }
+
+    #[test]
+    fn test_partitioned_roundtrip() {
+        let root = std::env::temp_dir().join(format!("snapshot_{}", uuid::Uuid::new_v4()));
+        let contratos = vec![
+            contrato("CO1.1", Some("2024-03-01T00:00:00.000"), "100"),
+            contrato("CO1.2", Some("2024-03-15T00:00:00.000"), "200"),
+            contrato("CO1.3", Some("2023-12-31T00:00:00.000"), "300"),
+            contrato("CO1.4", None, "400"),
+        ];
+
+        let partitions = write_snapshot(&contratos, &root).unwrap();
+        assert_eq!(partitions, 3);
+        assert!(root.join("anio=2024/mes=3").join(PARTITION_FILE).exists());
+
+        // Rewriting replaces the previous snapshot instead of accumulating files
+        write_snapshot(&contratos[..2], &root).unwrap();
+
+        let df = read_snapshot(&root).unwrap();
+        assert_eq!(df.height(), 2);
+
+        write_snapshot(&contratos, &root).unwrap();
+        let march = scan_snapshot(&root)
+            .unwrap()
+            .filter(col("anio").eq(lit(2024)).and(col("mes").eq(lit(3))))
+            .collect()
+            .unwrap();
+        assert_eq!(march.height(), 2);
+
+        std::fs::remove_dir_all(&root).ok();
+        assert_eq!(read_snapshot(&root).unwrap().height(), 0);
+    }
+
+    #[test]
+    fn test_merge_rewrites_only_touched_partitions() {
+        let root = std::env::temp_dir().join(format!("snapshot_{}", uuid::Uuid::new_v4()));
+        write_snapshot(
+            &[
+                contrato("CO1.1", Some("2024-03-01T00:00:00.000"), "100"),
+                contrato("CO1.2", Some("2024-02-15T00:00:00.000"), "200"),
+                contrato("CO1.3", Some("2023-12-31T00:00:00.000"), "300"),
+            ],
+            &root,
+        )
+        .unwrap();
+        let untouched = root.join("anio=2023/mes=12").join(PARTITION_FILE);
+        let before = std::fs::metadata(&untouched).unwrap().modified().unwrap();
+
+        // CO1.1 changes value, CO1.2 moves from February to April, CO1.4 is new
+        let delta = [
+            contrato("CO1.1", Some("2024-03-01T00:00:00.000"), "150"),
+            contrato("CO1.2", Some("2024-04-02T00:00:00.000"), "200"),
+            contrato("CO1.4", Some("2024-03-20T00:00:00.000"), "400"),
+        ];
+        assert_eq!(merge_into_snapshot(&delta, &root).unwrap(), 4);
+        // Merging the same delta again changes nothing
+        assert_eq!(merge_into_snapshot(&delta, &root).unwrap(), 4);
+
+        assert_eq!(std::fs::metadata(&untouched).unwrap().modified().unwrap(), before);
+        assert!(!root.join("anio=2024/mes=2").exists());
+
+        let df = read_snapshot(&root)
+            .unwrap()
+            .sort(["id_contrato"], Default::default())
+            .unwrap();
+        let valores: Vec<_> = df.column("valor_del_contrato").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(valores, [Some(150.0), Some(200.0), Some(300.0), Some(400.0)]);
+        let meses: Vec<_> = df.column("mes").unwrap().cast(&DataType::Int64).unwrap().i64().unwrap().into_iter().collect();
+        assert_eq!(meses, [Some(3), Some(4), Some(12), Some(3)]);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_contracts_without_id_are_not_stored() {
+        let root = std::env::temp_dir().join(format!("snapshot_{}", uuid::Uuid::new_v4()));
+        let mut sin_id = contrato("", Some("2024-03-10T00:00:00.000"), "500");
+        sin_id.id_contrato = None;
+
+        let stored = contrato("CO1.1", Some("2024-03-01T00:00:00.000"), "100");
+        write_snapshot(&[stored.clone(), sin_id.clone()], &root).unwrap();
+        assert_eq!(snapshot_len(&root).unwrap(), 1);
+
+        // A partition written before they were skipped
+        write_parquet(&mut build_frame(&[&stored, &sin_id]).unwrap(), &root.join("anio=2024/mes=3")).unwrap();
+        assert_eq!(snapshot_len(&root).unwrap(), 2);
+
+        // Every run fetches the same row again; it must not pile up
+        let delta = [sin_id, contrato("", Some("2024-03-11T00:00:00.000"), "600")];
+        assert_eq!(merge_into_snapshot(&delta, &root).unwrap(), 1);
+        assert_eq!(merge_into_snapshot(&delta, &root).unwrap(), 1);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_attach_resolution() {
+        let mut contratos: Vec<ContratoSecop> = [
+            ("CO1.1", "800.197.268-4", "Constructora Nacional S.A.S."),
+            ("CO1.2", "800197268", "CONSTRUCTORA NACIONAL"),
+            ("CO1.3", "79123456", "Juan Perez"),
+        ]
+        .iter()
+        .map(|(id, nit, nombre)| {
+            serde_json::from_value(serde_json::json!({
+                "id_contrato": id,
+                "nit_contratista": nit,
+                "nombre_contratista": nombre,
+            }))
+            .unwrap()
+        })
+        .collect();
+
+        let mut resolver = mdm_core::resolver::EntityResolver::new();
+        let resolved = resolver
+            .resolve_frame(&contratos_to_dataframe(&contratos).unwrap(), &batch::BatchColumns::default())
+            .unwrap();
+        // Only the delta is annotated
+        attach_resolution(&mut contratos[1..], &resolved).unwrap();
+
+        assert_eq!(contratos[0].contractor_uuid, None);
+        assert!(contratos[1].contractor_uuid.is_some());
+        assert_eq!(contratos[1].match_type.as_deref(), Some("exact"));
+        assert_eq!(contratos[1].match_confidence, Some(1.0));
+        assert_ne!(contratos[1].contractor_uuid, contratos[2].contractor_uuid);
+        assert_eq!(contratos[2].match_type.as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn test_merge_procesos_replaces_by_portafolio() {
+        let root = std::env::temp_dir().join(format!("procesos_{}", uuid::Uuid::new_v4()));
+        let proceso = |portafolio: &str, oferentes: &str| -> ProcesoSecop {
+            serde_json::from_value(serde_json::json!({
+                "id_del_proceso": format!("P-{}", portafolio),
+                "id_del_portafolio": portafolio,
+                "proveedores_unicos_con": oferentes,
+            }))
+            .unwrap()
+        };
+
+        write_procesos(&[proceso("CO1.BDOS.1", "1"), proceso("CO1.BDOS.2", "2")], &root).unwrap();
+        let total = merge_procesos(&[proceso("CO1.BDOS.2", "5"), proceso("CO1.BDOS.3", "3")], &root).unwrap();
+        assert_eq!(total, 3);
+
+        let df = scan_procesos(&root)
+            .unwrap()
+            .sort(["id_del_portafolio"], Default::default())
+            .collect()
+            .unwrap();
+        let oferentes: Vec<_> = df.column("numero_de_oferentes").unwrap().i64().unwrap().into_iter().collect();
+        assert_eq!(oferentes, [Some(1), Some(5), Some(3)]);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,165 @@
+//! Persisted sync cursors for incremental (delta) ingestion.
+//!
+//! The state file records, per dataset, the highest `fecha_de_firma` and
+//! `(:updated_at, :id)` pair seen in the stored snapshot. It is only rewritten
+//! after the snapshot itself has been saved, so a crashed run never advances
+//! the cursor.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Cursor for a single Socrata dataset
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatasetCursor {
+    /// Highest `fecha_de_firma` present in the snapshot
+    pub last_sync_date: Option<String>,
+    /// Highest Socrata `:updated_at` present in the snapshot
+    pub last_updated_at: Option<String>,
+    /// Highest Socrata `:id` among the rows updated at `last_updated_at`
+    #[serde(default)]
+    pub last_id: Option<String>,
+    /// When the cursor was last advanced
+    pub last_run: Option<DateTime<Utc>>,
+    /// Rows in the snapshot after the last successful run
+    pub total_records: usize,
+    /// Socrata `rowsUpdatedAt` (epoch seconds) of the dataset at the last run
+    pub rows_updated_at: Option<u64>,
+}
+
+impl DatasetCursor {
+    /// Raise the cursor to cover the given values (ISO-8601 strings compare lexicographically).
+    /// `:updated_at` and `:id` advance together as one `(updated_at, id)` pair.
+    pub fn observe(&mut self, fecha_de_firma: Option<&str>, updated_at: Option<&str>, row_id: Option<&str>) {
+        advance(&mut self.last_sync_date, fecha_de_firma);
+        let Some(updated_at) = updated_at.filter(|v| !v.is_empty()) else {
+            return;
+        };
+        match self.last_updated_at.as_deref() {
+            Some(current) if updated_at < current => {}
+            Some(current) if updated_at == current => advance(&mut self.last_id, row_id),
+            _ => {
+                self.last_updated_at = Some(updated_at.to_string());
+                self.last_id = row_id.filter(|v| !v.is_empty()).map(str::to_string);
+            }
+        }
+    }
+
+    /// Whether the dataset may have changed since this cursor was written.
+    /// Unknown timestamps on either side count as changed.
+    pub fn is_stale(&self, rows_updated_at: Option<u64>) -> bool {
+        match (self.rows_updated_at, rows_updated_at) {
+            (Some(synced), Some(current)) => current > synced,
+            _ => true,
+        }
+    }
+}
+
+fn advance(current: &mut Option<String>, candidate: Option<&str>) {
+    if let Some(value) = candidate.filter(|v| !v.is_empty()) {
+        if current.as_deref().is_none_or(|c| value > c) {
+            *current = Some(value.to_string());
+        }
+    }
+}
+
+/// Sync cursors for every ingested dataset, keyed by 4x4 dataset ID
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncState {
+    pub datasets: HashMap<String, DatasetCursor>,
+}
+
+impl SyncState {
+    /// Load the state file, or start empty if it does not exist yet.
+    pub async fn load(path: &Path) -> Result<Self> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(raw) => serde_json::from_str(&raw)
+                .with_context(|| format!("Invalid sync state file {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Atomically persist the state (write to a temp file, then rename).
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        write_atomic(path, serde_json::to_string_pretty(self)?.as_bytes()).await
+    }
+
+    pub fn cursor(&self, dataset_id: &str) -> Option<&DatasetCursor> {
+        self.datasets.get(dataset_id)
+    }
+
+    pub fn set_cursor(&mut self, dataset_id: &str, cursor: DatasetCursor) {
+        self.datasets.insert(dataset_id.to_string(), cursor);
+    }
+}
+
+/// Write `contents` to `path` via a sibling temp file so readers never see a partial file.
+pub async fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let tmp = path.with_extension("tmp");
+    tokio::fs::write(&tmp, contents)
+        .await
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .with_context(|| format!("Failed to move {} into place", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_only_moves_forward() {
+        let mut cursor = DatasetCursor::default();
+        cursor.observe(Some("2024-03-01T00:00:00.000"), Some("2024-03-02T10:00:00.000Z"), Some("row-b"));
+        cursor.observe(Some("2024-01-15T00:00:00.000"), None, Some("row-z"));
+        cursor.observe(Some(""), Some("2024-03-05T08:00:00.000Z"), Some("row-c"));
+        cursor.observe(None, Some("2024-03-05T08:00:00.000Z"), Some("row-a"));
+        cursor.observe(None, Some("2024-03-04T08:00:00.000Z"), Some("row-y"));
+
+        assert_eq!(cursor.last_sync_date.as_deref(), Some("2024-03-01T00:00:00.000"));
+        assert_eq!(cursor.last_updated_at.as_deref(), Some("2024-03-05T08:00:00.000Z"));
+        assert_eq!(cursor.last_id.as_deref(), Some("row-c"));
+
+        cursor.observe(None, Some("2024-03-05T08:00:00.000Z"), Some("row-d"));
+        assert_eq!(cursor.last_id.as_deref(), Some("row-d"));
+    }
+
+    #[test]
+    fn test_cursor_staleness() {
+        let mut cursor = DatasetCursor::default();
+        assert!(cursor.is_stale(Some(1_717_200_000)));
+
+        cursor.rows_updated_at = Some(1_717_200_000);
+        assert!(!cursor.is_stale(Some(1_717_200_000)));
+        assert!(cursor.is_stale(Some(1_717_300_000)));
+        assert!(cursor.is_stale(None));
+    }
+
+    #[tokio::test]
+    async fn test_state_roundtrip() {
+        let path = std::env::temp_dir().join(format!("sync_state_{}.json", uuid::Uuid::new_v4()));
+
+        assert!(SyncState::load(&path).await.unwrap().datasets.is_empty());
+
+        let mut state = SyncState::default();
+        let mut cursor = DatasetCursor::default();
+        cursor.observe(Some("2024-03-01T00:00:00.000"), None, None);
+        state.set_cursor("jbjy-vk9h", cursor);
+        state.save(&path).await.unwrap();
+
+        let loaded = SyncState::load(&path).await.unwrap();
+        assert_eq!(
+            loaded.cursor("jbjy-vk9h").unwrap().last_sync_date.as_deref(),
+            Some("2024-03-01T00:00:00.000")
+        );
+        tokio::fs::remove_file(&path).await.ok();
+    }
+}
@@ -0,0 +1,97 @@
+//! End-to-end pipeline run against the committed Socrata fixtures
+//! (`fixtures/socrata`), with no network access.
+//!
+//! The binary writes its reports to `../frontend/public`, so each run happens
+//! inside a throwaway copy of that layout.
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+struct Workspace {
+    root: PathBuf,
+}
+
+impl Workspace {
+    fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("veeduria_{}_{}", name, std::process::id()));
+        std::fs::remove_dir_all(&root).ok();
+        std::fs::create_dir_all(root.join("backend")).unwrap();
+        std::fs::create_dir_all(root.join("frontend/public")).unwrap();
+        Self { root }
+    }
+
+    fn run(&self, args: &[&str]) {
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let status = Command::new(env!("CARGO_BIN_EXE_backend"))
+            .args(args)
+            .current_dir(self.root.join("backend"))
+            .env("SOCRATA_REPLAY", "replay")
+            .env("SOCRATA_FIXTURES_DIR", manifest.join("fixtures/socrata"))
+            .env("RISK_RULES_PATH", manifest.join("risk_rules.toml"))
+            .env_remove("SOCRATA_APP_TOKEN")
+            .env_remove("HF_TOKEN")
+            .status()
+            .expect("failed to start the backend binary");
+        assert!(status.success(), "pipeline failed with {}", status);
+    }
+
+    fn report(&self, name: &str) -> Value {
+        let path = self.root.join("frontend/public").join(name);
+        let raw = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        serde_json::from_str(&raw).unwrap()
+    }
+
+    fn data(&self, name: &str) -> PathBuf {
+        self.root.join("backend/data").join(name)
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.root).ok();
+    }
+}
+
+#[test]
+fn test_replay_full_load_then_unchanged_run() {
+    let workspace = Workspace::new("replay");
+
+    workspace.run(&["--full-resync"]);
+
+    let report = workspace.report("daily_report.json");
+    let report = report.as_array().unwrap();
+    assert_eq!(report.len(), 10);
+    assert_eq!(report[0]["id_contrato"], "CO1.PCCNTR.6412000");
+    assert!(report.iter().all(|c| c["contractor_uuid"].is_string()));
+
+    let stats = workspace.report("stats.json");
+    assert_eq!(stats["total_contracts"], 10);
+    assert_eq!(stats["delta_contracts"], 10);
+    assert_eq!(stats["rule_counts"]["single_bidder"], 1);
+    assert_eq!(stats["rule_counts"]["short_timeline"], 2);
+    assert_eq!(stats["national"]["contratos"], 10);
+
+    // Local and server totals agree, so the only alerts would be real regressions
+    let quality = workspace.report("data_quality.json");
+    assert_eq!(quality["total_records"], 10);
+    assert_eq!(quality["national_totals"]["contratos_servidor"], 10);
+    assert_eq!(quality["alerts"], Value::Array(Vec::new()));
+
+    assert!(workspace.data("contratos").is_dir());
+    assert!(workspace.data("procesos").is_dir());
+    assert!(workspace.data("golden_records").is_dir());
+    assert!(workspace.data("sync_state.json").is_file());
+
+    // Same metadata timestamp: nothing is fetched and the reports are kept
+    let rule_counts = stats["rule_counts"].clone();
+    workspace.run(&[]);
+
+    let stats = workspace.report("stats.json");
+    assert_eq!(stats["total_contracts"], 10);
+    assert_eq!(stats["delta_contracts"], 0);
+    // Flags are counted over the snapshot, not the (empty) delta
+    assert_eq!(stats["rule_counts"], rule_counts);
+    assert_eq!(workspace.report("daily_report.json").as_array().unwrap().len(), 10);
+    assert_eq!(workspace.report("data_quality.json")["total_records"], 10);
+}